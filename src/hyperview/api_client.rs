@@ -0,0 +1,811 @@
+use log::{debug, warn};
+use reqwest::{
+    Method, Request, RequestBuilder, Response, StatusCode,
+    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE, HeaderMap},
+};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::sync::Mutex;
+
+use super::{
+    app_errors::ApiError,
+    auth::AuthToken,
+    config_data::AppConfig,
+    http_capture::{self, HttpCapture},
+    http_client::api_http_client,
+    http_replay::{HttpReplay, REPLAY_INSTANCE_URL},
+    rate_limit::{RateLimitSummary, RateLimiter},
+    retry::{RetryPolicy, is_retryable_status, is_transient_error, retry_after},
+};
+
+/// The one way API calls reach a Hyperview instance. Owns the profile, the HTTP client and the
+/// access token, and gives every request the same treatment:
+///
+/// - paths (an `api_constants` prefix plus any suffix or query string) are joined to
+///   `instance_url`;
+/// - the token is refreshed before it expires, and a request the server rejects with
+///   `401 Unauthorized` is retried once with a freshly fetched token;
+/// - `Accept` and, for requests with a body, `Content-Type` are set to `application/json`;
+/// - GET, PUT and DELETE requests that fail with 429, a 5xx gateway or server error, or a
+///   connection error or timeout are retried with backoff according to the profile's
+///   [`RetryPolicy`]. POSTs usually create something and are only retried when sent with
+///   [`HyperviewClient::post_idempotent`];
+/// - if the profile sets `rate_limit_per_sec`, every request, retries included, first waits
+///   its turn at the shared [`RateLimiter`];
+/// - when `--capture-http` is set, every attempt is recorded to the capture file; a client made
+///   with [`HyperviewClient::for_replay`] answers requests from such a file instead, without
+///   waiting between retries;
+/// - non-success statuses are turned into an [`ApiError`] that keeps the server's explanation.
+///
+/// The token sits behind a lock so the client can be shared; when several requests hit a 401 at
+/// once only the first one fetches a new token.
+pub struct HyperviewClient {
+    config: AppConfig,
+    http: reqwest::Client,
+    retry: RetryPolicy,
+    rate_limiter: Option<RateLimiter>,
+    capture: Option<&'static HttpCapture>,
+    replay: Option<HttpReplay>,
+    auth_token: Mutex<AuthToken>,
+}
+
+impl HyperviewClient {
+    pub fn new(config: AppConfig, auth_token: AuthToken) -> color_eyre::Result<Self> {
+        let http = api_http_client(&config)?;
+        let retry = RetryPolicy::from_config(&config);
+        let rate_limiter = RateLimiter::from_config(&config);
+
+        Ok(Self {
+            config,
+            http,
+            retry,
+            rate_limiter,
+            capture: http_capture::active(),
+            replay: None,
+            auth_token: Mutex::new(auth_token),
+        })
+    }
+
+    /// A client that sends nothing over the network and needs no credentials: every request is
+    /// answered from `replay`, and a request it has no answer for fails.
+    pub fn for_replay(replay: HttpReplay) -> Self {
+        Self {
+            config: AppConfig {
+                instance_url: REPLAY_INSTANCE_URL.to_string(),
+                ..Default::default()
+            },
+            http: reqwest::Client::new(),
+            retry: RetryPolicy::default(),
+            rate_limiter: None,
+            capture: None,
+            replay: Some(replay),
+            auth_token: Mutex::new(AuthToken::for_replay()),
+        }
+    }
+
+    /// Requests sent and time spent waiting on the rate limit so far, or `None` when the profile
+    /// sets no rate limit.
+    pub fn rate_limit_summary(&self) -> Option<RateLimitSummary> {
+        self.rate_limiter.as_ref().map(RateLimiter::summary)
+    }
+
+    /// The full URL for `path` on this instance.
+    pub fn url(&self, path: &str) -> String {
+        format!("{}{path}", self.config.instance_url)
+    }
+
+    pub async fn get<T: DeserializeOwned>(&self, path: &str) -> color_eyre::Result<T> {
+        let resp = self.send_async(Method::GET, path, true, |r| r).await?;
+
+        Ok(resp.json::<T>().await?)
+    }
+
+    pub async fn get_with_query<T, Q>(&self, path: &str, query: &Q) -> color_eyre::Result<T>
+    where
+        T: DeserializeOwned,
+        Q: Serialize + ?Sized,
+    {
+        let resp = self
+            .send_async(Method::GET, path, true, |r| r.query(query))
+            .await?;
+
+        Ok(resp.json::<T>().await?)
+    }
+
+    pub async fn put<B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> color_eyre::Result<Response> {
+        self.send_json_async(Method::PUT, path, true, body).await
+    }
+
+    pub async fn post<B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> color_eyre::Result<Response> {
+        self.send_json_async(Method::POST, path, false, body).await
+    }
+
+    /// A POST that changes nothing on the server, such as a search, and so is retried like a
+    /// GET.
+    pub async fn post_idempotent<B: Serialize + ?Sized>(
+        &self,
+        path: &str,
+        body: &B,
+    ) -> color_eyre::Result<Response> {
+        self.send_json_async(Method::POST, path, true, body).await
+    }
+
+    pub async fn delete(&self, path: &str) -> color_eyre::Result<Response> {
+        self.send_async(Method::DELETE, path, true, |r| r).await
+    }
+
+    async fn send_json_async<B: Serialize + ?Sized>(
+        &self,
+        method: Method,
+        path: &str,
+        retryable: bool,
+        body: &B,
+    ) -> color_eyre::Result<Response> {
+        let body = serde_json::to_vec(body)?;
+
+        self.send_async(method, path, retryable, |r| {
+            r.header(CONTENT_TYPE, "application/json")
+                .body(body.clone())
+        })
+        .await
+    }
+
+    /// Sends a request built by `build` on top of the common method, URL and headers. `build` is
+    /// called again for every retry, so it must not consume anything. Transient failures are
+    /// only retried when `retryable` is set; the single retry after a 401 always happens.
+    async fn send_async(
+        &self,
+        method: Method,
+        path: &str,
+        retryable: bool,
+        build: impl Fn(RequestBuilder) -> RequestBuilder,
+    ) -> color_eyre::Result<Response> {
+        let url = self.url(path);
+        debug!("{method} {url}");
+
+        let send = async |auth_header: String| -> color_eyre::Result<reqwest::Result<Response>> {
+            let request = build(
+                self.http
+                    .request(method.clone(), &url)
+                    .header(AUTHORIZATION, auth_header)
+                    .header(ACCEPT, "application/json"),
+            )
+            .build()?;
+            self.execute_async(request).await
+        };
+
+        let mut attempt = 1;
+        let mut token_replaced = false;
+
+        loop {
+            if let Some(rate_limiter) = &self.rate_limiter {
+                rate_limiter.acquire_async().await;
+            }
+
+            let auth_header = self.auth_header_async().await?;
+
+            let (delay, reason) = match send(auth_header.clone()).await? {
+                Ok(resp) if resp.status() == StatusCode::UNAUTHORIZED && !token_replaced => {
+                    debug!("Request was unauthorized; refreshing token and retrying once");
+                    self.replace_auth_header_async(&auth_header).await?;
+                    token_replaced = true;
+                    continue;
+                }
+                Ok(resp) if retryable && is_retryable_status(resp.status()) => {
+                    match self.retry.delay(attempt, retry_after(&resp)) {
+                        Some(delay) => (delay, resp.status().to_string()),
+                        None => return Ok(error_for_status_async(&method, resp).await?),
+                    }
+                }
+                Err(e) if retryable && is_transient_error(&e) => {
+                    match self.retry.delay(attempt, None) {
+                        Some(delay) => (delay, e.to_string()),
+                        None => return Err(e.into()),
+                    }
+                }
+                result => return Ok(error_for_status_async(&method, result?).await?),
+            };
+
+            warn!(
+                "{method} {url} failed ({reason}); retrying in {delay:?} (attempt {} of {})",
+                attempt + 1,
+                self.retry.max_attempts
+            );
+            if self.replay.is_none() {
+                tokio::time::sleep(delay).await;
+            }
+            attempt += 1;
+        }
+    }
+
+    /// Sends `request`, or answers it from the replay file. The outer error is a request the
+    /// replay file has no answer for, which is never retried.
+    async fn execute_async(
+        &self,
+        request: Request,
+    ) -> color_eyre::Result<reqwest::Result<Response>> {
+        if let Some(replay) = &self.replay {
+            return Ok(Ok(replay.respond(&request)?));
+        }
+
+        Ok(match self.capture {
+            Some(capture) => capture.execute_async(&self.http, request).await,
+            None => self.http.execute(request).await,
+        })
+    }
+
+    /// The current `Authorization` header, refreshed first if the token is about to expire.
+    async fn auth_header_async(&self) -> color_eyre::Result<String> {
+        let mut auth_token = self.auth_token.lock().await;
+        auth_token.refresh_if_needed_async(&self.config).await?;
+
+        Ok(auth_token.header.clone())
+    }
+
+    /// Fetches a new token after `rejected` was refused, unless another request already
+    /// replaced it while this one was in flight.
+    async fn replace_auth_header_async(&self, rejected: &str) -> color_eyre::Result<String> {
+        let mut auth_token = self.auth_token.lock().await;
+        // A replayed 401 is answered by the next captured attempt; there is no token to fetch.
+        if auth_token.header == rejected && self.replay.is_none() {
+            auth_token.force_refresh_async(&self.config).await?;
+        }
+
+        Ok(auth_token.header.clone())
+    }
+
+    /// Test-only constructor: a client for `config` whose token is `Bearer test_token` and does
+    /// not expire during the test. Retries follow the profile but do not wait.
+    #[cfg(test)]
+    pub fn for_test(config: AppConfig) -> Self {
+        let retry = RetryPolicy {
+            base_delay: std::time::Duration::ZERO,
+            ..RetryPolicy::from_config(&config)
+        };
+
+        Self {
+            rate_limiter: RateLimiter::from_config(&config),
+            config,
+            http: reqwest::Client::new(),
+            retry,
+            capture: None,
+            replay: None,
+            auth_token: Mutex::new(AuthToken::for_test(
+                "Bearer test_token",
+                std::time::Duration::from_hours(1),
+            )),
+        }
+    }
+
+    /// Test-only: records this client's requests to `path`, as `--capture-http` does.
+    #[cfg(test)]
+    pub fn with_capture(mut self, path: &std::path::Path) -> Self {
+        self.capture = Some(Box::leak(Box::new(HttpCapture::create(path).unwrap())));
+        self
+    }
+}
+
+/// Headers a server or gateway may use to identify a request, in order of preference.
+const REQUEST_ID_HEADERS: [&str; 3] = ["x-request-id", "x-correlation-id", "request-id"];
+
+/// `resp` if its status is a success, otherwise an [`ApiError`] holding the error body.
+async fn error_for_status_async(method: &Method, resp: Response) -> Result<Response, ApiError> {
+    let status = resp.status();
+    if !status.is_client_error() && !status.is_server_error() {
+        return Ok(resp);
+    }
+
+    let url = resp.url().clone();
+    let request_id = request_id(resp.headers());
+    let body = resp.text().await.unwrap_or_default();
+
+    Err(ApiError::new(
+        method.clone(),
+        url,
+        status,
+        request_id,
+        &body,
+    ))
+}
+
+fn request_id(headers: &HeaderMap) -> Option<String> {
+    REQUEST_ID_HEADERS
+        .iter()
+        .find_map(|name| headers.get(*name)?.to_str().ok())
+        .map(str::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::{Value, json};
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::{Duration, Instant};
+
+    fn token_config(auth_server: &MockServer, instance_server: &MockServer) -> AppConfig {
+        AppConfig {
+            client_id: "client_id".to_string(),
+            client_secret: "client_secret".to_string(),
+            scope: "scope".to_string(),
+            auth_url: "https://example.test/auth".to_string(),
+            token_url: format!("http://{}/token", auth_server.address()),
+            instance_url: format!("http://{}", instance_server.address()),
+            ..Default::default()
+        }
+    }
+
+    fn token_mock<'a>(server: &'a MockServer, access_token: &str) -> httpmock::Mock<'a> {
+        server.mock(|when, then| {
+            when.method(POST).path("/token");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({
+                    "access_token": access_token,
+                    "token_type": "bearer",
+                    "expires_in": 3600
+                }));
+        })
+    }
+
+    #[tokio::test]
+    async fn test_get_joins_instance_url_and_sets_headers() {
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/asset/assets/1")
+                .query_param("skip", "0")
+                .header("authorization", "Bearer test_token")
+                .header("accept", "application/json");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"name": "All"}));
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        let resp: Value = client
+            .get_with_query("/api/asset/assets/1", &[("skip", 0)])
+            .await
+            .unwrap();
+
+        m.assert();
+        assert_eq!(resp["name"], "All");
+    }
+
+    #[tokio::test]
+    async fn test_put_sends_json_body_with_content_type() {
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(PUT)
+                .path("/api/asset/sensors")
+                .header("content-type", "application/json")
+                .json_body(json!({"id": "sensor-1"}));
+            then.status(204);
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        client
+            .put("/api/asset/sensors", &json!({"id": "sensor-1"}))
+            .await
+            .unwrap();
+
+        m.assert();
+    }
+
+    #[tokio::test]
+    async fn test_error_status_is_error() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(DELETE).path("/api/thing");
+            then.status(404);
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        let err = client.delete("/api/thing").await.unwrap_err();
+
+        assert_eq!(
+            err.downcast_ref::<ApiError>().map(|e| e.status),
+            Some(StatusCode::NOT_FOUND)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_unauthorized_request_is_retried_once_with_new_token() {
+        let api_server = MockServer::start();
+        let stale_mock = api_server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/resource")
+                .header("authorization", "Bearer stale_token");
+            then.status(401);
+        });
+        let fresh_mock = api_server.mock(|when, then| {
+            when.method(POST)
+                .path("/api/resource")
+                .header("authorization", "Bearer fresh_token")
+                .body_includes("payload");
+            then.status(200);
+        });
+        let auth_server = MockServer::start();
+        let auth_mock = token_mock(&auth_server, "fresh_token");
+
+        let client = HyperviewClient::new(
+            token_config(&auth_server, &api_server),
+            AuthToken::for_test("Bearer stale_token", Duration::from_mins(5)),
+        )
+        .unwrap();
+
+        client
+            .post("/api/resource", &json!({"value": "payload"}))
+            .await
+            .unwrap();
+        client
+            .post("/api/resource", &json!({"value": "payload"}))
+            .await
+            .unwrap();
+
+        stale_mock.assert_calls(1);
+        fresh_mock.assert_calls(2);
+        auth_mock.assert_calls(1);
+    }
+
+    #[tokio::test]
+    async fn test_persistent_unauthorized_is_not_retried_twice() {
+        let api_server = MockServer::start();
+        let api_mock = api_server.mock(|when, then| {
+            when.method(GET).path("/api/resource");
+            then.status(401);
+        });
+        let auth_server = MockServer::start();
+        let auth_mock = token_mock(&auth_server, "also_rejected");
+
+        let client = HyperviewClient::new(
+            token_config(&auth_server, &api_server),
+            AuthToken::for_test("Bearer stale_token", Duration::from_mins(5)),
+        )
+        .unwrap();
+
+        let err = client.get::<Value>("/api/resource").await.unwrap_err();
+
+        api_mock.assert_calls(2);
+        auth_mock.assert_calls(1);
+        assert_eq!(
+            err.downcast_ref::<ApiError>().map(|e| e.status),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_expired_token_is_refreshed_before_request() {
+        let api_server = MockServer::start();
+        let api_mock = api_server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/resource")
+                .header("authorization", "Bearer renewed_token");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!([]));
+        });
+        let auth_server = MockServer::start();
+        let auth_mock = token_mock(&auth_server, "renewed_token");
+
+        let client = HyperviewClient::new(
+            token_config(&auth_server, &api_server),
+            AuthToken::for_test("Bearer expired_token", Duration::ZERO),
+        )
+        .unwrap();
+
+        let resp: Vec<Value> = client.get("/api/resource").await.unwrap();
+
+        api_mock.assert();
+        auth_mock.assert();
+        assert!(resp.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_get_honors_retry_after_on_503_then_succeeds() {
+        let server = MockServer::start();
+        let seen = Arc::new(AtomicUsize::new(0));
+        let counter = seen.clone();
+        let unavailable_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path("/api/resource")
+                .is_true(move |_| counter.fetch_add(1, Ordering::SeqCst) == 0);
+            then.status(503).header("Retry-After", "1");
+        });
+        let ok_mock = server.mock(|when, then| {
+            when.method(GET).path("/api/resource");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!([]));
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        let started = Instant::now();
+        let resp: Vec<Value> = client.get("/api/resource").await.unwrap();
+
+        assert!(started.elapsed() >= Duration::from_secs(1));
+        unavailable_mock.assert_calls(1);
+        ok_mock.assert_calls(1);
+        assert!(resp.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retry_after_beyond_max_delay_is_not_waited_for() {
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/resource");
+            then.status(429).header("Retry-After", "120");
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        let err = client.get::<Value>("/api/resource").await.unwrap_err();
+
+        m.assert_calls(1);
+        assert_eq!(
+            err.downcast_ref::<ApiError>().map(|e| e.status),
+            Some(StatusCode::TOO_MANY_REQUESTS)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_post_is_not_retried_unless_marked_idempotent() {
+        let server = MockServer::start();
+        let create_mock = server.mock(|when, then| {
+            when.method(POST).path("/api/create");
+            then.status(502);
+        });
+        let search_mock = server.mock(|when, then| {
+            when.method(POST).path("/api/search");
+            then.status(502);
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            max_attempts: Some(3),
+            ..Default::default()
+        });
+
+        assert!(client.post("/api/create", &json!({})).await.is_err());
+        assert!(
+            client
+                .post_idempotent("/api/search", &json!({}))
+                .await
+                .is_err()
+        );
+
+        create_mock.assert_calls(1);
+        search_mock.assert_calls(3);
+    }
+
+    #[tokio::test]
+    async fn test_timed_out_put_is_retried() {
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(PUT).path("/api/resource");
+            then.status(200).delay(Duration::from_secs(2));
+        });
+
+        let client = HyperviewClient::new(
+            AppConfig {
+                instance_url: format!("http://{}", server.address()),
+                read_timeout_secs: Some(1),
+                max_attempts: Some(2),
+                retry_base_delay_ms: Some(0),
+                ..Default::default()
+            },
+            AuthToken::for_test("Bearer test_token", Duration::from_hours(1)),
+        )
+        .unwrap();
+
+        let err = client.put("/api/resource", &json!({})).await.unwrap_err();
+
+        m.assert_calls(2);
+        assert!(
+            err.downcast_ref::<reqwest::Error>()
+                .is_some_and(reqwest::Error::is_timeout)
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_status_carries_problem_details_and_request_id() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(PUT).path("/api/asset/locations/1");
+            then.status(400)
+                .header("content-type", "application/problem+json")
+                .header("x-request-id", "req-42")
+                .json_body(json!({
+                    "type": "https://tools.ietf.org/html/rfc9110#section-15.5.1",
+                    "title": "One or more validation errors occurred.",
+                    "status": 400,
+                    "traceId": "00-abc-01",
+                    "errors": {
+                        "ParentId": ["The parent location does not exist."],
+                        "Name": ["The Name field is required.", "Name is too short."]
+                    }
+                }));
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        let err = client
+            .put("/api/asset/locations/1", &json!({}))
+            .await
+            .unwrap_err();
+        let api_error = err.downcast_ref::<ApiError>().unwrap();
+
+        assert_eq!(api_error.status, StatusCode::BAD_REQUEST);
+        assert_eq!(api_error.method, reqwest::Method::PUT);
+        assert_eq!(api_error.url.path(), "/api/asset/locations/1");
+        assert_eq!(api_error.request_id.as_deref(), Some("req-42"));
+        assert_eq!(api_error.problem.as_ref().unwrap().errors["Name"].len(), 2);
+        assert_eq!(
+            err.to_string(),
+            format!(
+                "PUT http://{}/api/asset/locations/1 failed with 400 Bad Request: \
+                 One or more validation errors occurred.; Name: The Name field is required.; \
+                 Name: Name is too short.; ParentId: The parent location does not exist. \
+                 (request id req-42)",
+                server.address()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn test_error_status_keeps_plain_text_body_and_trace_id() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(DELETE).path("/api/thing/1");
+            then.status(409)
+                .body("  Asset is still referenced by 3 sensors\n");
+        });
+        server.mock(|when, then| {
+            when.method(DELETE).path("/api/thing/2");
+            then.status(404)
+                .json_body(json!({"detail": "No such thing", "traceId": "00-def-01"}));
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        let err = client.delete("/api/thing/1").await.unwrap_err();
+        let api_error = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(api_error.problem, None);
+        assert_eq!(
+            api_error.reason().as_deref(),
+            Some("Asset is still referenced by 3 sensors")
+        );
+        assert_eq!(api_error.request_id, None);
+
+        let err = client.delete("/api/thing/2").await.unwrap_err();
+        let api_error = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(api_error.reason().as_deref(), Some("No such thing"));
+        assert_eq!(api_error.request_id.as_deref(), Some("00-def-01"));
+    }
+
+    #[tokio::test]
+    async fn test_error_status_keeps_json_body_without_a_reason() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(POST).path("/api/thing");
+            then.status(400)
+                .json_body(json!({"errorMessage": "Rack is full", "traceId": "00-abc-01"}));
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        let err = client.post("/api/thing", &json!({})).await.unwrap_err();
+        let api_error = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(api_error.problem, None);
+        assert_eq!(api_error.request_id.as_deref(), Some("00-abc-01"));
+        assert!(
+            api_error
+                .to_string()
+                .contains(r#""errorMessage":"Rack is full""#),
+            "{api_error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_capture_records_every_attempt() {
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(GET).path("/api/resource");
+            then.status(500).body("upstream down");
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.jsonl");
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            max_attempts: Some(2),
+            ..Default::default()
+        })
+        .with_capture(&path);
+
+        assert!(client.get::<Value>("/api/resource").await.is_err());
+
+        m.assert_calls(2);
+        let capture = std::fs::read_to_string(&path).unwrap();
+        let exchanges: Vec<Value> = capture
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(exchanges.len(), 2);
+        for exchange in &exchanges {
+            assert_eq!(exchange["status"], 500);
+            assert_eq!(exchange["response_body"], "upstream down");
+            assert_eq!(
+                exchange["request_headers"]["authorization"],
+                http_capture::REDACTED
+            );
+        }
+        assert!(!capture.contains("test_token"));
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_spaces_requests_and_reports_wait() {
+        let server = MockServer::start();
+        let m = server.mock(|when, then| {
+            when.method(POST).path("/api/asset/search");
+            then.status(200);
+        });
+
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            rate_limit_per_sec: Some(20.0),
+            rate_limit_burst: Some(1),
+            ..Default::default()
+        });
+
+        let started = Instant::now();
+        for _ in 0..3 {
+            client
+                .post_idempotent("/api/asset/search", &json!({}))
+                .await
+                .unwrap();
+        }
+
+        m.assert_calls(3);
+        assert!(started.elapsed() >= Duration::from_millis(90));
+        let summary = client.rate_limit_summary().unwrap();
+        assert_eq!(summary.requests, 3);
+        assert!(summary.waited >= Duration::from_millis(90));
+    }
+}
@@ -1,3 +1,5 @@
+/// Id of the root "All" location, which exists on every Hyperview instance.
+pub const ALL_LOCATION_ASSET_ID: &str = "11223344-5566-7788-99aa-bbccddeeff00";
 pub const ASSET_ALARM_EVENT_BULK_CLOSE_API_PREFIX: &str = "/api/asset/alarmEvents/bulkClose";
 pub const ASSET_ALARM_EVENT_BULK_ACKNOWLEDGE_API_PREFIX: &str =
     "/api/asset/alarmEvents/bulkAcknowledgementStates";
@@ -1,13 +1,17 @@
+use reqwest::{Method, StatusCode, Url};
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
 use thiserror::Error;
 
+/// Longest stretch of a non-JSON error body kept in an [`ApiError`].
+const MAX_ERROR_BODY_CHARS: usize = 500;
+
 #[derive(Debug, Error)]
 pub enum AppError {
-    #[error("File already exists, can't over write")]
+    #[error("File already exists, can't over write. Use --overwrite or --append")]
     FileExists,
 
-    #[error("Must provide an output filename")]
-    NoOutputFilename,
-
     #[error("Asset not found")]
     AssetNotFound,
 
@@ -29,4 +33,231 @@ pub enum AppError {
         "Bulk operation completed with {failed} failure(s) out of {total} row(s); see log for details."
     )]
     BulkOperationFailures { failed: usize, total: usize },
+
+    #[error(
+        "Config profile {0:?} not found; run `config list-profiles` to see the available profiles"
+    )]
+    ProfileNotFound(String),
+
+    #[error(
+        "Missing config value {field}; set it in the config file or the {env_var} environment variable"
+    )]
+    MissingConfigValue { field: String, env_var: String },
+
+    #[error("Only one of {0}, {0}_file and {0}_command may be set")]
+    ConflictingCredentialSources(String),
+
+    #[error("Unable to read {name}_file {path}: {reason}")]
+    CredentialFileUnreadable {
+        name: String,
+        path: String,
+        reason: String,
+    },
+
+    #[error("{name}_command failed: {reason}")]
+    CredentialCommandFailed { name: String, reason: String },
+
+    #[error("{0} resolved to an empty value")]
+    EmptyCredential(String),
+
+    #[error("Config file {0} already exists; use --force to overwrite it")]
+    ConfigFileExists(String),
+
+    #[error("No config file path; pass --config or set HVCLI_CONFIG")]
+    NoConfigPath,
+
+    #[error("Invalid instance URL {0:?}; expected e.g. https://example.hyperviewhq.com")]
+    InvalidInstanceUrl(String),
+
+    #[error("Configuration validation failed; see the failed step above")]
+    ConfigValidationFailed,
+
+    #[error("{0} CSV row(s) would be rejected; see the lines above")]
+    InvalidCsvRows(usize),
+
+    #[error(
+        "Profile {0} has no client secret; set client_secret (or HVCLI_CLIENT_SECRET), or run `hvcli login`"
+    )]
+    MissingClientSecret(String),
+
+    #[error("Login failed: {0}")]
+    LoginFailed(String),
+
+    #[error(
+        "The login session for profile {0} has expired or was revoked; run `hvcli login` again"
+    )]
+    LoginExpired(String),
+
+    #[error("Unable to load CA certificates from {path}: {reason}")]
+    CaFileUnreadable { path: String, reason: String },
+
+    #[error("Unable to create HTTP capture file {path}: {reason}")]
+    CaptureFileUnwritable { path: String, reason: String },
+
+    #[error("Unable to read replay file {path}: {reason}")]
+    ReplayFileUnreadable { path: String, reason: String },
+
+    #[error("No captured response in the replay file matches {method} {url}")]
+    ReplayUnmatched { method: String, url: String },
+
+    #[error("Unknown column(s) {unknown}. Valid columns are: {valid}")]
+    UnknownColumns { unknown: String, valid: String },
+
+    #[error("Records of this command have no named fields to pick columns from")]
+    NoColumns,
+
+    #[error("Invalid --format {template:?}: {reason}")]
+    InvalidFormat { template: String, reason: String },
+
+    #[error("Unknown field(s) {unknown} in --format. Valid fields are: {valid}")]
+    UnknownFormatFields { unknown: String, valid: String },
+
+    #[error("--format only applies to record output")]
+    FormatNeedsRecordOutput,
+
+    #[error("Invalid --filter {expression:?}: {reason}")]
+    InvalidFilter { expression: String, reason: String },
+
+    #[error(
+        "Invalid --sort {value:?}. Use FIELD, FIELD:asc or FIELD:desc, where FIELD is one of: {valid}"
+    )]
+    InvalidSort { value: String, valid: String },
+
+    #[error(
+        "The search API cannot sort by the --sort fields. Sort by another field, or add --all to fetch every match and sort them here"
+    )]
+    SortUnsupported,
+
+    #[error("Can't append: the file has columns {existing} but the new records have {new}")]
+    AppendColumnsDiffer { existing: String, new: String },
+
+    #[error("--append is not supported for {0} output")]
+    AppendUnsupported(String),
+
+    #[error("Invalid proxy URL {url:?}: {reason}")]
+    InvalidProxyUrl { url: String, reason: String },
+
+    #[error("Cannot derive the device authorization URL from token_url {0:?}; set device_auth_url")]
+    NoDeviceAuthUrl(String),
+}
+
+/// A request the Hyperview API answered with an error status. Carries what the server said about
+/// why, so that a rejected row in a bulk import can be fixed without re-running at `trace` level.
+#[derive(Debug, Error)]
+pub struct ApiError {
+    pub status: StatusCode,
+    pub method: Method,
+    pub url: Url,
+    /// The body, if it was JSON with a reason in it: RFC 7807 problem details, ASP.NET validation
+    /// errors, or an object with a `message`.
+    pub problem: Option<ProblemDetails>,
+    /// The body, if it was anything else, shortened to a readable length.
+    pub body: Option<String>,
+    /// Request or correlation id the server returned, to quote when asking Hyperview support
+    /// about the failure.
+    pub request_id: Option<String>,
+}
+
+impl ApiError {
+    /// Builds the error from the failed response's parts, parsing `body` as problem details when
+    /// it is JSON that says why. Any other body, JSON or not, is kept as it is.
+    pub fn new(
+        method: Method,
+        url: Url,
+        status: StatusCode,
+        request_id: Option<String>,
+        body: &str,
+    ) -> Self {
+        let parsed = serde_json::from_str::<ProblemDetails>(body).ok();
+        let request_id = request_id.or_else(|| parsed.as_ref().and_then(|p| p.trace_id.clone()));
+
+        // Every field of a problem document is optional, so any JSON object parses as one; it
+        // only stands in for the body if it holds a reason.
+        let (problem, body) = match parsed.filter(|p| p.reason().is_some()) {
+            Some(problem) => (Some(problem), None),
+            None => {
+                let body = body.trim();
+                let body =
+                    (!body.is_empty()).then(|| body.chars().take(MAX_ERROR_BODY_CHARS).collect());
+                (None, body)
+            }
+        };
+
+        Self {
+            status,
+            method,
+            url,
+            request_id,
+            problem,
+            body,
+        }
+    }
+
+    /// The server's explanation of the failure, if it gave one.
+    pub fn reason(&self) -> Option<String> {
+        match &self.problem {
+            Some(problem) => problem.reason(),
+            None => self.body.clone(),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {} failed with {}",
+            self.method, self.url, self.status
+        )?;
+        if let Some(reason) = self.reason() {
+            write!(f, ": {reason}")?;
+        }
+        if let Some(request_id) = &self.request_id {
+            write!(f, " (request id {request_id})")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// An error body as the Hyperview API returns it. Every field is optional, so any JSON object
+/// parses; fields the server did not send are empty.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProblemDetails {
+    #[serde(rename = "type")]
+    pub problem_type: Option<String>,
+    pub title: Option<String>,
+    pub status: Option<u16>,
+    pub detail: Option<String>,
+    pub instance: Option<String>,
+    pub trace_id: Option<String>,
+    /// Validation messages by field name.
+    #[serde(default)]
+    pub errors: BTreeMap<String, Vec<String>>,
+    pub message: Option<String>,
+}
+
+impl ProblemDetails {
+    /// The detail (or, failing that, the title or message), followed by each validation error.
+    pub fn reason(&self) -> Option<String> {
+        let summary = self
+            .detail
+            .as_ref()
+            .or(self.title.as_ref())
+            .or(self.message.as_ref())
+            .map(|s| s.trim().to_string());
+        let errors = self.errors.iter().flat_map(|(field, messages)| {
+            messages.iter().map(move |message| {
+                if field.is_empty() {
+                    message.clone()
+                } else {
+                    format!("{field}: {message}")
+                }
+            })
+        });
+
+        let parts: Vec<String> = summary.into_iter().chain(errors).collect();
+        (!parts.is_empty()).then(|| parts.join("; "))
+    }
 }
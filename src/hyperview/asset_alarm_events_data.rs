@@ -1,3 +1,4 @@
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use serde_with::{DefaultOnNull, serde_as};
 use std::fmt;
@@ -95,3 +96,15 @@ pub struct AlarmListResponse {
     #[serde(alias = "totalCount")]
     total_count: i64,
 }
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+pub enum ManageActionOptions {
+    Acknowledge,
+    Close,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+pub enum AlarmEventFilterOptions {
+    Unacknowledged,
+    Active,
+}
@@ -1,36 +1,22 @@
 use log::{debug, error};
-use reqwest::{
-    Client,
-    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-};
 use serde_json::{Map, Value, json};
 
-use crate::retry_on_unauthorized_async;
-
 use super::{
+    api_client::HyperviewClient,
     api_constants::{
         ASSET_ALARM_EVENT_BULK_ACKNOWLEDGE_API_PREFIX, ASSET_ALARM_EVENT_BULK_CLOSE_API_PREFIX,
         ASSET_ALARM_EVENT_LIST_API_PREFIX, BULK_ACTION_BATCH_SIZE,
     },
     asset_alarm_events_data::{AlarmEventDto, AlarmListResponse},
-    auth::AuthToken,
-    cli_data::{AlarmEventFilterOptions, AppConfig, ManageActionOptions},
+    asset_alarm_events_data::{AlarmEventFilterOptions, ManageActionOptions},
 };
 
 pub async fn list_alarm_events_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     skip: u32,
     limit: u32,
     alarm_filter_option: AlarmEventFilterOptions,
 ) -> color_eyre::Result<AlarmListResponse> {
-    let target_url = format!(
-        "{}{}",
-        config.instance_url, ASSET_ALARM_EVENT_LIST_API_PREFIX
-    );
-    debug!("Request URL: {target_url}");
-
     let mut query_params = Map::new();
 
     query_params.insert("skip".to_string(), Value::Number(skip.into()));
@@ -57,41 +43,24 @@ pub async fn list_alarm_events_async(
         serde_json::to_string(&query_params).unwrap()
     );
 
-    let resp = req
-        .get(target_url)
-        .query(&query_params)
-        .header(AUTHORIZATION, auth_header)
-        .header(CONTENT_TYPE, "application/json")
-        .header(ACCEPT, "application/json")
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<AlarmListResponse>()
-        .await?;
-
-    Ok(resp)
+    client
+        .get_with_query(ASSET_ALARM_EVENT_LIST_API_PREFIX, &query_params)
+        .await
 }
 
 async fn close_alarm_batch_async(
-    req: &Client,
-    auth_header: &String,
-    target_url: &str,
+    client: &HyperviewClient,
     batch: &[String],
 ) -> color_eyre::Result<()> {
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(batch)
-        .send()
-        .await?
-        .error_for_status()?;
+    client
+        .put(ASSET_ALARM_EVENT_BULK_CLOSE_API_PREFIX, batch)
+        .await?;
 
     Ok(())
 }
 
 async fn acknowledge_alarm_batch_async(
-    req: &Client,
-    auth_header: &String,
-    target_url: &str,
+    client: &HyperviewClient,
     batch: &[String],
 ) -> color_eyre::Result<()> {
     let payload = json!({
@@ -99,20 +68,15 @@ async fn acknowledge_alarm_batch_async(
         "acknowledgementState": "acknowledged"
     });
 
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&payload)
-        .send()
-        .await?
-        .error_for_status()?;
+    client
+        .put(ASSET_ALARM_EVENT_BULK_ACKNOWLEDGE_API_PREFIX, &payload)
+        .await?;
 
     Ok(())
 }
 
 pub async fn manage_asset_alarm_events_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: String,
     manage_action_options: ManageActionOptions,
 ) -> color_eyre::Result<()> {
@@ -131,41 +95,16 @@ pub async fn manage_asset_alarm_events_async(
 
     match manage_action_options {
         ManageActionOptions::Close => {
-            let target_url = format!(
-                "{}{}",
-                config.instance_url, ASSET_ALARM_EVENT_BULK_CLOSE_API_PREFIX
-            );
-            debug!("Request URL: {target_url}");
-
             for batch in work_batches {
-                auth_token.refresh_if_needed_async(config).await?;
-
-                if let Err(e) = retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
-                    close_alarm_batch_async(req, &auth_token.header, &target_url, &batch).await
-                ) {
+                if let Err(e) = close_alarm_batch_async(client, &batch).await {
                     error!("Failed to close alarm event batch {batch:?}: {e}");
                 }
             }
         }
 
         ManageActionOptions::Acknowledge => {
-            let target_url = format!(
-                "{}{}",
-                config.instance_url, ASSET_ALARM_EVENT_BULK_ACKNOWLEDGE_API_PREFIX
-            );
-            debug!("Request URL: {target_url}");
-
             for batch in work_batches {
-                auth_token.refresh_if_needed_async(config).await?;
-
-                if let Err(e) = retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
-                    acknowledge_alarm_batch_async(req, &auth_token.header, &target_url, &batch)
-                        .await
-                ) {
+                if let Err(e) = acknowledge_alarm_batch_async(client, &batch).await {
                     error!("Failed to acknowledge alarm event batch {batch:?}: {e}");
                 }
             }
@@ -178,14 +117,10 @@ pub async fn manage_asset_alarm_events_async(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
     use std::io::Write;
-    use std::time::Duration;
-
-    fn auth_token() -> AuthToken {
-        AuthToken::for_test("Bearer test_token", Duration::from_hours(1))
-    }
 
     fn write_alarm_csv(ids: &[&str]) -> tempfile::NamedTempFile {
         let mut tmp = tempfile::NamedTempFile::new().unwrap();
@@ -231,19 +166,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth = "Bearer t".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let result = list_alarm_events_async(
-            &config,
-            &client,
-            &auth,
-            0,
-            10,
-            AlarmEventFilterOptions::Unacknowledged,
-        )
-        .await
-        .unwrap();
+        let result =
+            list_alarm_events_async(&client, 0, 10, AlarmEventFilterOptions::Unacknowledged)
+                .await
+                .unwrap();
 
         m.assert();
         assert!(result.data.is_empty());
@@ -265,17 +193,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        list_alarm_events_async(
-            &config,
-            &client,
-            &"Bearer t".to_string(),
-            0,
-            10,
-            AlarmEventFilterOptions::Active,
-        )
-        .await
-        .unwrap();
+        let client = HyperviewClient::for_test(config);
+        list_alarm_events_async(&client, 0, 10, AlarmEventFilterOptions::Active)
+            .await
+            .unwrap();
 
         m.assert();
     }
@@ -295,14 +216,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_alarm_csv(&["alarm-1", "alarm-2"]);
 
         manage_asset_alarm_events_async(
-            &config,
             &client,
-            &mut token,
             csv.path().to_string_lossy().to_string(),
             ManageActionOptions::Close,
         )
@@ -328,14 +246,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_alarm_csv(&["alarm-x"]);
 
         manage_asset_alarm_events_async(
-            &config,
             &client,
-            &mut token,
             csv.path().to_string_lossy().to_string(),
             ManageActionOptions::Acknowledge,
         )
@@ -364,14 +279,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_alarm_csv(&id_refs);
 
         manage_asset_alarm_events_async(
-            &config,
             &client,
-            &mut token,
             csv.path().to_string_lossy().to_string(),
             ManageActionOptions::Close,
         )
@@ -408,21 +320,20 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_alarm_csv(&id_refs);
 
         manage_asset_alarm_events_async(
-            &config,
             &client,
-            &mut token,
             csv.path().to_string_lossy().to_string(),
             ManageActionOptions::Close,
         )
         .await
         .expect("manage alarm events must not abort on a per-batch 500");
 
-        fail_mock.assert();
+        // The 500 is retried up to the default max_attempts before the batch is given up.
+
+        fail_mock.assert_calls(4);
         ok_mock.assert();
     }
 }
@@ -1,8 +1,275 @@
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::fmt;
+use std::str::FromStr;
 use uuid::Uuid;
 
-use super::cli_data::{RackPanelType, RackPosition, RackSide};
+use super::{
+    app_errors::AppError,
+    filter_expression::{FilterExpression, search_field},
+};
+
+/// Page size of an asset search when none is set.
+const DEFAULT_SEARCH_LIMIT: u32 = 100;
+
+/// The most assets the search API returns in one page.
+pub const MAX_SEARCH_LIMIT: u32 = 1000;
+
+/// An asset search, built up from its filters:
+///
+/// ```no_run
+/// # use hvcli::hyperview::asset_api_data::{AssetSearch, AssetTypes};
+/// let search = AssetSearch::new()
+///     .asset_type(AssetTypes::Rack)
+///     .location_path("All/Data Center 1")
+///     .property("serialNumber", "SN-1234")
+///     .limit(50);
+/// ```
+///
+/// Every filter that is set must match. Without a location path the search covers every asset
+/// under the "All" location.
+///
+/// A search returns one page of `limit` assets starting at `skip`, unless [`AssetSearch::all`]
+/// is set, in which case pages of `limit` are fetched until every match has been returned.
+///
+/// Results come in the order of `sort`, or by id when fetching every page, so that the same
+/// export comes out the same each run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AssetSearch {
+    pub search_pattern: Option<String>,
+    pub asset_type: Option<AssetTypes>,
+    pub location_path: Option<String>,
+    /// Asset property filters, as (property type, value) pairs.
+    pub properties: Vec<(String, String)>,
+    /// Custom asset property filters, as (name, value) pairs.
+    pub custom_properties: Vec<(String, String)>,
+    pub id: Option<Uuid>,
+    pub manufacturer: Option<String>,
+    /// Matches product names containing this text.
+    pub product: Option<String>,
+    /// A condition built with the `--filter` expression language.
+    pub filter: Option<FilterExpression>,
+    /// A condition in the search API's own filter syntax, sent as it is.
+    pub raw_filter: Option<String>,
+    /// An asset property type to look up for each result and return in `AssetDto::property`.
+    pub show_property: Option<String>,
+    /// Result order, most significant key first.
+    pub sort: Vec<SortKey>,
+    pub skip: u32,
+    /// Assets per page.
+    pub limit: u32,
+    /// Fetch every page rather than only the first.
+    pub all: bool,
+    /// With `all`, stop after this many assets.
+    pub max_results: Option<u32>,
+}
+
+impl Default for AssetSearch {
+    fn default() -> Self {
+        Self {
+            search_pattern: None,
+            asset_type: None,
+            location_path: None,
+            properties: Vec::new(),
+            custom_properties: Vec::new(),
+            id: None,
+            manufacturer: None,
+            product: None,
+            filter: None,
+            raw_filter: None,
+            show_property: None,
+            sort: Vec::new(),
+            skip: 0,
+            limit: DEFAULT_SEARCH_LIMIT,
+            all: false,
+            max_results: None,
+        }
+    }
+}
+
+impl AssetSearch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Free text to search for.
+    pub fn search_pattern(mut self, pattern: impl Into<String>) -> Self {
+        self.search_pattern = Some(pattern.into());
+        self
+    }
+
+    pub fn asset_type(mut self, asset_type: AssetTypes) -> Self {
+        self.asset_type = Some(asset_type);
+        self
+    }
+
+    /// Only assets at or below this location, e.g. `All/Data Center 1`.
+    pub fn location_path(mut self, path: impl Into<String>) -> Self {
+        self.location_path = Some(path.into());
+        self
+    }
+
+    pub fn property(mut self, property_type: impl Into<String>, value: impl Into<String>) -> Self {
+        self.properties.push((property_type.into(), value.into()));
+        self
+    }
+
+    pub fn custom_property(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.custom_properties.push((name.into(), value.into()));
+        self
+    }
+
+    pub fn id(mut self, id: Uuid) -> Self {
+        self.id = Some(id);
+        self
+    }
+
+    pub fn manufacturer(mut self, manufacturer: impl Into<String>) -> Self {
+        self.manufacturer = Some(manufacturer.into());
+        self
+    }
+
+    pub fn product(mut self, product: impl Into<String>) -> Self {
+        self.product = Some(product.into());
+        self
+    }
+
+    pub fn filter(mut self, filter: FilterExpression) -> Self {
+        self.filter = Some(filter);
+        self
+    }
+
+    pub fn raw_filter(mut self, expression: impl Into<String>) -> Self {
+        self.raw_filter = Some(expression.into());
+        self
+    }
+
+    pub fn show_property(mut self, property_type: impl Into<String>) -> Self {
+        self.show_property = Some(property_type.into());
+        self
+    }
+
+    /// Adds a sort key after any already given.
+    pub fn sort(mut self, key: SortKey) -> Self {
+        self.sort.push(key);
+        self
+    }
+
+    pub fn skip(mut self, skip: u32) -> Self {
+        self.skip = skip;
+        self
+    }
+
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = limit;
+        self
+    }
+
+    /// Fetch every matching asset, a page of `limit` at a time.
+    pub fn all(mut self) -> Self {
+        self.all = true;
+        self
+    }
+
+    pub fn max_results(mut self, max_results: u32) -> Self {
+        self.max_results = Some(max_results);
+        self
+    }
+
+    /// The order results are asked for in: `sort`, or by id when fetching every page.
+    pub fn sort_keys(&self) -> Vec<SortKey> {
+        if self.sort.is_empty() && self.all {
+            vec![SortKey::ascending("id")]
+        } else {
+            self.sort.clone()
+        }
+    }
+}
+
+/// Asset fields results can be sorted by, named as in the JSON output.
+pub const SORT_FIELDS: [&str; 14] = [
+    "id",
+    "name",
+    "assetLifecycleState",
+    "assetTypeId",
+    "manufacturerId",
+    "manufacturerName",
+    "monitoringState",
+    "parentId",
+    "parentName",
+    "productId",
+    "productName",
+    "status",
+    "path",
+    "serialNumber",
+];
+
+/// A field to sort search results by, given as `field`, `field:asc` or `field:desc`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SortKey {
+    /// One of [`SORT_FIELDS`].
+    pub field: String,
+    pub descending: bool,
+}
+
+impl SortKey {
+    pub fn ascending(field: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            descending: false,
+        }
+    }
+
+    pub fn descending(field: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            descending: true,
+        }
+    }
+
+    /// The key as the search API's `sort` parameter takes it, e.g. `displayName:desc`.
+    pub fn search_syntax(&self) -> String {
+        let direction = if self.descending { "desc" } else { "asc" };
+        format!("{}:{direction}", search_field(&self.field))
+    }
+
+    /// Orders two assets by this key, for sorting results the search API would not sort.
+    pub fn compare(&self, a: &AssetDto, b: &AssetDto) -> Ordering {
+        let ordering = a.sort_value(&self.field).cmp(&b.sort_value(&self.field));
+        if self.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        }
+    }
+}
+
+impl FromStr for SortKey {
+    type Err = AppError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (field, descending) = match value.rsplit_once(':') {
+            Some((field, direction)) if direction.eq_ignore_ascii_case("asc") => (field, false),
+            Some((field, direction)) if direction.eq_ignore_ascii_case("desc") => (field, true),
+            None => (value, false),
+            Some(_) => ("", false),
+        };
+        let field = field.trim();
+        if !SORT_FIELDS.contains(&field) {
+            return Err(AppError::InvalidSort {
+                value: value.to_string(),
+                valid: SORT_FIELDS.join(", "),
+            });
+        }
+
+        Ok(Self {
+            field: field.to_string(),
+            descending,
+        })
+    }
+}
 
 #[derive(Debug, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -34,6 +301,29 @@ pub struct AssetDto {
     pub property: Option<String>,
 }
 
+impl AssetDto {
+    /// The value of one of [`SORT_FIELDS`], or an empty string for any other name.
+    fn sort_value(&self, field: &str) -> Cow<'_, str> {
+        match field {
+            "id" => Cow::Owned(self.id.to_string()),
+            "name" => Cow::Borrowed(&self.name),
+            "assetLifecycleState" => Cow::Borrowed(&self.asset_lifecycle_state),
+            "assetTypeId" => Cow::Borrowed(&self.asset_type_id),
+            "manufacturerId" => Cow::Borrowed(&self.manufacturer_id),
+            "manufacturerName" => Cow::Borrowed(&self.manufacturer_name),
+            "monitoringState" => Cow::Borrowed(&self.monitoring_state),
+            "parentId" => Cow::Borrowed(&self.parent_id),
+            "parentName" => Cow::Borrowed(&self.parent_name),
+            "productId" => Cow::Borrowed(&self.product_id),
+            "productName" => Cow::Borrowed(&self.product_name),
+            "status" => Cow::Borrowed(&self.status),
+            "path" => Cow::Borrowed(&self.path),
+            "serialNumber" => Cow::Borrowed(&self.serial_number),
+            _ => Cow::Borrowed(""),
+        }
+    }
+}
+
 impl fmt::Display for AssetDto {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let asset_record = format!(
@@ -155,3 +445,87 @@ port_type_value_id: {}
         write!(f, "{asset_port_record}")
     }
 }
+
+// camelCase across clap (CLI input), serde (CSV input), and Display (wire output) so the CLI's
+// representation of an asset type is a single value that matches the Hyperview API's
+// AssetTypeEnum (which is camelCase). The API is case-insensitive on input but emits camelCase.
+#[derive(Debug, ValueEnum, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[clap(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase")]
+pub enum AssetTypes {
+    BatteryBank,
+    BladeEnclosure,
+    BladeNetwork,
+    BladeServer,
+    BladeStorage,
+    Busway,
+    Camera,
+    Chiller,
+    Crac,
+    Crah,
+    DcRectifier,
+    Environmental,
+    FireControlPanel,
+    Generator,
+    InRowCooling,
+    KvmSwitch,
+    Location,
+    Monitor,
+    NetworkDevice,
+    NetworkStorage,
+    NodeServer,
+    OtherDevice,
+    PatchPanel,
+    PduAndRpp,
+    PowerMeter,
+    Rack,
+    RackPdu,
+    Server,
+    SmallUps,
+    Switchboard,
+    Switchgear,
+    TransferSwitch,
+    Unknown,
+    Ups,
+    VirtualServer,
+}
+
+impl fmt::Display for AssetTypes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Emit the API's camelCase form: the PascalCase variant name with its first letter
+        // lowercased. This matches `#[serde(rename_all = "camelCase")]` and the clap value names.
+        let name = format!("{self:?}");
+        let mut chars = name.chars();
+        match chars.next() {
+            Some(first) => write!(f, "{}{}", first.to_ascii_lowercase(), chars.as_str()),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Debug, ValueEnum, Clone, Serialize, Deserialize)]
+#[clap(rename_all = "PascalCase")]
+pub enum RackSide {
+    Front,
+    Rear,
+    Unknown,
+}
+
+#[derive(Debug, ValueEnum, Clone, Serialize, Deserialize)]
+#[clap(rename_all = "PascalCase")]
+pub enum RackPosition {
+    Left,
+    Right,
+    Top,
+    Bottom,
+    Above,
+    Below,
+    Unknown,
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy, Deserialize)]
+#[clap(rename_all = "PascalCase")]
+pub enum RackPanelType {
+    BlankingPanel,
+    CableManagement,
+}
@@ -1,76 +1,86 @@
-use log::{debug, error, info, trace};
-use reqwest::{
-    Client,
-    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-};
+use futures::stream::{self, Stream, TryStreamExt};
+use log::{debug, error, info, trace, warn};
+use reqwest::StatusCode;
 use serde_json::{Value, json};
+use std::cmp::Ordering;
 use std::str::FromStr;
 use uuid::Uuid;
 
 use crate::hyperview::asset_api_data::AddRackAccessoryRecord;
-use crate::retry_on_unauthorized_async;
 
 use super::{
+    api_client::HyperviewClient,
     api_constants::{
-        ASSET_ASSETS_API_PREFIX, ASSET_LOCATION_API_PREFIX, ASSET_PORTS_API_PREFIX,
-        ASSET_SEARCH_API_PREFIX, RACK_PANEL_API_PREFIX,
+        ALL_LOCATION_ASSET_ID, ASSET_ASSETS_API_PREFIX, ASSET_LOCATION_API_PREFIX,
+        ASSET_PORTS_API_PREFIX, ASSET_SEARCH_API_PREFIX, RACK_PANEL_API_PREFIX,
     },
-    app_errors::AppError,
+    app_errors::{ApiError, AppError},
     asset_api_data::{
-        AssetDto, AssetLocationDTO, AssetPortDto, UpdateAssetLocationRecord, UpdateAssetNameRecord,
+        AssetDto, AssetLocationDTO, AssetPortDto, AssetSearch, RackPanelType, RackSide, SortKey,
+        UpdateAssetLocationRecord, UpdateAssetNameRecord,
     },
     asset_properties_api_functions::get_named_asset_property_async,
-    auth::AuthToken,
-    cli_data::{
-        AppConfig, ListAnyOfArgs, ListRecordsByAssetIdArgs, RackPanelType, RackSide,
-        SearchAssetsArgs, UpdateAssetLocationArgs,
-    },
+    bulk::run_rows_async,
+    search_filter::SearchFilter,
 };
 
+/// Asset fields a search returns, as read by `hit_to_asset_dto`.
+const SEARCH_ATTRIBUTES: [&str; 14] = [
+    "id",
+    "displayName",
+    "assetLifecycleState",
+    "assetType",
+    "manufacturerId",
+    "manufacturerName",
+    "monitoringState",
+    "parentId",
+    "parentDisplayName",
+    "productId",
+    "productName",
+    "status",
+    "delimitedPath",
+    "assetProperty_serialNumber",
+];
+
 pub async fn bulk_add_rack_accessory_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: &String,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
-    let mut reader = csv::Reader::from_path(filename)?;
-    while let Some(Ok(record)) = reader.deserialize::<AddRackAccessoryRecord>().next() {
-        auth_token.refresh_if_needed_async(config).await?;
-
-        debug!("Adding rack accessory to rack_id {}", record.id);
-        if let Err(e) = retry_on_unauthorized_async!(
-            config,
-            auth_token,
+    let records: Vec<AddRackAccessoryRecord> = csv::Reader::from_path(filename)?
+        .deserialize()
+        .map_while(Result::ok)
+        .collect();
+
+    run_rows_async(
+        records,
+        concurrency,
+        |record| Some(record.id),
+        |record| async move {
+            debug!("Adding rack accessory to rack_id {}", record.id);
             add_rack_accessory_async(
-                config,
-                req,
-                &auth_token.header,
+                client,
                 &record.id,
                 &record.panel_type,
                 &record.side,
                 record.u_location,
             )
             .await
-        ) {
-            error!("Failed to add rack accessory to rack_id {}: {e}", record.id);
-        }
-    }
+            .map_err(|e| format!("Failed to add rack accessory to rack_id {}: {e}", record.id))
+        },
+    )
+    .await;
 
     Ok(())
 }
 
 pub async fn add_rack_accessory_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     rack_id: &Uuid,
     rack_panel_type: &RackPanelType,
     rack_side: &RackSide,
     rack_u_location: usize,
 ) -> color_eyre::Result<()> {
-    let target_url = format!("{}{}", config.instance_url, RACK_PANEL_API_PREFIX);
-    debug!("Request URL: {target_url}");
-
     let display_name_annotation = match rack_side {
         RackSide::Rear => "(R)",
         _ => "",
@@ -113,13 +123,7 @@ pub async fn add_rack_accessory_async(
         serde_json::to_string_pretty(&payload)?
     );
 
-    let resp = req
-        .post(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&payload)
-        .send()
-        .await?
-        .error_for_status()?;
+    let resp = client.post(RACK_PANEL_API_PREFIX, &payload).await?;
 
     trace!("Server response: {}", resp.status());
 
@@ -127,93 +131,78 @@ pub async fn add_rack_accessory_async(
 }
 
 pub async fn bulk_update_ports_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: String,
     is_patchpanel: bool,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
-    let mut reader = csv::Reader::from_path(filename)?;
-    while let Some(Ok(record)) = reader.deserialize::<AssetPortDto>().next() {
-        auth_token.refresh_if_needed_async(config).await?;
-
-        debug!("Updating port id: {}", record.id);
-
-        // Patch panel flow
-        if is_patchpanel {
-            trace!("Updating patch panel port");
-            let target_url = format!(
-                "{}{}/patchPanel/{}",
-                config.instance_url, ASSET_PORTS_API_PREFIX, record.id
-            );
-            debug!("Request URL: {target_url}");
+    let records: Vec<AssetPortDto> = csv::Reader::from_path(filename)?
+        .deserialize()
+        .map_while(Result::ok)
+        .collect();
+
+    run_rows_async(
+        records,
+        concurrency,
+        |record| Some(record.id),
+        |record| async move {
+            debug!("Updating port id: {}", record.id);
+
+            // Patch panel flow
+            if is_patchpanel {
+                trace!("Updating patch panel port");
+                let target_path = format!("{ASSET_PORTS_API_PREFIX}/patchPanel/{}", record.id);
+
+                let payload = json!({
+                  "id": record.id,
+                  "name": record.name,
+                  "parentId": record.parent_id,
+                  "portNumber": record.port_number,
+                  "connectorTypeValueId": record.connector_type_value_id,
+                  "portSideValueId": record.port_side_value_id,
+                });
+                debug!("Payload: {payload:#}");
+
+                return update_port_async(client, &target_path, &payload)
+                    .await
+                    .map_err(|e| {
+                        format!("Failed to update patch panel port id {}: {e}", record.id)
+                    });
+            }
+
+            // Asset flow
+            trace!("Updating asset port");
+            let target_path = format!("{ASSET_PORTS_API_PREFIX}/{}", record.id);
 
             let payload = json!({
               "id": record.id,
               "name": record.name,
               "parentId": record.parent_id,
               "portNumber": record.port_number,
-              "connectorTypeValueId": record.connector_type_value_id,
               "portSideValueId": record.port_side_value_id,
+              "portTypeValueId": record.port_type_value_id
             });
-            debug!("Payload: {}", serde_json::to_string_pretty(&payload)?);
-
-            if let Err(e) = retry_on_unauthorized_async!(
-                config,
-                auth_token,
-                update_port_async(req, &auth_token.header, &target_url, &payload).await
-            ) {
-                error!("Failed to update patch panel port id {}: {e}", record.id);
-            }
-
-            // Go to next record
-            continue;
-        }
-
-        // Asset flow
-        trace!("Updating asset port");
-        let target_url = format!(
-            "{}{}/{}",
-            config.instance_url, ASSET_PORTS_API_PREFIX, record.id
-        );
-        debug!("Request URL: {target_url}");
-
-        let payload = json!({
-          "id": record.id,
-          "name": record.name,
-          "parentId": record.parent_id,
-          "portNumber": record.port_number,
-          "portSideValueId": record.port_side_value_id,
-          "portTypeValueId": record.port_type_value_id
-        });
 
-        debug!("Payload: {}", serde_json::to_string_pretty(&payload)?);
+            debug!("Payload: {payload:#}");
 
-        if let Err(e) = retry_on_unauthorized_async!(
-            config,
-            auth_token,
-            update_port_async(req, &auth_token.header, &target_url, &payload).await
-        ) {
-            error!("Failed to update asset port id {}: {e}", record.id);
-        }
-    }
+            update_port_async(client, &target_path, &payload)
+                .await
+                .map_err(|e| format!("Failed to update asset port id {}: {e}", record.id))
+        },
+    )
+    .await;
 
     Ok(())
 }
 
 async fn update_port_async(
-    req: &Client,
-    auth_header: &String,
-    target_url: &str,
+    client: &HyperviewClient,
+    target_path: &str,
     payload: &Value,
 ) -> color_eyre::Result<()> {
-    let resp = req
-        .put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(payload)
-        .send()
+    let resp = client
+        .put(target_path, payload)
         .await?
-        .error_for_status()?
         .json::<Value>()
         .await?;
 
@@ -226,24 +215,11 @@ async fn update_port_async(
 }
 
 pub async fn list_asset_ports_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
-    list_asset_ports_args: ListRecordsByAssetIdArgs,
+    client: &HyperviewClient,
+    asset_id: Uuid,
 ) -> color_eyre::Result<Vec<AssetPortDto>> {
-    let target_url = format!(
-        "{}{}/detailed/{}",
-        config.instance_url, ASSET_PORTS_API_PREFIX, list_asset_ports_args.id
-    );
-
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .json::<Vec<Value>>()
+    let resp: Vec<Value> = client
+        .get(&format!("{ASSET_PORTS_API_PREFIX}/detailed/{asset_id}"))
         .await?;
 
     let mut asset_ports = Vec::new();
@@ -292,40 +268,20 @@ pub async fn list_asset_ports_async(
 }
 
 pub async fn update_asset_location_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
-    update_location_data: UpdateAssetLocationArgs,
+    client: &HyperviewClient,
+    asset_id: Uuid,
+    asset_location_dto: AssetLocationDTO,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/{}?id={}",
-        config.instance_url,
-        ASSET_LOCATION_API_PREFIX,
-        update_location_data.id,
-        update_location_data.id
-    );
-
-    debug!("Request URL: {target_url}");
-
-    let asset_location_dto = AssetLocationDTO {
-        parent_id: update_location_data.new_location_id,
-        rack_position: update_location_data.rack_position,
-        rack_side: update_location_data.rack_side,
-        rack_u_location: update_location_data.rack_u_location,
-    };
+    let target_path = format!("{ASSET_LOCATION_API_PREFIX}/{asset_id}?id={asset_id}");
 
     debug!(
         "New location payload: {}",
         serde_json::to_string_pretty(&asset_location_dto)?
     );
 
-    let resp = req
-        .put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&asset_location_dto)
-        .send()
+    let resp = client
+        .put(&target_path, &asset_location_dto)
         .await?
-        .error_for_status()?
         .json::<Value>()
         .await?;
 
@@ -338,49 +294,40 @@ pub async fn update_asset_location_async(
 }
 
 pub async fn bulk_update_asset_location_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: String,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
-    let mut reader = csv::Reader::from_path(filename)?;
-    let mut total: usize = 0;
-    let mut failed: usize = 0;
-    while let Some(Ok(record)) = reader.deserialize::<UpdateAssetLocationRecord>().next() {
-        auth_token.refresh_if_needed_async(config).await?;
-        total += 1;
-
-        debug!(
-            "Updating asset id: {} with new location: {}",
-            record.asset_id, record.new_location_id
-        );
-
-        let id = record.asset_id;
-        let new_location_id = record.new_location_id;
-
-        let update_location_data = UpdateAssetLocationArgs {
-            id,
-            new_location_id,
-            rack_position: record.rack_position,
-            rack_side: record.rack_side,
-            rack_u_location: record.rack_u_location,
-        };
+    let records: Vec<UpdateAssetLocationRecord> = csv::Reader::from_path(filename)?
+        .deserialize()
+        .map_while(Result::ok)
+        .collect();
+    let total = records.len();
+
+    let failed = run_rows_async(
+        records,
+        concurrency,
+        |record| Some(record.asset_id),
+        |record| async move {
+            debug!(
+                "Updating asset id: {} with new location: {}",
+                record.asset_id, record.new_location_id
+            );
 
-        if let Err(e) = retry_on_unauthorized_async!(
-            config,
-            auth_token,
-            update_asset_location_async(
-                config,
-                req,
-                &auth_token.header,
-                update_location_data.clone(),
-            )
-            .await
-        ) {
-            error!("Failed to update location for asset id {id}: {e}");
-            failed += 1;
-        }
-    }
+            let id = record.asset_id;
+            let asset_location_dto = AssetLocationDTO {
+                parent_id: record.new_location_id,
+                rack_position: record.rack_position,
+                rack_side: record.rack_side,
+                rack_u_location: record.rack_u_location,
+            };
+
+            update_asset_location_async(client, id, asset_location_dto)
+                .await
+                .map_err(|e| format!("Failed to update location for asset id {id}: {e}"))
+        },
+    )
+    .await;
 
     if failed > 0 {
         return Err(AppError::BulkOperationFailures { failed, total }.into());
@@ -389,36 +336,18 @@ pub async fn bulk_update_asset_location_async(
 }
 
 async fn get_raw_asset_by_id_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     id: &Uuid,
 ) -> color_eyre::Result<Value> {
-    let target_url = format!("{}{}/{}", config.instance_url, ASSET_ASSETS_API_PREFIX, id);
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Value>()
-        .await?;
-
-    Ok(resp)
+    client.get(&format!("{ASSET_ASSETS_API_PREFIX}/{id}")).await
 }
 
 pub async fn update_asset_name_by_id_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     id: Uuid,
     new_name: String,
 ) -> color_eyre::Result<()> {
-    let target_url = format!("{}{}/{}", config.instance_url, ASSET_ASSETS_API_PREFIX, id);
-    debug!("Request URL: {target_url}");
-
-    let mut asset_value = get_raw_asset_by_id_async(config, req, auth_header, &id).await?;
+    let mut asset_value = get_raw_asset_by_id_async(client, &id).await?;
 
     trace!(
         "Returned asset value: {}",
@@ -437,12 +366,9 @@ pub async fn update_asset_name_by_id_async(
             // and matching only on `Value::String` used to silently PUT the untouched body.
             *name = Value::String(new_name);
 
-            req.put(target_url)
-                .header(AUTHORIZATION, auth_header)
-                .json(&asset_value)
-                .send()
-                .await?
-                .error_for_status()?;
+            client
+                .put(&format!("{ASSET_ASSETS_API_PREFIX}/{id}"), &asset_value)
+                .await?;
 
             Ok(())
         }
@@ -452,45 +378,45 @@ pub async fn update_asset_name_by_id_async(
 }
 
 pub async fn bulk_update_asset_name_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: String,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
-    let mut reader = csv::Reader::from_path(filename)?;
-    while let Some(Ok(record)) = reader.deserialize::<UpdateAssetNameRecord>().next() {
-        auth_token.refresh_if_needed_async(config).await?;
-
-        debug!(
-            "Updating asset id: {} with new name: {}",
-            record.asset_id, record.new_name
-        );
+    let records: Vec<UpdateAssetNameRecord> = csv::Reader::from_path(filename)?
+        .deserialize()
+        .map_while(Result::ok)
+        .collect();
+
+    run_rows_async(
+        records,
+        concurrency,
+        |record| Some(record.asset_id),
+        |record| async move {
+            debug!(
+                "Updating asset id: {} with new name: {}",
+                record.asset_id, record.new_name
+            );
 
-        let new_name = record.new_name.trim().replace('"', "");
+            let new_name = record.new_name.trim().replace('"', "");
 
-        if new_name.is_empty() {
-            error!("New name can't be empty for asset id: {}", record.asset_id);
-            continue;
-        }
+            if new_name.is_empty() {
+                return Err(format!(
+                    "New name can't be empty for asset id: {}",
+                    record.asset_id
+                ));
+            }
 
-        if let Err(e) = retry_on_unauthorized_async!(
-            config,
-            auth_token,
-            update_asset_name_by_id_async(
-                config,
-                req,
-                &auth_token.header,
-                record.asset_id,
-                new_name.clone(),
-            )
-            .await
-        ) {
-            error!(
-                "Failed to update name for asset id {}: {e}",
-                record.asset_id
-            );
-        }
-    }
+            update_asset_name_by_id_async(client, record.asset_id, new_name)
+                .await
+                .map_err(|e| {
+                    format!(
+                        "Failed to update name for asset id {}: {e}",
+                        record.asset_id
+                    )
+                })
+        },
+    )
+    .await;
 
     Ok(())
 }
@@ -534,205 +460,246 @@ fn hit_to_asset_dto(hit: &Value) -> Option<AssetDto> {
     })
 }
 
+/// Assets whose `property_type` property has any of `values`, narrowed down by the filters in
+/// `search`.
 pub async fn list_any_of_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
-    options: ListAnyOfArgs,
+    client: &HyperviewClient,
+    property_type: &str,
+    values: &[String],
+    search: &AssetSearch,
 ) -> color_eyre::Result<Vec<AssetDto>> {
-    let target_url = format!("{}{}", config.instance_url, ASSET_SEARCH_API_PREFIX);
-    debug!("Request URL: {target_url}");
-    debug!("Options: {options:#?}");
-
-    let search_query = compose_any_of_query(options.clone());
-
-    trace!("{}", serde_json::to_string_pretty(&search_query).unwrap());
+    collect_pages_async(list_any_of_pages(client, property_type, values, search)).await
+}
 
-    let resp = req
-        .post(target_url)
-        .header(AUTHORIZATION, auth_header.clone())
-        .header(CONTENT_TYPE, "application/json")
-        .header(ACCEPT, "application/json")
-        .json(&search_query)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Value>()
-        .await?;
+/// Assets whose `property_type` property has any of `values`, a page at a time. See
+/// [`AssetSearch`] for how many pages there are.
+pub fn list_any_of_pages<'a>(
+    client: &'a HyperviewClient,
+    property_type: &'a str,
+    values: &'a [String],
+    search: &'a AssetSearch,
+) -> impl Stream<Item = color_eyre::Result<SearchPage>> + 'a {
+    debug!("Any of {property_type} in {values:?}; search: {search:#?}");
+
+    search_pages(
+        client,
+        compose_any_of_query(property_type, values, search),
+        search,
+    )
+}
 
-    let total = resp
-        .get("estimatedTotalHits")
-        .expect("Expected estimatedTotalHits to be defined in response body.")
-        .as_u64()
-        .unwrap();
+fn compose_any_of_query(property_type: &str, values: &[String], search: &AssetSearch) -> Value {
+    let mut search_query = json!({
+      "limit": search.limit,
+      "offset": search.skip,
+      "attributesToRetrieve": SEARCH_ATTRIBUTES,
+      "filter": "",
+    });
 
-    let limit = resp
-        .get("limit")
-        .expect("Expected limit to be defined in response body.")
-        .as_u64()
-        .unwrap();
+    if let Some(pattern) = &search.search_pattern {
+        search_query["q"] = json!(pattern);
+    }
 
-    info!("Meta Data: | Total: {total} | Limit: {limit} |");
+    set_sort(&mut search_query, search);
 
-    let mut asset_list = Vec::new();
+    let field = format!("assetProperty_{property_type}");
+    let mut filter = SearchFilter::new();
+    filter.exists(&field).any_of(&field, values);
 
-    if total == 0 {
-        return Ok(asset_list);
+    if let Some(t) = &search.asset_type {
+        filter.equals("assetType", t);
     }
 
-    if let Some(assets) = resp.get("hits") {
-        assets.as_array().unwrap().iter().for_each(|a| {
-            debug!("RAW: {}", serde_json::to_string_pretty(&a).unwrap());
-            match hit_to_asset_dto(a) {
-                Some(asset) => asset_list.push(asset),
-                None => error!(
-                    "Skipping hit with missing or malformed id: {}",
-                    serde_json::to_string(a).unwrap_or_else(|_| "<unrenderable>".to_string())
-                ),
-            }
-        });
+    if let Some(p) = &search.location_path {
+        filter.starts_with("delimitedPath", p.replace('/', "~"));
     }
 
-    if let Some(property_type) = options.show_property {
-        for a in &mut asset_list {
-            let props = get_named_asset_property_async(
-                config,
-                req,
-                auth_header,
-                a.id,
-                property_type.clone(),
-            )
-            .await?;
-
-            let prop_values: String = props.iter().fold(String::new(), |mut a, v| {
-                let v = format!("{} ", v.value);
-                a.push_str(&v);
-                a
-            });
+    push_common_filters(&mut filter, search);
 
-            a.property = Some(prop_values);
-        }
-    }
+    search_query["filter"] = Value::String(filter.to_string());
 
-    Ok(asset_list)
+    search_query
 }
 
-fn compose_any_of_query(options: ListAnyOfArgs) -> serde_json::Value {
-    let mut search_query = json!({
-      "limit": options.limit,
-      "offset": options.skip,
-      "attributesToRetrieve": [
-        "id",
-        "displayName",
-        "assetLifecycleState",
-        "assetType",
-        "manufacturerId",
-        "manufacturerName",
-        "monitoringState",
-        "parentId",
-        "parentDisplayName",
-        "productId",
-        "productName",
-        "status",
-        "delimitedPath",
-        "assetProperty_serialNumber"
-      ],
-      "filter": "",
-    });
+pub async fn search_assets_async(
+    client: &HyperviewClient,
+    search: &AssetSearch,
+) -> color_eyre::Result<Vec<AssetDto>> {
+    collect_pages_async(search_asset_pages(client, search)).await
+}
 
-    let mut filters = Vec::new();
+/// The assets matching `search`, a page at a time. See [`AssetSearch`] for how many pages there
+/// are.
+pub fn search_asset_pages<'a>(
+    client: &'a HyperviewClient,
+    search: &'a AssetSearch,
+) -> impl Stream<Item = color_eyre::Result<SearchPage>> + 'a {
+    debug!("Search: {search:#?}");
+
+    stream::once(async move {
+        let all_location_uuid = Uuid::parse_str(ALL_LOCATION_ASSET_ID)?;
+        let all_location = get_raw_asset_by_id_async(client, &all_location_uuid).await?;
+        let all_location_name = all_location
+            .get("name")
+            .expect("All location did not have a name")
+            .as_str()
+            .expect("Unable to parse location name as str");
+
+        Ok::<_, color_eyre::Report>(compose_search_query(search, all_location_name))
+    })
+    .map_ok(move |search_query| search_pages(client, search_query, search))
+    .try_flatten()
+}
 
-    filters.push(format!(
-        "assetProperty_{} EXISTS AND assetProperty_{} IN {:?}",
-        options.property_key, options.property_key, options.property_value
-    ));
+/// One page of search results.
+#[derive(Debug)]
+pub struct SearchPage {
+    pub assets: Vec<AssetDto>,
+    /// The search API's estimate of how many assets match in all.
+    pub estimated_total_hits: u64,
+    /// Hits returned by this page and the ones before it.
+    pub fetched: u64,
+}
 
-    if let Some(t) = options.asset_type {
-        let asset_type = t.to_string();
-        filters.push(format!("assetType = '{asset_type}'"));
-    }
+async fn collect_pages_async(
+    pages: impl Stream<Item = color_eyre::Result<SearchPage>>,
+) -> color_eyre::Result<Vec<AssetDto>> {
+    pages
+        .try_fold(Vec::new(), |mut assets, page| async move {
+            assets.extend(page.assets);
+            Ok(assets)
+        })
+        .await
+}
 
-    if let Some(p) = options.location_path {
-        let prepared_path = p.replace('/', "~").clone();
-        filters.push(format!("delimitedPath STARTS WITH '{prepared_path}'"));
-    }
+/// Sends `search_query` a page at a time. Without `search.all` that is the one page of
+/// `search.limit` at `search.skip`. With it, pages follow one another until one comes back short,
+/// as the total the API reports is only an estimate, or `search.max_results` is reached.
+///
+/// If the search API turns down the query's sort, a search for one page fails with
+/// [`AppError::SortUnsupported`], since sorting that page alone would not make it the right page
+/// of the sorted results. With `search.all`, the query is sent again without the sort and the
+/// results are sorted here: every page is held back in memory and all of them are returned in
+/// the last one, as the order is only known once they have all arrived.
+fn search_pages<'a>(
+    client: &'a HyperviewClient,
+    search_query: Value,
+    search: &'a AssetSearch,
+) -> impl Stream<Item = color_eyre::Result<SearchPage>> + 'a {
+    let max_results = if search.all {
+        search.max_results.map(u64::from)
+    } else {
+        Some(u64::from(search.limit))
+    };
+    let start = PageState {
+        search_query,
+        fetched: 0,
+        held: None,
+    };
 
-    if let Some(custom_properties) = options.custom_properties {
-        for custom_property in custom_properties {
-            if let Some((custom_property_key_name, custom_property_key_value)) =
-                custom_property.split_once('=')
-            {
-                let custom_property_key_attribute =
-                    format!("customProperty_{} ", custom_property_key_name.trim());
-                filters.push(format!(
-                    "{} = '{}'",
-                    custom_property_key_attribute,
-                    custom_property_key_value.trim()
-                ));
-            } else {
-                error!(
-                    "Custom asset property filter was formatted incorrectly. Skipping... '{custom_property}'"
-                );
-            }
+    stream::try_unfold(Some(start), move |state| async move {
+        let Some(mut state) = state else {
+            return Ok(None);
+        };
+        let remaining = max_results.map_or(u64::MAX, |max| max.saturating_sub(state.fetched));
+        let page_size = remaining.min(u64::from(search.limit));
+        if page_size == 0 {
+            return Ok(None);
         }
-    }
-
-    if let Some(id_guid) = options.id {
-        let id_query = format!("id = '{id_guid}'");
-        filters.push(id_query);
-    }
 
-    if let Some(manufacturer) = options.manufacturer {
-        let manufacturer_name_query = format!("manufacturerName = '{manufacturer}'");
-        filters.push(manufacturer_name_query);
-    }
+        state.search_query["offset"] = json!(u64::from(search.skip) + state.fetched);
+        state.search_query["limit"] = json!(page_size);
+        let (mut assets, hits, estimated_total_hits) =
+            match run_search_async(client, &state.search_query, search).await {
+                Err(e) if state.fetched == 0 && is_rejected_sort(&state.search_query, &e) => {
+                    if !search.all {
+                        return Err(e.wrap_err(AppError::SortUnsupported));
+                    }
+                    warn!(
+                        "The search API would not sort the results ({e}); sorting them here \
+                         once every page has arrived"
+                    );
+                    if let Some(query) = state.search_query.as_object_mut() {
+                        query.remove("sort");
+                    }
+                    state.held = Some(Vec::new());
+                    run_search_async(client, &state.search_query, search).await?
+                }
+                result => result?,
+            };
+        state.fetched += hits;
+        let fetched = state.fetched;
+
+        let more = search.all && hits == page_size;
+        if search.all && !more && max_results.is_some_and(|max| fetched >= max) {
+            warn!(
+                "Stopped after {fetched} assets as asked; about {estimated_total_hits} match in all"
+            );
+        }
 
-    if let Some(product) = options.product {
-        let product_name_query = format!("productName CONTAINS '{product}'");
-        filters.push(product_name_query);
-    }
+        if let Some(held) = &mut state.held {
+            held.append(&mut assets);
+            if !more {
+                assets = std::mem::take(held);
+                let sort = search.sort_keys();
+                assets.sort_by(|a, b| {
+                    sort.iter()
+                        .map(|key| key.compare(a, b))
+                        .find(|ordering| ordering.is_ne())
+                        .unwrap_or(Ordering::Equal)
+                });
+            }
+        }
 
-    let filter_str = filters.join(" AND ");
+        Ok(Some((
+            SearchPage {
+                assets,
+                estimated_total_hits,
+                fetched,
+            },
+            more.then_some(state),
+        )))
+    })
+}
 
-    if let Some(filter_field) = search_query.get_mut("filter") {
-        *filter_field = Value::String(filter_str);
-    }
+/// Where [`search_pages`] has got to.
+struct PageState {
+    search_query: Value,
+    /// Hits returned so far.
+    fetched: u64,
+    /// Assets fetched but not yet returned, when sorting them here.
+    held: Option<Vec<AssetDto>>,
+}
 
-    search_query
+/// Whether `e` is the search API turning down a query because of its sort, such as a sort on a
+/// field the search index cannot sort by. Only a 400 whose explanation is about sorting counts,
+/// so a query rejected for its filter fails with that error rather than being sent again.
+fn is_rejected_sort(search_query: &Value, e: &color_eyre::Report) -> bool {
+    search_query.get("sort").is_some()
+        && e.downcast_ref::<ApiError>().is_some_and(|e| {
+            e.status == StatusCode::BAD_REQUEST
+                && e.reason().is_some_and(|reason| {
+                    reason
+                        .to_lowercase()
+                        .split(|c: char| !c.is_ascii_alphanumeric())
+                        .any(|word| matches!(word, "sort" | "sortable" | "sorting"))
+                })
+        })
 }
 
-pub async fn search_assets_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
-    options: SearchAssetsArgs,
-) -> color_eyre::Result<Vec<AssetDto>> {
-    let target_url = format!("{}{}", config.instance_url, ASSET_SEARCH_API_PREFIX);
-    debug!("Request URL: {target_url}");
-    debug!("Options: {options:#?}");
-
-    let all_location_uuid = Uuid::parse_str("11223344-5566-7788-99aa-bbccddeeff00")?;
-    let all_location =
-        get_raw_asset_by_id_async(config, req, auth_header, &all_location_uuid).await?;
-    let all_location_name = all_location
-        .get("name")
-        .expect("All location did not have a name")
-        .as_str()
-        .expect("Unable to parse location name as str");
-
-    let search_query = compose_search_query(options.clone(), all_location_name);
-
-    trace!("{}", serde_json::to_string_pretty(&search_query).unwrap());
-
-    let resp = req
-        .post(target_url)
-        .header(AUTHORIZATION, auth_header.clone())
-        .header(CONTENT_TYPE, "application/json")
-        .header(ACCEPT, "application/json")
-        .json(&search_query)
-        .send()
+/// Sends `search_query` and turns the hits into assets, looking up `search.show_property` for
+/// each one if set. Also returns the number of hits, which counts any that were skipped, and the
+/// estimated total.
+async fn run_search_async(
+    client: &HyperviewClient,
+    search_query: &Value,
+    search: &AssetSearch,
+) -> color_eyre::Result<(Vec<AssetDto>, u64, u64)> {
+    trace!("{}", serde_json::to_string_pretty(search_query).unwrap());
+
+    let resp = client
+        .post_idempotent(ASSET_SEARCH_API_PREFIX, search_query)
         .await?
-        .error_for_status()?
         .json::<Value>()
         .await?;
 
@@ -753,32 +720,27 @@ pub async fn search_assets_async(
     let mut asset_list = Vec::new();
 
     if total == 0 {
-        return Ok(asset_list);
+        return Ok((asset_list, 0, total));
     }
 
-    if let Some(assets) = resp.get("hits") {
-        assets.as_array().unwrap().iter().for_each(|a| {
-            debug!("RAW: {}", serde_json::to_string_pretty(&a).unwrap());
-            match hit_to_asset_dto(a) {
-                Some(asset) => asset_list.push(asset),
-                None => error!(
-                    "Skipping hit with missing or malformed id: {}",
-                    serde_json::to_string(a).unwrap_or_else(|_| "<unrenderable>".to_string())
-                ),
-            }
-        });
-    }
+    let hits = resp
+        .get("hits")
+        .and_then(Value::as_array)
+        .map_or(&[][..], Vec::as_slice);
+    hits.iter().for_each(|a| {
+        debug!("RAW: {}", serde_json::to_string_pretty(&a).unwrap());
+        match hit_to_asset_dto(a) {
+            Some(asset) => asset_list.push(asset),
+            None => error!(
+                "Skipping hit with missing or malformed id: {}",
+                serde_json::to_string(a).unwrap_or_else(|_| "<unrenderable>".to_string())
+            ),
+        }
+    });
 
-    if let Some(property_type) = options.show_property {
+    if let Some(property_type) = &search.show_property {
         for a in &mut asset_list {
-            let props = get_named_asset_property_async(
-                config,
-                req,
-                auth_header,
-                a.id,
-                property_type.clone(),
-            )
-            .await?;
+            let props = get_named_asset_property_async(client, a.id, property_type.clone()).await?;
 
             let prop_values: String = props.iter().fold(String::new(), |mut a, v| {
                 let v = format!("{} ", v.value);
@@ -790,110 +752,89 @@ pub async fn search_assets_async(
         }
     }
 
-    Ok(asset_list)
+    Ok((asset_list, hits.len() as u64, total))
 }
 
-fn compose_search_query(options: SearchAssetsArgs, all_location_name: &str) -> serde_json::Value {
+fn compose_search_query(search: &AssetSearch, all_location_name: &str) -> Value {
     let mut search_query = json!({
-      "limit": options.limit,
-      "offset": options.skip,
-      "attributesToRetrieve": [
-        "id",
-        "displayName",
-        "assetLifecycleState",
-        "assetType",
-        "manufacturerId",
-        "manufacturerName",
-        "monitoringState",
-        "parentId",
-        "parentDisplayName",
-        "productId",
-        "productName",
-        "status",
-        "delimitedPath",
-        "assetProperty_serialNumber"
-      ],
-      "q": options.search_pattern,
+      "limit": search.limit,
+      "offset": search.skip,
+      "attributesToRetrieve": SEARCH_ATTRIBUTES,
+      "q": search.search_pattern,
       "filter": "",
     });
 
-    let mut filters = Vec::new();
+    set_sort(&mut search_query, search);
 
-    if let Some(t) = options.asset_type {
-        let asset_type = t.to_string();
-        filters.push(format!("assetType = '{asset_type}'"));
-    }
+    let mut filter = SearchFilter::new();
 
-    let mut path_filter = format!("delimitedPath STARTS WITH '{all_location_name}~'");
-    if let Some(p) = options.location_path {
-        let prepared_path = p.replace('/', "~");
-        path_filter = format!("delimitedPath STARTS WITH '{prepared_path}'");
+    if let Some(t) = &search.asset_type {
+        filter.equals("assetType", t);
     }
-    filters.push(path_filter);
-
-    if let Some(properties) = options.properties {
-        for property in properties {
-            if let Some((property_key_name, property_key_value)) = property.split_once('=') {
-                let property_key_attribute = format!("assetProperty_{} ", property_key_name.trim());
-                filters.push(format!(
-                    "{} = '{}'",
-                    property_key_attribute,
-                    property_key_value.trim()
-                ));
-            } else {
-                error!("Asset property filter was formatted incorrectly. Skipping... '{property}'");
-            }
-        }
+
+    match &search.location_path {
+        Some(p) => filter.starts_with("delimitedPath", p.replace('/', "~")),
+        None => filter.starts_with("delimitedPath", format!("{all_location_name}~")),
+    };
+
+    push_common_filters(&mut filter, search);
+
+    search_query["filter"] = Value::String(filter.to_string());
+
+    search_query
+}
+
+/// Asks for results in the order of `search.sort_keys()`, if there is one.
+fn set_sort(search_query: &mut Value, search: &AssetSearch) {
+    let sort: Vec<String> = search
+        .sort_keys()
+        .iter()
+        .map(SortKey::search_syntax)
+        .collect();
+    if !sort.is_empty() {
+        search_query["sort"] = json!(sort);
     }
+}
 
-    if let Some(custom_properties) = options.custom_properties {
-        for custom_property in custom_properties {
-            if let Some((custom_property_key_name, custom_property_key_value)) =
-                custom_property.split_once('=')
-            {
-                let custom_property_key_attribute =
-                    format!("customProperty_{} ", custom_property_key_name.trim());
-                filters.push(format!(
-                    "{} = '{}'",
-                    custom_property_key_attribute,
-                    custom_property_key_value.trim()
-                ));
-            } else {
-                error!(
-                    "Custom asset property filter was formatted incorrectly. Skipping... '{custom_property}'"
-                );
-            }
-        }
+/// Filters on asset and custom properties, id, manufacturer and product, and `--filter` and
+/// `--raw-filter` expressions, which both kinds of search apply the same way.
+fn push_common_filters(filter: &mut SearchFilter, search: &AssetSearch) {
+    for (property_type, value) in &search.properties {
+        filter.equals(
+            &format!("assetProperty_{}", property_type.trim()),
+            value.trim(),
+        );
     }
 
-    if let Some(id_guid) = options.id {
-        let id_query = format!("id = '{id_guid}'");
-        filters.push(id_query);
+    for (name, value) in &search.custom_properties {
+        filter.equals(&format!("customProperty_{}", name.trim()), value.trim());
     }
 
-    if let Some(manufacturer) = options.manufacturer {
-        let manufacturer_name_query = format!("manufacturerName = '{manufacturer}'");
-        filters.push(manufacturer_name_query);
+    if let Some(id_guid) = search.id {
+        filter.equals("id", id_guid);
     }
 
-    if let Some(product) = options.product {
-        let product_name_query = format!("productName CONTAINS '{product}'");
-        filters.push(product_name_query);
+    if let Some(manufacturer) = &search.manufacturer {
+        filter.equals("manufacturerName", manufacturer);
     }
 
-    let filter_str = filters.join(" AND ");
+    if let Some(product) = &search.product {
+        filter.contains("productName", product);
+    }
 
-    if let Some(filter_field) = search_query.get_mut("filter") {
-        *filter_field = Value::String(filter_str);
+    if let Some(expression) = &search.filter {
+        filter.expression(expression);
     }
 
-    search_query
+    if let Some(expression) = &search.raw_filter {
+        filter.raw(expression);
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::hyperview::cli_data::*;
+    use crate::hyperview::{asset_api_data::AssetTypes, config_data::AppConfig};
 
     use httpmock::prelude::*;
     use serde_json::json;
@@ -924,23 +865,9 @@ mod tests {
             "filter": "delimitedPath STARTS WITH 'All~'"
         });
 
-        let mut options = SearchAssetsArgs {
-            search_pattern: Some("search_pattern".to_string()),
-            asset_type: None,
-            location_path: None,
-            properties: None,
-            custom_properties: None,
-            id: None,
-            manufacturer: None,
-            product: None,
-            limit: 100,
-            skip: 0,
-            filename: None,
-            output_type: OutputOptions::Record,
-            show_property: None,
-        };
+        let search = AssetSearch::new().search_pattern("search_pattern");
 
-        assert_eq!(compose_search_query(options.clone(), "All"), query1);
+        assert_eq!(compose_search_query(&search, "All"), query1);
 
         // Test with asset type and location set
         let mut filter = Vec::new();
@@ -957,10 +884,89 @@ mod tests {
             *filter_field = Value::String(filter_str);
         }
 
-        options.location_path = Some("All/".to_string());
-        options.asset_type = Some(AssetTypes::Server);
+        let search = search.location_path("All/").asset_type(AssetTypes::Server);
 
-        assert_eq!(compose_search_query(options, "All"), query1);
+        assert_eq!(compose_search_query(&search, "All"), query1);
+    }
+
+    #[test]
+    fn test_compose_search_query_escapes_user_values() {
+        let search = AssetSearch::new()
+            .location_path("All/O'Hare DC")
+            .manufacturer("Brother's Racks")
+            .product(r"19\ 'rack")
+            .custom_property("Cost Center", "x' OR assetType = 'server")
+            .property("assetTag", r#"say "hi""#);
+
+        let query = compose_search_query(&search, "All");
+
+        assert_eq!(
+            query["filter"],
+            r#"delimitedPath STARTS WITH 'All~O\'Hare DC' AND assetProperty_assetTag = 'say "hi"' AND 'customProperty_Cost Center' = 'x\' OR assetType = \'server' AND manufacturerName = 'Brother\'s Racks' AND productName CONTAINS '19\\ \'rack'"#
+        );
+    }
+
+    #[test]
+    fn test_compose_search_query_wraps_filter_expressions() {
+        let search = AssetSearch::new()
+            .asset_type(AssetTypes::Rack)
+            .filter(
+                "path CONTAINS 'Building A' OR path CONTAINS 'Building B'"
+                    .parse()
+                    .unwrap(),
+            )
+            .raw_filter("status = normal OR status = warning");
+
+        let query = compose_search_query(&search, "All");
+
+        assert_eq!(
+            query["filter"],
+            "assetType = 'rack' AND delimitedPath STARTS WITH 'All~' \
+             AND (delimitedPath CONTAINS 'Building A' OR delimitedPath CONTAINS 'Building B') \
+             AND (status = normal OR status = warning)"
+        );
+    }
+
+    #[test]
+    fn test_compose_queries_ask_for_sort_and_default_to_id_for_all() {
+        let search = AssetSearch::new()
+            .sort(SortKey::ascending("path"))
+            .sort(SortKey::descending("name"));
+        assert_eq!(
+            compose_search_query(&search, "All")["sort"],
+            json!(["delimitedPath:asc", "displayName:desc"])
+        );
+
+        assert_eq!(
+            compose_search_query(&AssetSearch::new(), "All").get("sort"),
+            None
+        );
+        assert_eq!(
+            compose_any_of_query("assetTag", &[], &AssetSearch::new().all())["sort"],
+            json!(["id:asc"])
+        );
+    }
+
+    #[test]
+    fn test_compose_any_of_query_applies_search_filters() {
+        let search = AssetSearch::new()
+            .asset_type(AssetTypes::Rack)
+            .location_path("All/DC1")
+            .property("serialNumber", "SN-1")
+            .custom_property("Owner", "Ops")
+            .limit(10);
+
+        let query =
+            compose_any_of_query("assetTag", &["A1".to_string(), "A2".to_string()], &search);
+
+        assert_eq!(query["limit"], 10);
+        assert_eq!(query.get("q"), None);
+        assert_eq!(
+            query["filter"],
+            "assetProperty_assetTag EXISTS AND assetProperty_assetTag IN ['A1', 'A2'] \
+             AND assetType = 'rack' AND delimitedPath STARTS WITH 'All~DC1' \
+             AND assetProperty_serialNumber = 'SN-1' AND customProperty_Owner = 'Ops'"
+        );
     }
 
     #[tokio::test]
@@ -990,26 +996,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
-        let auth_header = "Bearer test_token".to_string();
-
-        let options = SearchAssetsArgs {
-            search_pattern: Some("labworker16".to_string()),
-            asset_type: None,
-            location_path: None,
-            properties: None,
-            custom_properties: None,
-            id: None,
-            manufacturer: None,
-            product: None,
-            limit: 100,
-            skip: 0,
-            filename: None,
-            output_type: OutputOptions::Record,
-            show_property: None,
-        };
+        let client = HyperviewClient::for_test(config);
+
+        let search = AssetSearch::new().search_pattern("labworker16");
         // Act
-        let result = search_assets_async(&config, &client, &auth_header, options).await;
+        let result = search_assets_async(&client, &search).await;
 
         // Assert
         m.assert();
@@ -1065,26 +1056,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
-        let auth_header = "Bearer test_token".to_string();
-
-        let options = SearchAssetsArgs {
-            search_pattern: Some("rack".to_string()),
-            asset_type: None,
-            location_path: None,
-            properties: None,
-            custom_properties: None,
-            id: None,
-            manufacturer: None,
-            product: None,
-            limit: 100,
-            skip: 0,
-            filename: None,
-            output_type: OutputOptions::Record,
-            show_property: None,
-        };
+        let client = HyperviewClient::for_test(config);
 
-        let result = search_assets_async(&config, &client, &auth_header, options).await;
+        let search = AssetSearch::new().search_pattern("rack");
+
+        let result = search_assets_async(&client, &search).await;
 
         m.assert();
         all_location_mock.assert();
@@ -1104,11 +1080,184 @@ mod tests {
         assert!(assets[0].status.is_empty());
     }
 
+    fn search_page_resp(first: usize, count: usize) -> Value {
+        let hits: Vec<Value> = (first..first + count)
+            .map(|i| {
+                json!({
+                    "id": format!("00000000-0000-0000-0000-{i:012}"),
+                    "displayName": format!("rack-{i}"),
+                    "assetType": "Rack",
+                })
+            })
+            .collect();
+        json!({"estimatedTotalHits": 4, "limit": 2, "hits": hits})
+    }
+
+    #[tokio::test]
+    async fn test_search_asset_pages_follows_pages_until_short_or_capped() {
+        let server = MockServer::start();
+        let pages: Vec<_> = [(0, 2, 2), (2, 2, 2), (4, 2, 1), (2, 1, 1)]
+            .into_iter()
+            .map(|(offset, limit, count)| {
+                server.mock(|when, then| {
+                    when.method(POST)
+                        .path(ASSET_SEARCH_API_PREFIX)
+                        .json_body_includes(json!({"offset": offset, "limit": limit}).to_string());
+                    then.status(200)
+                        .header("Content-Type", "application/json")
+                        .json_body(search_page_resp(offset, count));
+                })
+            })
+            .collect();
+        server.mock(|when, then| {
+            when.method(GET).path(format!(
+                "{ASSET_ASSETS_API_PREFIX}/11223344-5566-7788-99aa-bbccddeeff00"
+            ));
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"name": "All"}));
+        });
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        // The third page comes back short, so there is no fourth request.
+        let search = AssetSearch::new().limit(2).all();
+        let assets = search_assets_async(&client, &search).await.unwrap();
+        assert_eq!(assets.len(), 5);
+        assert_eq!(assets[4].name, "rack-4");
+        pages[0].assert();
+        pages[1].assert();
+        pages[2].assert();
+
+        // The cap trims the second page to what is left of it.
+        let search = search.max_results(3);
+        let assets = search_assets_async(&client, &search).await.unwrap();
+        assert_eq!(
+            assets.iter().map(|a| a.name.as_str()).collect::<Vec<_>>(),
+            ["rack-0", "rack-1", "rack-2"]
+        );
+        pages[3].assert();
+    }
+
+    #[tokio::test]
+    async fn test_search_sorts_here_when_the_api_rejects_the_sort() {
+        let server = MockServer::start();
+        let rejected = server.mock(|when, then| {
+            when.method(POST)
+                .path(ASSET_SEARCH_API_PREFIX)
+                .json_body_includes(json!({"sort": ["displayName:desc"]}).to_string());
+            then.status(400)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"message": "Attribute `displayName` is not sortable"}));
+        });
+        for (offset, count) in [(0, 2), (2, 1)] {
+            server.mock(|when, then| {
+                when.method(POST)
+                    .path(ASSET_SEARCH_API_PREFIX)
+                    .body_excludes("\"sort\"")
+                    .json_body_includes(json!({"offset": offset}).to_string());
+                then.status(200)
+                    .header("Content-Type", "application/json")
+                    .json_body(search_page_resp(offset, count));
+            });
+        }
+        server.mock(|when, then| {
+            when.method(GET).path(format!(
+                "{ASSET_ASSETS_API_PREFIX}/11223344-5566-7788-99aa-bbccddeeff00"
+            ));
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"name": "All"}));
+        });
+        let client = HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        });
+
+        let search = AssetSearch::new()
+            .limit(2)
+            .all()
+            .sort("name:desc".parse().unwrap());
+        let pages: Vec<SearchPage> = search_asset_pages(&client, &search)
+            .try_collect()
+            .await
+            .unwrap();
+
+        rejected.assert();
+        // Every asset comes in the last page, once the order is known.
+        assert!(pages[0].assets.is_empty());
+        assert_eq!(pages[0].fetched, 2);
+        assert_eq!(
+            pages[1]
+                .assets
+                .iter()
+                .map(|a| a.name.as_str())
+                .collect::<Vec<_>>(),
+            ["rack-2", "rack-1", "rack-0"]
+        );
+    }
+
+    fn all_location_client(server: &MockServer) -> HyperviewClient {
+        server.mock(|when, then| {
+            when.method(GET).path(format!(
+                "{ASSET_ASSETS_API_PREFIX}/11223344-5566-7788-99aa-bbccddeeff00"
+            ));
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"name": "All"}));
+        });
+        HyperviewClient::for_test(AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        })
+    }
+
+    #[tokio::test]
+    async fn test_search_rejected_for_its_filter_is_sent_once() {
+        let server = MockServer::start();
+        let rejected = server.mock(|when, then| {
+            when.method(POST).path(ASSET_SEARCH_API_PREFIX);
+            then.status(400)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"message": "Attribute `colour` is not filterable"}));
+        });
+        let client = all_location_client(&server);
+
+        // `--all` asks for a sort by id, which must not be blamed for the filter.
+        let search = AssetSearch::new().all().raw_filter("colour = red");
+        let err = search_assets_async(&client, &search).await.unwrap_err();
+
+        rejected.assert();
+        let api_error = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(api_error.status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_single_page_search_fails_when_the_api_rejects_the_sort() {
+        let server = MockServer::start();
+        let rejected = server.mock(|when, then| {
+            when.method(POST).path(ASSET_SEARCH_API_PREFIX);
+            then.status(400)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"message": "Attribute `displayName` is not sortable"}));
+        });
+        let client = all_location_client(&server);
+
+        let search = AssetSearch::new().skip(100).sort("name".parse().unwrap());
+        let err = search_assets_async(&client, &search).await.unwrap_err();
+
+        rejected.assert();
+        assert!(matches!(
+            err.downcast_ref::<AppError>(),
+            Some(AppError::SortUnsupported)
+        ));
+    }
+
     #[tokio::test]
     async fn test_bulk_add_rack_accessory_async_continues_after_row_error() {
-        use crate::hyperview::auth::AuthToken;
         use std::io::Write;
-        use std::time::Duration;
 
         let rack_fail = Uuid::new_v4();
         let rack_ok = Uuid::new_v4();
@@ -1132,8 +1281,7 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
-        let mut token = AuthToken::for_test("Bearer t", Duration::from_hours(1));
+        let client = HyperviewClient::for_test(config);
 
         let mut tmp = tempfile::NamedTempFile::new().unwrap();
         writeln!(tmp, "id,panel_type,side,u_location").unwrap();
@@ -1141,14 +1289,9 @@ mod tests {
         writeln!(tmp, "{rack_ok},BlankingPanel,Front,2").unwrap();
         tmp.flush().unwrap();
 
-        bulk_add_rack_accessory_async(
-            &config,
-            &client,
-            &mut token,
-            &tmp.path().to_string_lossy().to_string(),
-        )
-        .await
-        .expect("bulk add must not abort on a per-row 500");
+        bulk_add_rack_accessory_async(&client, &tmp.path().to_string_lossy().to_string(), 1)
+            .await
+            .expect("bulk add must not abort on a per-row 500");
 
         fail_mock.assert();
         ok_mock.assert();
@@ -2,7 +2,7 @@ use serde::{Deserialize, Serialize};
 use std::fmt;
 use uuid::Uuid;
 
-use super::cli_data::AssetTypes;
+use super::asset_api_data::AssetTypes;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PowerProviderComponentDto {
@@ -1,214 +1,177 @@
-use log::{debug, error};
-use reqwest::{
-    Client,
-    header::{ACCEPT, AUTHORIZATION, CONTENT_TYPE},
-};
+use log::debug;
 use serde_json::Map;
 use std::collections::HashMap;
+use std::sync::Mutex;
 use uuid::Uuid;
 
 use crate::hyperview::{
     api_constants::{
         BUSWAY_TAPOFF_API_PREFIX, PDU_RPP_BREAKERS_API_PREFIX, RACK_PDU_OUTLETS_API_PREFIX,
     },
-    cli_data::AssetTypes,
+    asset_api_data::AssetTypes,
 };
-use crate::retry_on_unauthorized_async;
 
 use super::{
+    api_client::HyperviewClient,
     api_constants::POWER_ASSOCIATION_API_PREFIX,
     asset_power_api_data::{
         BulkPowerAssociationCreateDto, PowerAssociationCreateDto, PowerProviderComponentDto,
     },
-    auth::AuthToken,
-    cli_data::AppConfig,
+    bulk::run_rows_async,
 };
 
 pub async fn get_power_provider_components_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     api_path: &str,
     id: Uuid,
 ) -> color_eyre::Result<Vec<PowerProviderComponentDto>> {
     let mut query_params = Map::new();
 
-    let target_url = if api_path == PDU_RPP_BREAKERS_API_PREFIX {
+    let target_path = if api_path == PDU_RPP_BREAKERS_API_PREFIX {
         query_params.insert(
             "assetId".to_string(),
             serde_json::Value::String(id.to_string()),
         );
-        format!("{}{}", config.instance_url, api_path)
+        api_path.to_string()
     } else {
-        format!("{}{}/{}", config.instance_url, api_path, id)
+        format!("{api_path}/{id}")
     };
 
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .query(&query_params)
-        .header(AUTHORIZATION, auth_header)
-        .header(CONTENT_TYPE, "application/json")
-        .header(ACCEPT, "application/json")
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<PowerProviderComponentDto>>()
-        .await?;
-
-    Ok(resp)
+    client.get_with_query(&target_path, &query_params).await
 }
 
+/// A power provider's components: (Component Number, Optional Panel Number): Component Id
+type ComponentMap = HashMap<(u64, Option<u64>), Uuid>;
+
 pub async fn bulk_add_power_association_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: &String,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
-    // Asset ID : (Component Number, Optional Panel Number): Component Id
-    let mut power_provider_component_map: HashMap<Uuid, HashMap<(u64, Option<u64>), Uuid>> =
-        HashMap::new();
-
-    let mut reader = csv::Reader::from_path(filename)?;
-
-    while let Some(Ok(record)) = reader.deserialize::<BulkPowerAssociationCreateDto>().next() {
-        auth_token.refresh_if_needed_async(config).await?;
-
-        debug!("updating asset id {}", record.asset_id);
-
-        if record.provider_component_number.is_none() {
-            debug!("Component number is not asset, assuming direct asset to asset association");
-            if let Err(e) = retry_on_unauthorized_async!(
-                config,
-                auth_token,
-                add_power_association_async(
-                    config,
-                    req,
-                    &auth_token.header,
+    let power_provider_component_map: Mutex<HashMap<Uuid, ComponentMap>> =
+        Mutex::new(HashMap::new());
+    let power_provider_component_map = &power_provider_component_map;
+
+    let records: Vec<BulkPowerAssociationCreateDto> = csv::Reader::from_path(filename)?
+        .deserialize()
+        .map_while(Result::ok)
+        .collect();
+
+    // Rows are grouped by the asset being powered, so rows for one asset run in file order.
+    // Rows for different assets may share a provider, so its components can be fetched more
+    // than once when its first rows run at the same time; the cache still saves every later
+    // fetch.
+    run_rows_async(
+        records,
+        concurrency,
+        |record| Some(record.asset_id),
+        |record| async move {
+            debug!("updating asset id {}", record.asset_id);
+
+            let Some(component_number) = record.provider_component_number else {
+                debug!("Component number is not asset, assuming direct asset to asset association");
+                return add_power_association_async(
+                    client,
                     record.asset_id,
                     record.provider_asset_id,
                 )
                 .await
-            ) {
-                error!(
-                    "Failed to add power association for asset id {}: {e}",
-                    record.asset_id
-                );
-            }
-
-            continue;
-        }
-
-        // Cache the component mapping (e.g. Outlets) to make the work faster
-        if !power_provider_component_map.contains_key(&record.provider_asset_id) {
-            let api_path = match record.provider_asset_type {
-                AssetTypes::PduAndRpp => Some(PDU_RPP_BREAKERS_API_PREFIX),
-                AssetTypes::RackPdu => Some(RACK_PDU_OUTLETS_API_PREFIX),
-                AssetTypes::Busway => Some(BUSWAY_TAPOFF_API_PREFIX),
-                _ => None,
+                .map_err(|e| {
+                    format!(
+                        "Failed to add power association for asset id {}: {e}",
+                        record.asset_id
+                    )
+                });
             };
 
-            if api_path.is_none() {
-                continue;
+            let is_mapped = power_provider_component_map
+                .lock()
+                .expect("component map lock poisoned")
+                .contains_key(&record.provider_asset_id);
+
+            // Cache the component mapping (e.g. Outlets) to make the work faster
+            if !is_mapped {
+                let api_path = match record.provider_asset_type {
+                    AssetTypes::PduAndRpp => PDU_RPP_BREAKERS_API_PREFIX,
+                    AssetTypes::RackPdu => RACK_PDU_OUTLETS_API_PREFIX,
+                    AssetTypes::Busway => BUSWAY_TAPOFF_API_PREFIX,
+                    _ => return Ok(()),
+                };
+
+                let component_map =
+                    get_provider_component_map_async(client, record.provider_asset_id, api_path)
+                        .await
+                        .map_err(|e| {
+                            format!(
+                                "Failed to fetch power provider components for asset id {}: {e}",
+                                record.provider_asset_id
+                            )
+                        })?;
+
+                power_provider_component_map
+                    .lock()
+                    .expect("component map lock poisoned")
+                    .insert(record.provider_asset_id, component_map);
             }
 
-            if let Err(e) = retry_on_unauthorized_async!(
-                config,
-                auth_token,
-                get_provider_component_map_async(
-                    config,
-                    req,
-                    &auth_token.header,
-                    record.provider_asset_id,
-                    api_path.expect("Expect API path variable to be set at this point"),
-                    &mut power_provider_component_map,
-                )
-                .await
-            ) {
-                error!(
-                    "Failed to fetch power provider components for asset id {}: {e}",
-                    record.provider_asset_id
-                );
-                continue;
+            let component_id = power_provider_component_map
+                .lock()
+                .expect("component map lock poisoned")
+                .get(&record.provider_asset_id)
+                .and_then(|component_map| {
+                    component_map
+                        .get(&(component_number, record.provider_panel_number))
+                        .copied()
+                });
+
+            // Add power association
+            match component_id {
+                Some(component_id) => {
+                    add_power_association_async(client, record.asset_id, component_id)
+                        .await
+                        .map_err(|e| {
+                            format!(
+                                "Failed to add power association for asset id {}: {e}",
+                                record.asset_id
+                            )
+                        })
+                }
+                None => Ok(()),
             }
-        }
-
-        // Add power association
-        if let Some(component_map) = power_provider_component_map.get(&record.provider_asset_id)
-            && let Some(component_id) = component_map.get(&(
-                record
-                    .provider_component_number
-                    .expect("Expect component number to be set"),
-                record.provider_panel_number,
-            ))
-            && let Err(e) = retry_on_unauthorized_async!(
-                config,
-                auth_token,
-                add_power_association_async(
-                    config,
-                    req,
-                    &auth_token.header,
-                    record.asset_id,
-                    *component_id,
-                )
-                .await
-            )
-        {
-            error!(
-                "Failed to add power association for asset id {}: {e}",
-                record.asset_id
-            );
-        }
-    }
+        },
+    )
+    .await;
 
     Ok(())
 }
 
 async fn get_provider_component_map_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     provider_asset_id: Uuid,
     api_path: &str,
-    power_provider_component_map: &mut HashMap<Uuid, HashMap<(u64, Option<u64>), Uuid>>,
-) -> color_eyre::Result<()> {
-    let mut component_map: HashMap<(u64, Option<u64>), Uuid> = HashMap::new();
-
+) -> color_eyre::Result<ComponentMap> {
     let component_list =
-        get_power_provider_components_async(config, req, auth_header, api_path, provider_asset_id)
-            .await?;
+        get_power_provider_components_async(client, api_path, provider_asset_id).await?;
 
-    for component in component_list {
-        component_map.insert((component.number, component.panel_number), component.id);
-    }
-
-    power_provider_component_map.insert(provider_asset_id, component_map);
-
-    Ok(())
+    Ok(component_list
+        .into_iter()
+        .map(|component| ((component.number, component.panel_number), component.id))
+        .collect())
 }
 
 pub async fn add_power_association_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     power_consuming_asset_id: Uuid,
     power_providing_asset_id: Uuid,
 ) -> color_eyre::Result<()> {
-    let target_url = format!("{}{}", config.instance_url, POWER_ASSOCIATION_API_PREFIX);
-    debug!("Request URL: {target_url}");
-
     let association_data = PowerAssociationCreateDto {
         consuming_destination_asset_id: power_consuming_asset_id,
         providing_source_asset_id: power_providing_asset_id,
     };
 
-    req.post(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&association_data)
-        .send()
-        .await?
-        .error_for_status()?;
+    client
+        .post(POWER_ASSOCIATION_API_PREFIX, &association_data)
+        .await?;
 
     Ok(())
 }
@@ -216,14 +179,10 @@ pub async fn add_power_association_async(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
     use std::io::Write;
-    use std::time::Duration;
-
-    fn auth_token() -> AuthToken {
-        AuthToken::for_test("Bearer test_token", Duration::from_hours(1))
-    }
 
     fn write_csv(rows: &[&str]) -> tempfile::NamedTempFile {
         let mut tmp = tempfile::NamedTempFile::new().unwrap();
@@ -261,18 +220,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth = "Bearer t".to_string();
-
-        let result = get_power_provider_components_async(
-            &config,
-            &client,
-            &auth,
-            RACK_PDU_OUTLETS_API_PREFIX,
-            provider_id,
-        )
-        .await
-        .unwrap();
+        let client = HyperviewClient::for_test(config);
+
+        let result =
+            get_power_provider_components_async(&client, RACK_PDU_OUTLETS_API_PREFIX, provider_id)
+                .await
+                .unwrap();
 
         m.assert();
         assert_eq!(result.len(), 1);
@@ -302,18 +255,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth = "Bearer t".to_string();
-
-        let result = get_power_provider_components_async(
-            &config,
-            &client,
-            &auth,
-            PDU_RPP_BREAKERS_API_PREFIX,
-            provider_id,
-        )
-        .await
-        .unwrap();
+        let client = HyperviewClient::for_test(config);
+
+        let result =
+            get_power_provider_components_async(&client, PDU_RPP_BREAKERS_API_PREFIX, provider_id)
+                .await
+                .unwrap();
 
         m.assert();
         assert_eq!(result.len(), 1);
@@ -339,19 +286,13 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         // No component number — direct asset-to-asset path.
         let csv = write_csv(&[&format!("{consumer},{provider},rackPdu,,")]);
 
-        bulk_add_power_association_async(
-            &config,
-            &client,
-            &mut token,
-            &csv.path().to_string_lossy().to_string(),
-        )
-        .await
-        .unwrap();
+        bulk_add_power_association_async(&client, &csv.path().to_string_lossy().to_string(), 1)
+            .await
+            .unwrap();
 
         assoc_mock.assert();
     }
@@ -376,18 +317,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_csv(&[&format!("{consumer},{provider},Location,1,")]);
 
-        bulk_add_power_association_async(
-            &config,
-            &client,
-            &mut token,
-            &csv.path().to_string_lossy().to_string(),
-        )
-        .await
-        .unwrap();
+        bulk_add_power_association_async(&client, &csv.path().to_string_lossy().to_string(), 1)
+            .await
+            .unwrap();
 
         any_get.assert_calls(0);
         any_post.assert_calls(0);
@@ -424,27 +359,54 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         // Two rows pointing at the same provider — the component list should only be fetched once.
         let csv = write_csv(&[
             &format!("{consumer_a},{provider},rackPdu,1,"),
             &format!("{consumer_b},{provider},rackPdu,1,"),
         ]);
 
-        bulk_add_power_association_async(
-            &config,
-            &client,
-            &mut token,
-            &csv.path().to_string_lossy().to_string(),
-        )
-        .await
-        .unwrap();
+        bulk_add_power_association_async(&client, &csv.path().to_string_lossy().to_string(), 1)
+            .await
+            .unwrap();
 
         list_mock.assert_calls(1);
         assoc_mock.assert_calls(2);
     }
 
+    #[tokio::test]
+    async fn test_bulk_add_power_association_runs_rows_for_one_asset_in_order() {
+        let consumer = Uuid::new_v4();
+        let delay = std::time::Duration::from_millis(200);
+
+        let server = MockServer::start();
+        let assoc_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path(POWER_ASSOCIATION_API_PREFIX)
+                .body_includes(consumer.to_string());
+            then.status(200).delay(delay);
+        });
+
+        let config = AppConfig {
+            instance_url: format!("http://{}", server.address()),
+            ..Default::default()
+        };
+        let client = HyperviewClient::for_test(config);
+        // One asset fed by two providers: the rows must not overlap, however many may run.
+        let csv = write_csv(&[
+            &format!("{consumer},{},rackPdu,,", Uuid::new_v4()),
+            &format!("{consumer},{},rackPdu,,", Uuid::new_v4()),
+        ]);
+
+        let started = std::time::Instant::now();
+        bulk_add_power_association_async(&client, &csv.path().to_string_lossy().to_string(), 4)
+            .await
+            .unwrap();
+
+        assoc_mock.assert_calls(2);
+        assert!(started.elapsed() >= delay * 2);
+    }
+
     #[tokio::test]
     async fn test_bulk_add_power_association_continues_after_association_error() {
         let consumer_fail = Uuid::new_v4();
@@ -469,22 +431,16 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         // Direct asset-to-asset path so the cache lookup is bypassed.
         let csv = write_csv(&[
             &format!("{consumer_fail},{provider},rackPdu,,"),
             &format!("{consumer_ok},{provider},rackPdu,,"),
         ]);
 
-        bulk_add_power_association_async(
-            &config,
-            &client,
-            &mut token,
-            &csv.path().to_string_lossy().to_string(),
-        )
-        .await
-        .expect("bulk add should not abort on a per-row 500");
+        bulk_add_power_association_async(&client, &csv.path().to_string_lossy().to_string(), 1)
+            .await
+            .expect("bulk add should not abort on a per-row 500");
 
         fail_mock.assert();
         ok_mock.assert();
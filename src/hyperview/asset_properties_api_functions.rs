@@ -1,62 +1,59 @@
-use log::{debug, error, trace};
-use reqwest::{Client, header::AUTHORIZATION};
+use log::{debug, trace};
 use uuid::Uuid;
 
-use crate::retry_on_unauthorized_async;
-
 use super::{
+    api_client::HyperviewClient,
     api_constants::ASSET_PROPERTIES_API_PREFIX,
     app_errors::AppError,
     asset_properties_api_data::{AssetPropertyDto, AssetPropertyImportDto},
-    auth::AuthToken,
-    cli_data::AppConfig,
+    bulk::run_rows_async,
     common_types::MultiTypeValue,
 };
 
 pub async fn bulk_update_asset_property_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: String,
     asset_property_type: String,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
-    let mut reader = csv::Reader::from_path(filename)?;
-
-    while let Some(Ok(record)) = reader.deserialize::<AssetPropertyImportDto>().next() {
-        auth_token.refresh_if_needed_async(config).await?;
-
-        if let Err(e) = retry_on_unauthorized_async!(
-            config,
-            auth_token,
+    let records: Vec<AssetPropertyImportDto> = csv::Reader::from_path(filename)?
+        .deserialize()
+        .map_while(Result::ok)
+        .collect();
+    let asset_property_type = &asset_property_type;
+
+    run_rows_async(
+        records,
+        concurrency,
+        |record| Some(record.asset_id),
+        |record| async move {
             update_asset_property_async(
-                config,
-                req,
-                &auth_token.header,
+                client,
                 record.asset_id,
-                record.new_value.clone(),
+                record.new_value,
                 asset_property_type.clone(),
             )
             .await
-        ) {
-            error!(
-                "Failed to update {asset_property_type} for asset id {}: {e}",
-                record.asset_id
-            );
-        }
-    }
+            .map_err(|e| {
+                format!(
+                    "Failed to update {asset_property_type} for asset id {}: {e}",
+                    record.asset_id
+                )
+            })
+        },
+    )
+    .await;
+
     Ok(())
 }
 
 pub async fn update_asset_property_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     id: Uuid,
     new_value: String,
     asset_property_type: String,
 ) -> color_eyre::Result<()> {
-    let current_values =
-        get_named_asset_property_async(config, req, auth_header, id, asset_property_type).await?;
+    let current_values = get_named_asset_property_async(client, id, asset_property_type).await?;
 
     debug!(
         "Current property values: {}",
@@ -92,88 +89,47 @@ pub async fn update_asset_property_async(
 
         trace!("Payload: {}", serde_json::to_string_pretty(&payload)?);
 
-        if let Some(id) = payload.id {
+        let resp = if let Some(id) = payload.id {
             // Updating an existing value
-            let target_url = format!(
-                "{}{}/{}",
-                config.instance_url, ASSET_PROPERTIES_API_PREFIX, id
-            );
-            debug!("Request URL: {target_url}");
-
-            let resp = req
-                .put(target_url)
-                .header(AUTHORIZATION, auth_header)
-                .json(&payload)
-                .send()
+            client
+                .put(&format!("{ASSET_PROPERTIES_API_PREFIX}/{id}"), &payload)
                 .await?
-                .error_for_status()?
-                .json::<serde_json::Value>()
-                .await?;
-
-            debug!(
-                "Update serial number: {}",
-                serde_json::to_string_pretty(&resp)?
-            );
         } else {
             // Setting serial number for the first time
-            let target_url = format!(
-                "{}{}/?assetId={}",
-                config.instance_url, ASSET_PROPERTIES_API_PREFIX, id
-            );
-            debug!("Request URL: {target_url}");
-
-            let resp = req
-                .post(target_url)
-                .header(AUTHORIZATION, auth_header)
-                .json(&payload)
-                .send()
+            client
+                .post(
+                    &format!("{ASSET_PROPERTIES_API_PREFIX}/?assetId={id}"),
+                    &payload,
+                )
                 .await?
-                .error_for_status()?
-                .json::<serde_json::Value>()
-                .await?;
-
-            debug!(
-                "Update serial number: {}",
-                serde_json::to_string_pretty(&resp)?
-            );
         }
+        .json::<serde_json::Value>()
+        .await?;
+
+        debug!(
+            "Update serial number: {}",
+            serde_json::to_string_pretty(&resp)?
+        );
     }
 
     Ok(())
 }
 
 pub async fn get_asset_property_list_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     id: Uuid,
 ) -> color_eyre::Result<Vec<AssetPropertyDto>> {
-    let target_url = format!(
-        "{}{}/{}",
-        config.instance_url, ASSET_PROPERTIES_API_PREFIX, id
-    );
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<AssetPropertyDto>>()
-        .await?;
-
-    Ok(resp)
+    client
+        .get(&format!("{ASSET_PROPERTIES_API_PREFIX}/{id}"))
+        .await
 }
 
 pub async fn get_named_asset_property_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     id: Uuid,
     property_type: String,
 ) -> color_eyre::Result<Vec<AssetPropertyDto>> {
-    let property_list = get_asset_property_list_async(config, req, auth_header, id)
+    let property_list = get_asset_property_list_async(client, id)
         .await?
         .into_iter()
         .filter(|p| p.property_type == property_type)
@@ -186,16 +142,12 @@ pub async fn get_named_asset_property_async(
 mod tests {
     use std::io::Write;
     use std::str::FromStr;
-    use std::time::Duration;
 
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
 
-    fn auth_token() -> AuthToken {
-        AuthToken::for_test("Bearer test_token", Duration::from_hours(1))
-    }
-
     fn property_json(
         id: &str,
         property_type: &str,
@@ -281,11 +233,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
         // Act
-        let result = get_asset_property_list_async(&config, &client, &auth_header, asset_id).await;
+        let result = get_asset_property_list_async(&client, asset_id).await;
 
         // Assert
         m.assert();
@@ -328,18 +279,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
-        let auth = "Bearer t".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let result = get_named_asset_property_async(
-            &config,
-            &client,
-            &auth,
-            asset_id,
-            "assetTag".to_string(),
-        )
-        .await
-        .unwrap();
+        let result = get_named_asset_property_async(&client, asset_id, "assetTag".to_string())
+            .await
+            .unwrap();
 
         assert_eq!(result.len(), 1);
         assert_eq!(result[0].property_type, "assetTag");
@@ -378,12 +322,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
+        let client = HyperviewClient::for_test(config);
 
         update_asset_property_async(
-            &config,
             &client,
-            &"Bearer t".to_string(),
             asset_id,
             "new-tag".to_string(),
             "assetTag".to_string(),
@@ -427,12 +369,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
+        let client = HyperviewClient::for_test(config);
 
         update_asset_property_async(
-            &config,
             &client,
-            &"Bearer t".to_string(),
             asset_id,
             "12.5".to_string(),
             "designValue".to_string(),
@@ -471,12 +411,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
+        let client = HyperviewClient::for_test(config);
 
         let result = update_asset_property_async(
-            &config,
             &client,
-            &"Bearer t".to_string(),
             asset_id,
             "not-a-number".to_string(),
             "designValue".to_string(),
@@ -517,12 +455,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
+        let client = HyperviewClient::for_test(config);
 
         let result = update_asset_property_async(
-            &config,
             &client,
-            &"Bearer t".to_string(),
             asset_id,
             "new".to_string(),
             "assetTag".to_string(),
@@ -570,21 +506,21 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_property_csv(&[(asset_fail, "fail"), (asset_ok, "ok")]);
 
         bulk_update_asset_property_async(
-            &config,
             &client,
-            &mut token,
             csv.path().to_string_lossy().to_string(),
             "assetTag".to_string(),
+            1,
         )
         .await
         .expect("bulk update must not abort on a per-row error");
 
-        get_fail_mock.assert();
+        // The 500 is retried up to the default max_attempts before the row is given up.
+
+        get_fail_mock.assert_calls(4);
         get_ok_mock.assert();
         put_ok_mock.assert();
     }
@@ -1,101 +1,104 @@
-use log::{debug, error, trace};
-use reqwest::{Client, header::AUTHORIZATION};
-use std::{collections::HashMap, str::FromStr};
+use log::{debug, trace};
+use std::{collections::HashMap, str::FromStr, sync::Mutex};
 use uuid::Uuid;
 
-use crate::retry_on_unauthorized_async;
-
 use super::{
+    api_client::HyperviewClient,
     api_constants::{SENSOR_API_PREFIX, SENSOR_DAILY_SUMMARIES_NUMERIC_API_PREFIX},
     asset_sensor_api_data::{AssetSensorDto, AssetSensorUpdateDto, NumericSensorDailySummaryDto},
-    auth::AuthToken,
-    cli_data::AppConfig,
+    bulk::run_rows_async,
 };
 
 pub async fn bulk_update_asset_sensor_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: &String,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
-    let mut asset_sensors_map: HashMap<String, HashMap<String, AssetSensorDto>> = HashMap::new();
-    let mut reader = csv::Reader::from_path(filename)?;
-    let mut total: usize = 0;
-    let mut failed: usize = 0;
-
-    while let Some(Ok(mut record)) = reader.deserialize::<AssetSensorUpdateDto>().next() {
-        auth_token.refresh_if_needed_async(config).await?;
-        total += 1;
-
-        debug!("updating sensor_id {}", record.sensor_id);
-
-        // if the asset is not mapped, lookup and cache sensor mapping
-        if !asset_sensors_map.contains_key(&record.asset_id.to_string()) {
-            match retry_on_unauthorized_async!(
-                config,
-                auth_token,
-                get_asset_sensor_list_async(config, req, &auth_token.header, record.asset_id).await
-            ) {
-                Ok(sensors) => {
-                    map_asset_sensors(record.asset_id.to_string(), sensors, &mut asset_sensors_map);
-                }
-                Err(e) => {
-                    // Without the sensor list we can't tell whether to preserve or reset the
-                    // access policy, so PUTting the CSV row's (often None) value would silently
-                    // reset the sensor's ACL to its parent. Skip this row instead.
-                    error!(
-                        "Failed to fetch sensor list for asset {}: {e}; skipping sensor {}",
-                        record.asset_id, record.sensor_id
-                    );
-                    failed += 1;
-                    continue;
+    let asset_sensors_map: Mutex<HashMap<String, HashMap<String, AssetSensorDto>>> =
+        Mutex::new(HashMap::new());
+    let records: Vec<AssetSensorUpdateDto> = csv::Reader::from_path(filename)?
+        .deserialize()
+        .map_while(Result::ok)
+        .collect();
+    let total = records.len();
+    let asset_sensors_map = &asset_sensors_map;
+
+    // Rows for one asset run in order, so its sensor list is fetched once, by its first row.
+    let failed = run_rows_async(
+        records,
+        concurrency,
+        |record| Some(record.asset_id),
+        |mut record| async move {
+            debug!("updating sensor_id {}", record.sensor_id);
+
+            let asset_id = record.asset_id.to_string();
+            let is_mapped = asset_sensors_map
+                .lock()
+                .expect("sensor map lock poisoned")
+                .contains_key(&asset_id);
+
+            // if the asset is not mapped, lookup and cache sensor mapping
+            if !is_mapped {
+                match get_asset_sensor_list_async(client, record.asset_id).await {
+                    Ok(sensors) => {
+                        map_asset_sensors(
+                            asset_id.clone(),
+                            sensors,
+                            &mut asset_sensors_map.lock().expect("sensor map lock poisoned"),
+                        );
+                    }
+                    Err(e) => {
+                        // Without the sensor list we can't tell whether to preserve or reset the
+                        // access policy, so PUTting the CSV row's (often None) value would
+                        // silently reset the sensor's ACL to its parent. Skip this row instead.
+                        return Err(format!(
+                            "Failed to fetch sensor list for asset {}: {e}; skipping sensor {}",
+                            record.asset_id, record.sensor_id
+                        ));
+                    }
                 }
             }
-        }
 
-        // If the sensor exists update name and access policy
-        if let Some(sensor) = get_sensor_record(
-            &record.asset_id.to_string(),
-            &record.sensor_id.to_string(),
-            &asset_sensors_map,
-        ) {
-            // If the access policy is None and is not inherited, leave as is, do not reset to parent
-            if record.access_policy_id.is_none() && !sensor.access_policy_is_inherited {
-                debug!(
-                    "Update record does not set access policy. Keeping original: {}",
-                    &sensor.access_policy_id
-                );
-                match Uuid::from_str(&sensor.access_policy_id) {
-                    Ok(uuid) => record.access_policy_id = Some(uuid),
-                    Err(e) => {
-                        // Skip this row instead of aborting the whole bulk run.
-                        error!(
-                            "Failed to parse access policy id {:?} for sensor {}: {e}",
-                            sensor.access_policy_id, record.sensor_id
-                        );
-                        failed += 1;
-                        continue;
+            let sensor = get_sensor_record(
+                &asset_id,
+                &record.sensor_id.to_string(),
+                &asset_sensors_map.lock().expect("sensor map lock poisoned"),
+            );
+
+            // If the sensor exists update name and access policy
+            if let Some(sensor) = sensor {
+                // If the access policy is None and is not inherited, leave as is, do not reset to parent
+                if record.access_policy_id.is_none() && !sensor.access_policy_is_inherited {
+                    debug!(
+                        "Update record does not set access policy. Keeping original: {}",
+                        &sensor.access_policy_id
+                    );
+                    match Uuid::from_str(&sensor.access_policy_id) {
+                        Ok(uuid) => record.access_policy_id = Some(uuid),
+                        Err(e) => {
+                            // Skip this row instead of aborting the whole bulk run.
+                            return Err(format!(
+                                "Failed to parse access policy id {:?} for sensor {}: {e}",
+                                sensor.access_policy_id, record.sensor_id
+                            ));
+                        }
                     }
+                } else if let Some(record_access_policy) = record.access_policy_id
+                    && record_access_policy.is_nil()
+                {
+                    debug!("Nil UUID detected. Resetting to parent access policy");
+                    record.access_policy_id = None;
                 }
-            } else if let Some(record_access_policy) = record.access_policy_id
-                && record_access_policy.is_nil()
-            {
-                debug!("Nil UUID detected. Resetting to parent access policy");
-                record.access_policy_id = None;
             }
-        }
 
-        trace!("Sensor record: {}", serde_json::to_string(&record)?);
+            trace!("Sensor record: {record:?}");
 
-        if let Err(e) = retry_on_unauthorized_async!(
-            config,
-            auth_token,
-            update_asset_sensor_async(config, req, &auth_token.header, &record).await
-        ) {
-            error!("Failed to update sensor id {}: {e}", record.sensor_id);
-            failed += 1;
-        }
-    }
+            update_asset_sensor_async(client, &record)
+                .await
+                .map_err(|e| format!("Failed to update sensor id {}: {e}", record.sensor_id))
+        },
+    )
+    .await;
 
     if failed > 0 {
         return Err(super::app_errors::AppError::BulkOperationFailures { failed, total }.into());
@@ -132,59 +135,27 @@ fn get_sensor_record(
 }
 
 async fn update_asset_sensor_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     sensor: &AssetSensorUpdateDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!("{}{}", config.instance_url, SENSOR_API_PREFIX);
-    debug!("Request URL: {target_url}");
-
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.put(SENSOR_API_PREFIX, sensor).await?;
 
     Ok(())
 }
 
 pub async fn get_asset_sensor_list_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     id: Uuid,
 ) -> color_eyre::Result<Vec<AssetSensorDto>> {
-    let target_url = format!("{}{}/{}", config.instance_url, SENSOR_API_PREFIX, id);
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<AssetSensorDto>>()
-        .await?;
-
-    Ok(resp)
+    client.get(&format!("{SENSOR_API_PREFIX}/{id}")).await
 }
 
 pub async fn get_numeric_sensor_daily_summaries_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     sensor_ids: &[Uuid],
     start: chrono::NaiveDate,
     end: chrono::NaiveDate,
 ) -> color_eyre::Result<Vec<NumericSensorDailySummaryDto>> {
-    let target_url = format!(
-        "{}{}",
-        config.instance_url, SENSOR_DAILY_SUMMARIES_NUMERIC_API_PREFIX
-    );
-    debug!("Request URL: {target_url}");
-
     let mut query: Vec<(&str, String)> = sensor_ids
         .iter()
         .map(|id| ("sensorIds", id.to_string()))
@@ -198,14 +169,8 @@ pub async fn get_numeric_sensor_daily_summaries_async(
         format!("{}T00:00:00.000", end.format("%Y-%m-%d")),
     ));
 
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .query(&query)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<NumericSensorDailySummaryDto>>()
+    let resp: Vec<NumericSensorDailySummaryDto> = client
+        .get_with_query(SENSOR_DAILY_SUMMARIES_NUMERIC_API_PREFIX, &query)
         .await?;
 
     trace!("Numeric sensor daily summaries: {resp:?}");
@@ -216,6 +181,7 @@ pub async fn get_numeric_sensor_daily_summaries_async(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
     use std::io::Write;
@@ -325,10 +291,9 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let result = get_asset_sensor_list_async(&config, &client, &auth_header, asset_id)
+        let result = get_asset_sensor_list_async(&client, asset_id)
             .await
             .unwrap();
 
@@ -349,10 +314,6 @@ mod tests {
         tmp
     }
 
-    fn auth_token() -> AuthToken {
-        AuthToken::for_test("Bearer test_token", std::time::Duration::from_hours(1))
-    }
-
     #[tokio::test]
     async fn test_bulk_update_asset_sensor_async_succeeds_for_valid_row() {
         let asset_id = Uuid::new_v4();
@@ -396,18 +357,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_csv(&[(asset_id, sensor_id, "New name", Some(new_policy))]);
 
-        bulk_update_asset_sensor_async(
-            &config,
-            &client,
-            &mut token,
-            &csv.path().to_string_lossy().to_string(),
-        )
-        .await
-        .unwrap();
+        bulk_update_asset_sensor_async(&client, &csv.path().to_string_lossy().to_string(), 1)
+            .await
+            .unwrap();
 
         list_mock.assert();
         update_mock.assert();
@@ -421,7 +376,8 @@ mod tests {
         let list_path = format!("{SENSOR_API_PREFIX}/{asset_id}");
 
         let server = MockServer::start();
-        // List is hit only once because the asset id is cached after the first row.
+        // List is hit only once, even with several rows in flight: rows for one asset run in
+        // order, so the second row finds the list the first one cached.
         let list_mock = server.mock(|when, then| {
             when.method(GET).path(list_path);
             then.status(200)
@@ -490,24 +446,20 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_csv(&[
             (asset_id, sensor_id_fail, "fail-new", Some(Uuid::new_v4())),
             (asset_id, sensor_id_ok, "ok-new", Some(Uuid::new_v4())),
         ]);
 
-        let result = bulk_update_asset_sensor_async(
-            &config,
-            &client,
-            &mut token,
-            &csv.path().to_string_lossy().to_string(),
-        )
-        .await;
+        let result =
+            bulk_update_asset_sensor_async(&client, &csv.path().to_string_lossy().to_string(), 4)
+                .await;
 
         // Every mock must have been called: the bulk op processed both rows despite the failure.
         list_mock.assert();
-        put_fail_mock.assert();
+        // The 500 is retried up to the default max_attempts before the row is given up.
+        put_fail_mock.assert_calls(4);
         put_ok_mock.assert();
 
         // ... AND the failure was surfaced back to the caller (non-zero exit code for automation).
@@ -558,13 +510,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
         let result = get_numeric_sensor_daily_summaries_async(
-            &config,
             &client,
-            &auth_header,
             &[sensor_id_a, sensor_id_b],
             start,
             end,
@@ -598,19 +547,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let result = get_numeric_sensor_daily_summaries_async(
-            &config,
-            &client,
-            &auth_header,
-            &[sensor_id],
-            start,
-            end,
-        )
-        .await
-        .unwrap();
+        let result = get_numeric_sensor_daily_summaries_async(&client, &[sensor_id], start, end)
+            .await
+            .unwrap();
 
         m.assert();
         assert!(result.is_empty());
@@ -633,20 +574,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let result = get_numeric_sensor_daily_summaries_async(
-            &config,
-            &client,
-            &auth_header,
-            &[sensor_id],
-            start,
-            end,
-        )
-        .await;
+        let result =
+            get_numeric_sensor_daily_summaries_async(&client, &[sensor_id], start, end).await;
 
-        m.assert();
+        m.assert_calls(4);
         assert!(result.is_err());
     }
 
@@ -693,19 +626,14 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         // Empty access_policy_id triggers the "keep original" branch that parses the cached id.
         let csv = write_csv(&[(asset_id, sensor_id, "New name", None)]);
 
         // Must not abort the whole bulk run on a bad cached UUID, and must not fire the PUT.
-        let result = bulk_update_asset_sensor_async(
-            &config,
-            &client,
-            &mut token,
-            &csv.path().to_string_lossy().to_string(),
-        )
-        .await;
+        let result =
+            bulk_update_asset_sensor_async(&client, &csv.path().to_string_lossy().to_string(), 1)
+                .await;
 
         list_mock.assert();
         put_should_not_fire.assert_calls(0);
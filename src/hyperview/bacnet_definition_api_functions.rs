@@ -1,126 +1,76 @@
 use log::{debug, error};
-use reqwest::{Client, header::AUTHORIZATION};
 use uuid::Uuid;
 
-use crate::retry_on_unauthorized_async;
-
 use super::{
+    api_client::HyperviewClient,
     api_constants::BACNET_DEFINITION_API_PREFIX,
-    auth::AuthToken,
     bacnet_definition_api_data::{
         BacnetNonNumericSensorDefinitionDto, BacnetNonNumericSensorDefinitionImportCsv,
         BacnetNonNumericSensorDefinitionImportDto, BacnetNumericSensorDefinitionDto,
         BacnetNumericSensorDefinitionImportDto,
     },
-    cli_data::AppConfig,
+    bulk::run_rows_async,
 };
 
 pub async fn list_bacnet_numeric_sensor_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
 ) -> color_eyre::Result<Vec<BacnetNumericSensorDefinitionDto>> {
-    let target_url = format!(
-        "{}{}/bacnetIpNumericSensors/{}",
-        config.instance_url, BACNET_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<BacnetNumericSensorDefinitionDto>>()
-        .await?;
-
-    Ok(resp)
+    let target_path =
+        format!("{BACNET_DEFINITION_API_PREFIX}/bacnetIpNumericSensors/{definition_id}");
+
+    client.get(&target_path).await
 }
 
 pub async fn list_bacnet_non_numeric_sensor_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
 ) -> color_eyre::Result<Vec<BacnetNonNumericSensorDefinitionDto>> {
-    let target_url = format!(
-        "{}{}/bacnetIpNonNumericSensors/{}",
-        config.instance_url, BACNET_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<BacnetNonNumericSensorDefinitionDto>>()
-        .await?;
-
-    Ok(resp)
+    let target_path =
+        format!("{BACNET_DEFINITION_API_PREFIX}/bacnetIpNonNumericSensors/{definition_id}");
+
+    client.get(&target_path).await
 }
 
 async fn create_bacnet_numeric_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     sensor: &BacnetNumericSensorDefinitionImportDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/bacnetIpNumericSensors/{}",
-        config.instance_url, BACNET_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
+    let target_path =
+        format!("{BACNET_DEFINITION_API_PREFIX}/bacnetIpNumericSensors/{definition_id}");
 
-    req.post(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.post(&target_path, sensor).await?;
 
     Ok(())
 }
 
 async fn update_bacnet_numeric_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     sensor_id: Uuid,
     sensor: &BacnetNumericSensorDefinitionImportDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/bacnetIpNumericSensors/{}/{}",
-        config.instance_url, BACNET_DEFINITION_API_PREFIX, definition_id, sensor_id
+    let target_path = format!(
+        "{BACNET_DEFINITION_API_PREFIX}/bacnetIpNumericSensors/{definition_id}/{sensor_id}"
     );
-    debug!("Request URL: {target_url}");
 
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.put(&target_path, sensor).await?;
 
     Ok(())
 }
 
 pub async fn bulk_import_bacnet_numeric_sensor_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: &String,
     definition_id: Uuid,
     create_as_new: bool,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
     let mut reader = csv::Reader::from_path(filename)?;
     let mut total: usize = 0;
     let mut failed: usize = 0;
+    let mut sensors = Vec::new();
 
     for result in reader.deserialize::<BacnetNumericSensorDefinitionImportDto>() {
         total += 1;
@@ -140,47 +90,36 @@ pub async fn bulk_import_bacnet_numeric_sensor_definitions_async(
             sensor.id = None;
         }
 
-        auth_token.refresh_if_needed_async(config).await?;
+        sensors.push(sensor);
+    }
 
-        debug!("Processing sensor definition row: {sensor:?}");
+    failed += run_rows_async(
+        sensors,
+        concurrency,
+        |sensor| sensor.id,
+        |sensor| async move {
+            debug!("Processing sensor definition row: {sensor:?}");
 
-        let result = match sensor.id {
-            Some(id) => {
-                retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
+            let result = match sensor.id {
+                Some(id) => {
                     update_bacnet_numeric_sensor_definition_async(
-                        config,
-                        req,
-                        &auth_token.header,
+                        client,
                         definition_id,
                         id,
                         &sensor,
                     )
                     .await
-                )
-            }
-            None => {
-                retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
-                    create_bacnet_numeric_sensor_definition_async(
-                        config,
-                        req,
-                        &auth_token.header,
-                        definition_id,
-                        &sensor,
-                    )
-                    .await
-                )
-            }
-        };
-
-        if let Err(e) = result {
-            error!("Failed to import sensor definition '{}': {e}", sensor.name);
-            failed += 1;
-        }
-    }
+                }
+                None => {
+                    create_bacnet_numeric_sensor_definition_async(client, definition_id, &sensor)
+                        .await
+                }
+            };
+
+            result.map_err(|e| format!("Failed to import sensor definition '{}': {e}", sensor.name))
+        },
+    )
+    .await;
 
     if failed > 0 {
         return Err(super::app_errors::AppError::BulkOperationFailures { failed, total }.into());
@@ -190,63 +129,44 @@ pub async fn bulk_import_bacnet_numeric_sensor_definitions_async(
 }
 
 async fn create_bacnet_non_numeric_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     sensor: &BacnetNonNumericSensorDefinitionImportDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/bacnetIpNonNumericSensors/{}",
-        config.instance_url, BACNET_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
+    let target_path =
+        format!("{BACNET_DEFINITION_API_PREFIX}/bacnetIpNonNumericSensors/{definition_id}");
 
-    req.post(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.post(&target_path, sensor).await?;
 
     Ok(())
 }
 
 async fn update_bacnet_non_numeric_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     sensor_id: Uuid,
     sensor: &BacnetNonNumericSensorDefinitionImportDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/bacnetIpNonNumericSensors/{}/{}",
-        config.instance_url, BACNET_DEFINITION_API_PREFIX, definition_id, sensor_id
+    let target_path = format!(
+        "{BACNET_DEFINITION_API_PREFIX}/bacnetIpNonNumericSensors/{definition_id}/{sensor_id}"
     );
-    debug!("Request URL: {target_url}");
 
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.put(&target_path, sensor).await?;
 
     Ok(())
 }
 
 pub async fn bulk_import_bacnet_non_numeric_sensor_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: &String,
     definition_id: Uuid,
     create_as_new: bool,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
     let mut reader = csv::Reader::from_path(filename)?;
     let mut total: usize = 0;
     let mut failed: usize = 0;
+    let mut sensors = Vec::new();
 
     for result in reader.deserialize::<BacnetNonNumericSensorDefinitionImportCsv>() {
         total += 1;
@@ -260,8 +180,6 @@ pub async fn bulk_import_bacnet_non_numeric_sensor_definitions_async(
             }
         };
 
-        auth_token.refresh_if_needed_async(config).await?;
-
         let mut sensor = match BacnetNonNumericSensorDefinitionImportDto::try_from(&row) {
             Ok(sensor) => sensor,
             Err(e) => {
@@ -280,45 +198,40 @@ pub async fn bulk_import_bacnet_non_numeric_sensor_definitions_async(
             sensor.id = None;
         }
 
-        debug!("Processing sensor definition row: {sensor:?}");
+        sensors.push(sensor);
+    }
+
+    failed += run_rows_async(
+        sensors,
+        concurrency,
+        |sensor| sensor.id,
+        |sensor| async move {
+            debug!("Processing sensor definition row: {sensor:?}");
 
-        let result = match sensor.id {
-            Some(id) => {
-                retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
+            let result = match sensor.id {
+                Some(id) => {
                     update_bacnet_non_numeric_sensor_definition_async(
-                        config,
-                        req,
-                        &auth_token.header,
+                        client,
                         definition_id,
                         id,
                         &sensor,
                     )
                     .await
-                )
-            }
-            None => {
-                retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
+                }
+                None => {
                     create_bacnet_non_numeric_sensor_definition_async(
-                        config,
-                        req,
-                        &auth_token.header,
+                        client,
                         definition_id,
                         &sensor,
                     )
                     .await
-                )
-            }
-        };
+                }
+            };
 
-        if let Err(e) = result {
-            error!("Failed to import sensor definition '{}': {e}", sensor.name);
-            failed += 1;
-        }
-    }
+            result.map_err(|e| format!("Failed to import sensor definition '{}': {e}", sensor.name))
+        },
+    )
+    .await;
 
     if failed > 0 {
         return Err(super::app_errors::AppError::BulkOperationFailures { failed, total }.into());
@@ -330,14 +243,10 @@ pub async fn bulk_import_bacnet_non_numeric_sensor_definitions_async(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
     use std::io::Write;
-    use std::time::Duration;
-
-    fn auth_token() -> AuthToken {
-        AuthToken::for_test("Bearer test_token", Duration::from_hours(1))
-    }
 
     #[tokio::test]
     // offset is asserted against an exact deserialized sentinel (0.0), so an exact compare is fine.
@@ -372,17 +281,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = list_bacnet_numeric_sensor_definitions_async(
-            &config,
-            &client,
-            &auth_header,
-            definition_id,
-        )
-        .await
-        .unwrap();
+        let resp = list_bacnet_numeric_sensor_definitions_async(&client, definition_id)
+            .await
+            .unwrap();
 
         m.assert();
         assert_eq!(resp.len(), 1);
@@ -423,17 +326,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = list_bacnet_non_numeric_sensor_definitions_async(
-            &config,
-            &client,
-            &auth_header,
-            definition_id,
-        )
-        .await
-        .unwrap();
+        let resp = list_bacnet_non_numeric_sensor_definitions_async(&client, definition_id)
+            .await
+            .unwrap();
 
         m.assert();
         assert_eq!(resp.len(), 1);
@@ -475,17 +372,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_numeric_csv(&[("", "New Sensor")]);
 
         bulk_import_bacnet_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await
         .unwrap();
@@ -517,17 +412,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_numeric_csv(&[(&sensor_id.to_string(), "Existing Sensor")]);
 
         bulk_import_bacnet_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await
         .unwrap();
@@ -561,17 +454,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_numeric_csv(&[(&sensor_id.to_string(), "Cloned Sensor")]);
 
         bulk_import_bacnet_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             true,
+            1,
         )
         .await
         .unwrap();
@@ -604,17 +495,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_numeric_csv(&[("", "Bad Sensor"), ("", "Good Sensor")]);
 
         let result = bulk_import_bacnet_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await;
 
@@ -670,16 +559,14 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
         let result = bulk_import_bacnet_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await;
 
@@ -733,17 +620,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_non_numeric_csv(&[("", "Status", "Inactive:0,Active:1")]);
 
         bulk_import_bacnet_non_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await
         .unwrap();
@@ -768,17 +653,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_non_numeric_csv(&[("", "Status", "Inactive:not-a-number")]);
 
         let result = bulk_import_bacnet_non_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await;
 
@@ -0,0 +1,163 @@
+use futures::stream::{self, StreamExt};
+use log::error;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// Runs `op` on every row, with up to `concurrency` rows in flight, and returns how many rows
+/// failed. A failed row is logged with the message `op` returns and does not stop the others.
+///
+/// Rows for which `key` returns the same value (usually the asset the row changes) run one after
+/// another in input order, so a later row for an asset always sees the earlier one applied. Rows
+/// with no key, such as rows that create something new, have nothing to wait for. With a
+/// concurrency of 1 rows are not grouped and run exactly as they appear in the file.
+///
+/// Rows are driven from the calling task rather than spawned, so `op` may borrow the client and
+/// any caches the bulk command keeps.
+pub async fn run_rows_async<R, K, F, Fut>(
+    rows: Vec<R>,
+    concurrency: usize,
+    key: impl Fn(&R) -> Option<K>,
+    op: F,
+) -> usize
+where
+    K: Eq + Hash,
+    F: Fn(R) -> Fut,
+    Fut: Future<Output = Result<(), String>>,
+{
+    let op = &op;
+    let groups = if concurrency > 1 {
+        group_rows(rows, key)
+    } else {
+        vec![rows]
+    };
+
+    stream::iter(groups)
+        .map(|group| async move {
+            let mut failed = 0;
+            for row in group {
+                if let Err(message) = op(row).await {
+                    error!("{message}");
+                    failed += 1;
+                }
+            }
+            failed
+        })
+        .buffer_unordered(concurrency.max(1))
+        .fold(0, |total, failed| async move { total + failed })
+        .await
+}
+
+/// Splits `rows` into runs that share a key, each in input order. Groups are ordered by their
+/// first row.
+fn group_rows<R, K: Eq + Hash>(rows: Vec<R>, key: impl Fn(&R) -> Option<K>) -> Vec<Vec<R>> {
+    let mut groups: Vec<Vec<R>> = Vec::new();
+    let mut group_by_key: HashMap<K, usize> = HashMap::new();
+
+    for row in rows {
+        match key(&row) {
+            Some(k) => match group_by_key.get(&k) {
+                Some(&index) => groups[index].push(row),
+                None => {
+                    group_by_key.insert(k, groups.len());
+                    groups.push(vec![row]);
+                }
+            },
+            None => groups.push(vec![row]),
+        }
+    }
+
+    groups
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use std::time::Duration;
+
+    #[test]
+    fn test_group_rows_keeps_input_order_within_and_across_groups() {
+        let rows = vec![
+            ("a", 1),
+            ("b", 2),
+            ("a", 3),
+            ("new", 4),
+            ("new", 5),
+            ("b", 6),
+        ];
+
+        let groups = group_rows(rows, |(k, _)| (*k != "new").then_some(*k));
+
+        assert_eq!(
+            groups,
+            vec![
+                vec![("a", 1), ("a", 3)],
+                vec![("b", 2), ("b", 6)],
+                vec![("new", 4)],
+                vec![("new", 5)],
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_run_rows_counts_failures_and_orders_rows_per_key() {
+        let log = Mutex::new(Vec::new());
+        // The first row of "a" is the slowest, so without per-key ordering "a" 3 would finish
+        // before it.
+        let rows = vec![("a", 1, 30), ("b", 2, 0), ("a", 3, 0), ("c", 4, 0)];
+
+        let failed = run_rows_async(
+            rows,
+            4,
+            |(k, _, _)| Some(*k),
+            |(k, n, delay_ms)| {
+                let log = &log;
+                async move {
+                    tokio::time::sleep(Duration::from_millis(delay_ms)).await;
+                    log.lock().unwrap().push((k, n));
+                    if n % 2 == 0 {
+                        Err(format!("row {n} failed"))
+                    } else {
+                        Ok(())
+                    }
+                }
+            },
+        )
+        .await;
+
+        let log = log.into_inner().unwrap();
+        let a_rows: Vec<_> = log.iter().filter(|(k, _)| *k == "a").collect();
+
+        assert_eq!(failed, 2);
+        assert_eq!(log.len(), 4);
+        assert_eq!(a_rows, [&("a", 1), &("a", 3)]);
+        // The other keys did not wait for "a".
+        assert_eq!(log.last(), Some(&("a", 3)));
+    }
+
+    #[tokio::test]
+    async fn test_run_rows_without_concurrency_keeps_file_order() {
+        let log = Mutex::new(Vec::new());
+        let rows = vec![("a", 1), ("b", 2), ("a", 3), ("b", 4)];
+
+        let failed = run_rows_async(
+            rows,
+            1,
+            |(k, _)| Some(*k),
+            |row| {
+                let log = &log;
+                async move {
+                    log.lock().unwrap().push(row);
+                    Ok(())
+                }
+            },
+        )
+        .await;
+
+        assert_eq!(failed, 0);
+        assert_eq!(
+            log.into_inner().unwrap(),
+            [("a", 1), ("b", 2), ("a", 3), ("b", 4)]
+        );
+    }
+}
@@ -1,17 +1,18 @@
 use clap::{Args, Parser, Subcommand, ValueEnum, value_parser};
-use serde::{Deserialize, Serialize};
-use std::fmt;
+use clap_complete::Shell;
+use std::path::PathBuf;
 use uuid::Uuid;
 
-#[derive(Debug, Serialize, Deserialize, Default, Clone)]
-pub struct AppConfig {
-    pub client_id: String,
-    pub client_secret: String,
-    pub scope: String,
-    pub auth_url: String,
-    pub token_url: String,
-    pub instance_url: String,
-}
+use super::{
+    asset_alarm_events_data::{AlarmEventFilterOptions, ManageActionOptions},
+    asset_api_data::{AssetTypes, RackPanelType, RackPosition, RackSide, SortKey},
+    csv_input::{CsvTemplate, SensorDefinitionCsv},
+    definition_api_data::SensorValueClass,
+    filter_expression::FilterExpression,
+    output_file::ExistingFilePolicy,
+    output_template::OutputTemplate,
+    record_writer::OutputOptions,
+};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -20,92 +21,111 @@ pub struct AppArgs {
     #[arg(short = 'd', long, help = "Debug level", default_value = "error")]
     pub debug_level: DebugLevels,
 
+    #[arg(
+        long,
+        global = true,
+        env = "HVCLI_CONFIG",
+        help = "Path to the configuration file. Defaults to $HOME/.hyperview/hyperview.toml"
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "HVCLI_PROFILE",
+        help = "Config profile to use, e.g. staging. Defaults to the file's default_profile"
+    )]
+    pub profile: Option<String>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "HVCLI_CONCURRENCY",
+        default_value_t = 1,
+        value_parser = value_parser!(u16).range(1..=64),
+        help = "Number of CSV rows bulk commands process at once (1-64). Rows for the same asset still run in file order"
+    )]
+    pub concurrency: u16,
+
+    #[arg(
+        long,
+        global = true,
+        env = "HVCLI_CAPTURE_HTTP",
+        value_name = "FILE",
+        help = "Record every HTTP request and response to FILE as JSON lines, with credentials redacted"
+    )]
+    pub capture_http: Option<PathBuf>,
+
+    #[arg(
+        long,
+        global = true,
+        env = "HVCLI_REPLAY",
+        value_name = "FILE",
+        conflicts_with = "capture_http",
+        help = "Answer API requests from a file recorded with --capture-http instead of the network. No configuration or credentials are needed"
+    )]
+    pub replay: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub output: OutputArgs,
+
     #[command(subcommand)]
-    pub command: AppArgsSubcommands,
+    pub command: AppCommand,
 }
 
-#[derive(Debug, ValueEnum, Clone, Copy)]
-pub enum OutputOptions {
-    CsvFile,
-    Json,
-    Record,
-}
-
-// camelCase across clap (CLI input), serde (CSV input), and Display (wire output) so the CLI's
-// representation of an asset type is a single value that matches the Hyperview API's
-// AssetTypeEnum (which is camelCase). The API is case-insensitive on input but emits camelCase.
-#[derive(Debug, ValueEnum, Clone, Serialize, Deserialize)]
-#[clap(rename_all = "camelCase")]
-#[serde(rename_all = "camelCase")]
-pub enum AssetTypes {
-    BatteryBank,
-    BladeEnclosure,
-    BladeNetwork,
-    BladeServer,
-    BladeStorage,
-    Busway,
-    Camera,
-    Chiller,
-    Crac,
-    Crah,
-    DcRectifier,
-    Environmental,
-    FireControlPanel,
-    Generator,
-    InRowCooling,
-    KvmSwitch,
-    Location,
-    Monitor,
-    NetworkDevice,
-    NetworkStorage,
-    NodeServer,
-    OtherDevice,
-    PatchPanel,
-    PduAndRpp,
-    PowerMeter,
-    Rack,
-    RackPdu,
-    Server,
-    SmallUps,
-    Switchboard,
-    Switchgear,
-    TransferSwitch,
-    Unknown,
-    Ups,
-    VirtualServer,
-}
-
-impl fmt::Display for AssetTypes {
-    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        // Emit the API's camelCase form: the PascalCase variant name with its first letter
-        // lowercased. This matches `#[serde(rename_all = "camelCase")]` and the clap value names.
-        let name = format!("{self:?}");
-        let mut chars = name.chars();
-        match chars.next() {
-            Some(first) => write!(f, "{}{}", first.to_ascii_lowercase(), chars.as_str()),
-            None => Ok(()),
-        }
-    }
-}
+/// Output settings shared by every command that lists records.
+#[derive(Args, Debug, Clone, Default)]
+pub struct OutputArgs {
+    #[arg(
+        long,
+        global = true,
+        help = "Show table output in full instead of truncating columns to fit the terminal"
+    )]
+    pub wide: bool,
+
+    #[arg(
+        long,
+        global = true,
+        value_delimiter = ',',
+        value_name = "COLUMNS",
+        help = "Only show these fields, in this order, e.g. id,name,path"
+    )]
+    pub columns: Option<Vec<String>>,
+
+    #[arg(
+        long,
+        global = true,
+        value_name = "TEMPLATE",
+        help = "Print each record as one line from a template, e.g. \"{name} ({id}) in {path}\". See the README for the syntax"
+    )]
+    pub format: Option<OutputTemplate>,
+
+    #[arg(
+        long,
+        global = true,
+        conflicts_with = "append",
+        help = "Replace the output file if it already exists"
+    )]
+    pub overwrite: bool,
 
-#[derive(Debug, ValueEnum, Clone, Serialize, Deserialize)]
-#[clap(rename_all = "PascalCase")]
-pub enum RackSide {
-    Front,
-    Rear,
-    Unknown,
+    #[arg(
+        long,
+        global = true,
+        help = "Add to the end of the output file if it already exists. CSV headers must match"
+    )]
+    pub append: bool,
 }
 
-#[derive(Debug, ValueEnum, Clone, Serialize, Deserialize)]
-#[clap(rename_all = "PascalCase")]
-pub enum RackPosition {
-    Left,
-    Right,
-    Top,
-    Bottom,
-    Above,
-    Below,
-    Unknown,
+impl OutputArgs {
+    pub fn existing_file_policy(&self) -> ExistingFilePolicy {
+        if self.append {
+            ExistingFilePolicy::Append
+        } else if self.overwrite {
+            ExistingFilePolicy::Overwrite
+        } else {
+            ExistingFilePolicy::Fail
+        }
+    }
 }
 
 #[derive(Debug, ValueEnum, Clone, Copy)]
@@ -117,31 +137,20 @@ pub enum DebugLevels {
     Trace,
 }
 
-#[derive(Debug, ValueEnum, Clone, Copy, Deserialize)]
-#[clap(rename_all = "PascalCase")]
-pub enum RackPanelType {
-    BlankingPanel,
-    CableManagement,
-}
-
-#[derive(Debug, ValueEnum, Clone, Copy)]
-pub enum ManageActionOptions {
-    Acknowledge,
-    Close,
-}
-
-#[derive(Debug, ValueEnum, Clone, Copy)]
-pub enum AlarmEventFilterOptions {
-    Unacknowledged,
-    Active,
-}
+/// A subcommand, split by whether it talks to the Hyperview API. A command declares that it works
+/// offline by being a variant of [`OfflineSubcommands`] instead of [`AppArgsSubcommands`].
+#[allow(clippy::large_enum_variant)]
+#[derive(Subcommand)]
+pub enum AppCommand {
+    #[command(flatten)]
+    Api(AppArgsSubcommands),
 
-#[derive(Debug, ValueEnum, Clone, Copy)]
-pub enum SensorValueClass {
-    Numeric,
-    Enum,
+    #[command(flatten)]
+    Offline(OfflineSubcommands),
 }
 
+/// Commands that call the Hyperview API. `main` resolves the profile and fetches an access token
+/// before running them.
 #[allow(clippy::enum_variant_names)]
 #[derive(Subcommand)]
 pub enum AppArgsSubcommands {
@@ -321,6 +330,139 @@ pub enum AppArgsSubcommands {
     DeleteModbusNonNumericSensorDefinition(DeleteSensorDefinitionArgs),
 }
 
+/// Commands that need no access token: they work offline, with no config file or network, or
+/// (like `login` and `config validate`) set up their own connection.
+#[derive(Subcommand)]
+pub enum OfflineSubcommands {
+    /// Sign in as yourself through the browser (authorization code with PKCE), or with --device on a host without one. Later commands use the stored session instead of the client credentials
+    Login(LoginArgs),
+
+    /// Remove the selected profile's login session and cached access tokens from the on-disk token cache
+    #[clap(alias = "clear-token-cache")]
+    Logout(LogoutArgs),
+
+    /// Inspect the hvcli configuration file
+    #[command(subcommand)]
+    Config(ConfigSubcommands),
+
+    /// Print a shell completion script, e.g. hvcli completions bash > ~/.local/share/bash-completion/completions/hvcli
+    Completions(CompletionsArgs),
+
+    /// Print the CSV header a bulk command reads, e.g. hvcli csv-template bulk-update-asset-name > names.csv
+    CsvTemplate(CsvTemplateArgs),
+
+    /// Check a sensor definition CSV for rows the bulk import would reject, without calling the API
+    ValidateSensorDefinitionCsv(ValidateSensorDefinitionCsvArgs),
+}
+
+#[derive(Subcommand)]
+pub enum ConfigSubcommands {
+    /// List the connection profiles in the configuration file and the instance each one targets
+    ListProfiles(ListProfilesArgs),
+
+    /// Create the configuration file, prompting for the instance URL and client credentials. With --profile, the settings are written as that named profile
+    Init(ConfigInitArgs),
+
+    /// Check the configuration: load the file, parse its URLs, fetch an access token, and make one authenticated request
+    Validate,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CompletionsArgs {
+    #[arg(value_enum, help = "Shell to generate completions for")]
+    pub shell: Shell,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct CsvTemplateArgs {
+    #[arg(value_enum, help = "Bulk command to print the CSV header for")]
+    pub command: CsvTemplate,
+
+    #[arg(long, help = "Print the example rows after the header")]
+    pub example: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ValidateSensorDefinitionCsvArgs {
+    #[arg(
+        short,
+        long,
+        value_enum,
+        help = "Kind of sensor definition the file holds"
+    )]
+    pub kind: SensorDefinitionCsv,
+
+    #[arg(short, long, help = "Input filename, e.g. input.csv")]
+    pub filename: PathBuf,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct LoginArgs {
+    #[arg(
+        long,
+        help = "Use the device code flow: print a code to enter on another device instead of opening a local redirect listener"
+    )]
+    pub device: bool,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Loopback port for the browser redirect; 0 picks a free port"
+    )]
+    pub port: u16,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct LogoutArgs {
+    #[arg(long, help = "Remove cached tokens for every profile")]
+    pub all: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ConfigInitArgs {
+    #[arg(
+        short,
+        long,
+        help = "Hyperview instance URL, e.g. https://example.hyperviewhq.com. Prompted for when omitted"
+    )]
+    pub instance_url: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "API client id, e.g. c33472d0-c66b-4659-a8f8-73c289ba4dbe. Prompted for when omitted"
+    )]
+    pub client_id: Option<String>,
+
+    #[arg(
+        short,
+        long,
+        help = "API client scope",
+        default_value = "HyperviewManagerApi"
+    )]
+    pub scope: String,
+
+    #[arg(
+        long,
+        help = "Replace the selected profile's settings if the configuration file already exists. Other profiles are kept"
+    )]
+    pub force: bool,
+}
+
+#[derive(Args, Debug, Clone)]
+pub struct ListProfilesArgs {
+    #[arg(
+        short,
+        long,
+        help = "Output type, e.g. csv-file",
+        default_value = "record"
+    )]
+    pub output_type: OutputOptions,
+
+    #[arg(short, long, help = "Output filename, e.g. output.csv")]
+    pub filename: Option<String>,
+}
+
 #[derive(Args, Debug, Clone)]
 pub struct BulkUpdateSingleInputFileArgs {
     #[arg(short, long, help = "Input filename, e.g. input.csv")]
@@ -580,9 +722,31 @@ pub struct ListAnyOfArgs {
     #[arg(short = 'R', long, help = "Product name, e.g. poweredge")]
     pub product: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "EXPRESSION",
+        help = "Filter expression, e.g. \"(path CONTAINS 'Building A' OR path CONTAINS 'Building B') AND property.designValue > 5000\""
+    )]
+    pub filter: Option<FilterExpression>,
+
+    #[arg(
+        long,
+        value_name = "EXPRESSION",
+        help = "Filter in the search API's own syntax, sent unchecked"
+    )]
+    pub raw_filter: Option<String>,
+
     #[arg(short = 'U', long, help = "Show property in output, e.g. ratedVoltage")]
     pub show_property: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "FIELD[:asc|desc]",
+        value_delimiter = ',',
+        help = "Sort by a field, e.g. name or path:desc. Repeat for more keys. With --all, defaults to id"
+    )]
+    pub sort: Vec<SortKey>,
+
     #[arg(
         short,
         long,
@@ -600,6 +764,21 @@ pub struct ListAnyOfArgs {
     )]
     pub limit: u32,
 
+    #[arg(
+        long,
+        conflicts_with = "limit",
+        help = "Fetch every matching asset, 1000 per request, instead of one page of --limit"
+    )]
+    pub all: bool,
+
+    #[arg(
+        long,
+        requires = "all",
+        value_name = "COUNT",
+        help = "With --all, stop after this many assets"
+    )]
+    pub max_results: Option<u32>,
+
     #[arg(
         short,
         long,
@@ -682,6 +861,20 @@ pub struct GenerateSensorReportArgs {
     )]
     pub product: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "EXPRESSION",
+        help = "Filter expression, e.g. \"(path CONTAINS 'Building A' OR path CONTAINS 'Building B') AND property.designValue > 5000\""
+    )]
+    pub filter: Option<FilterExpression>,
+
+    #[arg(
+        long,
+        value_name = "EXPRESSION",
+        help = "Filter in the search API's own syntax, sent unchecked"
+    )]
+    pub raw_filter: Option<String>,
+
     #[arg(
         long,
         help = "Number of assets to skip (0 -> 1_000_000_000), e.g. 100",
@@ -697,6 +890,21 @@ pub struct GenerateSensorReportArgs {
     )]
     pub limit: u32,
 
+    #[arg(
+        long,
+        conflicts_with = "limit",
+        help = "Report on every matching asset, 1000 per request, instead of one page of --limit"
+    )]
+    pub all: bool,
+
+    #[arg(
+        long,
+        requires = "all",
+        value_name = "COUNT",
+        help = "With --all, stop after this many assets"
+    )]
+    pub max_results: Option<u32>,
+
     #[arg(
         short = 'o',
         long,
@@ -751,9 +959,31 @@ pub struct SearchAssetsArgs {
     #[arg(short = 'R', long, help = "Product name, e.g. poweredge")]
     pub product: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "EXPRESSION",
+        help = "Filter expression, e.g. \"(path CONTAINS 'Building A' OR path CONTAINS 'Building B') AND property.designValue > 5000\""
+    )]
+    pub filter: Option<FilterExpression>,
+
+    #[arg(
+        long,
+        value_name = "EXPRESSION",
+        help = "Filter in the search API's own syntax, sent unchecked"
+    )]
+    pub raw_filter: Option<String>,
+
     #[arg(short = 'U', long, help = "Show property in output, e.g. ratedVoltage")]
     pub show_property: Option<String>,
 
+    #[arg(
+        long,
+        value_name = "FIELD[:asc|desc]",
+        value_delimiter = ',',
+        help = "Sort by a field, e.g. name or path:desc. Repeat for more keys. With --all, defaults to id"
+    )]
+    pub sort: Vec<SortKey>,
+
     #[arg(
         short,
         long,
@@ -771,6 +1001,21 @@ pub struct SearchAssetsArgs {
     )]
     pub limit: u32,
 
+    #[arg(
+        long,
+        conflicts_with = "limit",
+        help = "Fetch every matching asset, 1000 per request, instead of one page of --limit"
+    )]
+    pub all: bool,
+
+    #[arg(
+        long,
+        requires = "all",
+        value_name = "COUNT",
+        help = "With --all, stop after this many assets"
+    )]
+    pub max_results: Option<u32>,
+
     #[arg(
         short,
         long,
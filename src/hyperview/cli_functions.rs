@@ -1,10 +1,13 @@
-use csv::Writer;
-use log::{LevelFilter, error};
+use chrono::NaiveDate;
+use clap::{CommandFactory, Parser, ValueEnum};
+use clap_complete::Shell;
+use futures::{Stream, TryStreamExt};
+use log::{LevelFilter, error, info};
 use serde::Serialize;
 use std::fmt::Display;
-use std::fs::File;
 use std::io::Write;
-use std::path::{MAIN_SEPARATOR_STR, Path};
+use std::path::Path;
+use std::pin::pin;
 
 use crate::hyperview::api_constants::{
     BUSWAY_TAPOFF_API_PREFIX, PDU_RPP_BREAKERS_API_PREFIX, RACK_PDU_OUTLETS_API_PREFIX,
@@ -14,16 +17,18 @@ use crate::hyperview::asset_power_api_functions::{
 };
 
 use super::{
+    api_client::HyperviewClient,
     api_constants::{
         ASSET_PROPERTY_ASSET_TAG, ASSET_PROPERTY_DESIGN_VALUE, ASSET_PROPERTY_SERIAL_NUMBER,
         BACNET_DEFINITION_API_PREFIX, MODBUS_DEFINITION_API_PREFIX,
     },
     app_errors::AppError,
     asset_alarm_events_functions::{list_alarm_events_async, manage_asset_alarm_events_async},
+    asset_api_data::{AssetLocationDTO, AssetSearch, MAX_SEARCH_LIMIT},
     asset_api_functions::{
-        add_rack_accessory_async, bulk_add_rack_accessory_async, bulk_update_asset_location_async,
-        bulk_update_asset_name_async, bulk_update_ports_async, list_any_of_async,
-        list_asset_ports_async, search_assets_async, update_asset_location_async,
+        SearchPage, add_rack_accessory_async, bulk_add_rack_accessory_async,
+        bulk_update_asset_location_async, bulk_update_asset_name_async, bulk_update_ports_async,
+        list_any_of_pages, list_asset_ports_async, search_asset_pages, update_asset_location_async,
         update_asset_name_by_id_async,
     },
     asset_power_api_functions::get_power_provider_components_async,
@@ -40,7 +45,16 @@ use super::{
         list_bacnet_non_numeric_sensor_definitions_async,
         list_bacnet_numeric_sensor_definitions_async,
     },
-    cli_data::{AppArgsSubcommands, AppConfig, DebugLevels, OutputOptions},
+    cli_data::{
+        AppArgs, AppArgsSubcommands, AppCommand, ConfigSubcommands, DebugLevels,
+        GenerateSensorReportArgs, ListAnyOfArgs, LogoutArgs, OfflineSubcommands, OutputArgs,
+        SearchAssetsArgs,
+    },
+    config_functions::{
+        get_config_path, init_config_file, list_profiles, load_app_config, load_config_file,
+        selected_profile_name, validate_config_async,
+    },
+    csv_input::validate_sensor_definition_csv,
     custom_asset_properties_api_functions::{
         bulk_update_custom_property_by_name_async, get_custom_asset_property_list_async,
         update_custom_property_by_name_async,
@@ -50,6 +64,9 @@ use super::{
         get_definition_async, list_definitions_async, list_sensor_definition_types_async,
         update_definition_async,
     },
+    http_capture,
+    http_replay::HttpReplay,
+    login::login_async,
     modbus_component_api_functions::{
         add_modbus_component_async, delete_modbus_component_async, list_modbus_components_async,
         update_modbus_component_async,
@@ -61,18 +78,200 @@ use super::{
         list_modbus_non_numeric_sensor_definitions_async,
         list_modbus_numeric_sensor_definitions_async,
     },
+    output_file::{ExistingFilePolicy, OutputFile, OutputTarget, read_csv_header},
+    progress::Progress,
+    record_writer::{OutputOptions, RecordWriter},
+    sensor_report_data::SensorReportQuery,
     sensor_report_functions::generate_sensor_report_async,
+    table_output::terminal_width,
+    token_cache::{TokenCache, clear_token_cache},
 };
 
-pub fn get_config_path() -> String {
-    let home_path = dirs::home_dir().expect("Error: Home directory not found");
+impl From<&SearchAssetsArgs> for AssetSearch {
+    fn from(options: &SearchAssetsArgs) -> Self {
+        Self {
+            search_pattern: options.search_pattern.clone(),
+            asset_type: options.asset_type.clone(),
+            location_path: options.location_path.clone(),
+            properties: key_value_filters(options.properties.as_deref(), "Asset property"),
+            custom_properties: key_value_filters(
+                options.custom_properties.as_deref(),
+                "Custom asset property",
+            ),
+            id: options.id,
+            manufacturer: options.manufacturer.clone(),
+            product: options.product.clone(),
+            filter: options.filter.clone(),
+            raw_filter: options.raw_filter.clone(),
+            show_property: options.show_property.clone(),
+            sort: options.sort.clone(),
+            skip: options.skip,
+            limit: page_size(options.all, options.limit),
+            all: options.all,
+            max_results: options.max_results,
+        }
+    }
+}
+
+impl From<&ListAnyOfArgs> for AssetSearch {
+    fn from(options: &ListAnyOfArgs) -> Self {
+        Self {
+            asset_type: options.asset_type.clone(),
+            location_path: options.location_path.clone(),
+            custom_properties: key_value_filters(
+                options.custom_properties.as_deref(),
+                "Custom asset property",
+            ),
+            id: options.id,
+            manufacturer: options.manufacturer.clone(),
+            product: options.product.clone(),
+            filter: options.filter.clone(),
+            raw_filter: options.raw_filter.clone(),
+            show_property: options.show_property.clone(),
+            sort: options.sort.clone(),
+            skip: options.skip,
+            limit: page_size(options.all, options.limit),
+            all: options.all,
+            max_results: options.max_results,
+            ..Default::default()
+        }
+    }
+}
+
+impl TryFrom<&GenerateSensorReportArgs> for SensorReportQuery {
+    type Error = AppError;
+
+    fn try_from(options: &GenerateSensorReportArgs) -> Result<Self, Self::Error> {
+        let (start, end) = resolve_date_range(options)?;
+
+        let mut assets = AssetSearch::new()
+            .asset_type(options.asset_type.clone())
+            .skip(options.skip)
+            .limit(page_size(options.all, options.limit));
+        assets.location_path = options.location_path.clone();
+        assets.manufacturer = options.manufacturer.clone();
+        assets.product = options.product.clone();
+        assets.filter = options.filter.clone();
+        assets.raw_filter = options.raw_filter.clone();
+        assets.all = options.all;
+        assets.max_results = options.max_results;
+
+        Ok(Self {
+            custom_property: options.custom_property.clone(),
+            ..Self::new(assets, options.sensor.clone(), start, end)
+        })
+    }
+}
+
+/// `--limit` is one page of results; `--all` fetches the largest pages the API allows instead.
+fn page_size(all: bool, limit: u32) -> u32 {
+    if all { MAX_SEARCH_LIMIT } else { limit }
+}
+
+/// Splits `name=value` filters from the command line, logging and skipping any without an `=`.
+fn key_value_filters(filters: Option<&[String]>, kind: &str) -> Vec<(String, String)> {
+    filters
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|filter| match filter.split_once('=') {
+            Some((name, value)) => Some((name.to_string(), value.to_string())),
+            None => {
+                error!("{kind} filter was formatted incorrectly. Skipping... '{filter}'");
+                None
+            }
+        })
+        .collect()
+}
+
+/// The report period from either `--year` and `--month` or `--start` and `--end`.
+fn resolve_date_range(
+    options: &GenerateSensorReportArgs,
+) -> Result<(NaiveDate, NaiveDate), AppError> {
+    let has_ym = options.year.is_some() || options.month.is_some();
+    let has_range = options.start.is_some() || options.end.is_some();
 
-    format!(
-        "{}{}.hyperview{}hyperview.toml",
-        home_path.to_str().unwrap(),
-        MAIN_SEPARATOR_STR,
-        MAIN_SEPARATOR_STR
-    )
+    if has_ym && has_range {
+        return Err(AppError::InvalidDateRangeArgs);
+    }
+
+    if has_range {
+        let start_str = options
+            .start
+            .as_ref()
+            .ok_or(AppError::InvalidDateRangeArgs)?;
+        let end_str = options.end.as_ref().ok_or(AppError::InvalidDateRangeArgs)?;
+        let start = NaiveDate::parse_from_str(start_str, "%Y-%m-%d")
+            .map_err(|_| AppError::InvalidDateFormat(start_str.clone()))?;
+        let end = NaiveDate::parse_from_str(end_str, "%Y-%m-%d")
+            .map_err(|_| AppError::InvalidDateFormat(end_str.clone()))?;
+        return Ok((start, end));
+    }
+
+    let year = options.year.ok_or(AppError::InvalidDateRangeArgs)?;
+    let month = options.month.ok_or(AppError::InvalidDateRangeArgs)?;
+
+    let start = NaiveDate::from_ymd_opt(year, month, 1)
+        .ok_or_else(|| AppError::InvalidDateFormat(format!("{year}-{month:02}-01")))?;
+    let (ny, nm) = if month == 12 {
+        (year + 1, 1)
+    } else {
+        (year, month + 1)
+    };
+    let end = NaiveDate::from_ymd_opt(ny, nm, 1)
+        .ok_or_else(|| AppError::InvalidDateFormat(format!("{ny}-{nm:02}-01")))?;
+    Ok((start, end))
+}
+
+/// Runs `hvcli` with the process's command line arguments: parses them, sets up logging, and
+/// routes the command, resolving a profile and fetching a token only for API commands.
+pub async fn run_async() -> color_eyre::Result<()> {
+    let args = AppArgs::parse();
+    let debug_level = args.debug_level;
+    let level_filter = get_debug_filter(debug_level);
+    env_logger::builder().filter(None, level_filter).init();
+
+    info!("Starting Hyperview Asset Tool");
+    info!(
+        "Startup options: | Debug Level: {debug_level:?} | Config: {:?} | Profile: {:?} |",
+        args.config, args.profile
+    );
+
+    let config_path = args.config.or_else(get_config_path);
+
+    if let Some(path) = &args.capture_http {
+        http_capture::install(path)?;
+    }
+
+    let command = match args.command {
+        AppCommand::Api(command) => command,
+        AppCommand::Offline(command) => {
+            return route_offline_command_async(
+                command,
+                config_path.as_deref(),
+                args.profile.as_deref(),
+                &args.output,
+            )
+            .await;
+        }
+    };
+
+    let client = match &args.replay {
+        Some(path) => HyperviewClient::for_replay(HttpReplay::load(path)?),
+        None => {
+            let config = load_app_config(config_path.as_deref(), args.profile.as_deref())?;
+            let token_cache = TokenCache::for_config(config_path.as_deref(), &config);
+            let auth_token = AuthToken::fetch_cached_async(&config, token_cache).await?;
+            HyperviewClient::new(config, auth_token)?
+        }
+    };
+
+    let result = route_command_async(command, &client, args.concurrency.into(), &args.output).await;
+
+    if let Some(summary) = client.rate_limit_summary() {
+        eprintln!("{summary}");
+    }
+
+    result
 }
 
 pub fn get_debug_filter(debug_level: DebugLevels) -> LevelFilter {
@@ -85,63 +284,191 @@ pub fn get_debug_filter(debug_level: DebugLevels) -> LevelFilter {
     }
 }
 
-pub fn write_output<T: Serialize>(filename: String, object_list: Vec<T>) -> color_eyre::Result<()> {
-    let mut writer = Writer::from_path(filename)?;
+/// Writes `resp` to `filename`, or to standard output when there is none. Records are written as
+/// they are taken from `resp` for the formats that allow it; see [`RecordWriter`]. A file only
+/// replaces or extends an existing one once every record has been written; see [`OutputFile`].
+pub fn handle_output_choice<T: Display + Serialize>(
+    output_type: OutputOptions,
+    filename: Option<&String>,
+    output: &OutputArgs,
+    resp: impl IntoIterator<Item = T>,
+) -> color_eyre::Result<()> {
+    let mut writer = open_record_writer(output_type, filename, output)?;
+    for record in resp {
+        writer.write(&record)?;
+    }
+
+    writer.finish()?.close()
+}
+
+/// A writer for `output_type` on `filename` or standard output, with the `output` settings
+/// applied. Records can then be written as they are fetched; [`OutputTarget::close`] on what
+/// [`RecordWriter::finish`] returns completes the output.
+pub fn open_record_writer(
+    output_type: OutputOptions,
+    filename: Option<&String>,
+    output: &OutputArgs,
+) -> color_eyre::Result<RecordWriter<OutputTarget>> {
+    let mut writer = match filename {
+        None => {
+            let max_width = if output.wide { None } else { terminal_width() };
+            RecordWriter::new(
+                output_type,
+                OutputTarget::Stdout(std::io::stdout()),
+                max_width,
+            )
+        }
+        Some(filename) => {
+            let path = Path::new(filename);
+            let policy = output.existing_file_policy();
+            let existing_csv_header = match (policy, output_type) {
+                (ExistingFilePolicy::Append, OutputOptions::Json | OutputOptions::Table) => {
+                    let name = output_type
+                        .to_possible_value()
+                        .map(|v| v.get_name().to_string())
+                        .unwrap_or_default();
+                    return Err(AppError::AppendUnsupported(name).into());
+                }
+                (ExistingFilePolicy::Append, OutputOptions::CsvFile) if path.exists() => {
+                    read_csv_header(path)?
+                }
+                _ => None,
+            };
+
+            let file = OutputFile::create(path, policy)?;
+            let writer = RecordWriter::new(output_type, OutputTarget::File(file), None);
+            match existing_csv_header {
+                Some(header) => writer.with_existing_csv_header(header),
+                None => writer,
+            }
+        }
+    };
 
-    for object in object_list {
-        writer.serialize(object)?;
+    if let Some(columns) = &output.columns {
+        writer = writer.with_columns(columns.clone());
+    }
+    if let Some(template) = &output.format {
+        writer = writer.with_template(template.clone())?;
     }
 
-    Ok(())
+    Ok(writer)
 }
 
-pub fn handle_output_choice<T: Display + Serialize>(
+/// Writes search results page by page as they arrive. With `show_progress`, a running count is
+/// kept on standard error.
+pub async fn write_search_pages_async(
+    pages: impl Stream<Item = color_eyre::Result<SearchPage>>,
+    show_progress: bool,
     output_type: OutputOptions,
     filename: Option<&String>,
-    resp: Vec<T>,
+    output: &OutputArgs,
 ) -> color_eyre::Result<()> {
-    let mut outfile = String::new();
+    let mut writer = open_record_writer(output_type, filename, output)?;
+    let mut progress = Progress::new("Assets", show_progress);
+    let mut pages = pin!(pages);
 
-    if let Some(f) = filename {
-        if Path::new(f).exists() {
-            error!("Specified file already exists. exiting ...");
-            return Err(AppError::FileExists.into());
+    while let Some(page) = pages.try_next().await? {
+        progress.update(page.fetched, page.estimated_total_hits);
+        for asset in &page.assets {
+            writer.write(asset)?;
         }
-
-        f.clone_into(&mut outfile);
     }
+    progress.finish();
 
-    match output_type {
-        OutputOptions::CsvFile => {
-            if filename.is_none() {
-                error!("Must provide a filename. exiting ...");
-                return Err(AppError::NoOutputFilename.into());
-            }
+    writer.finish()?.close()
+}
+
+/// Runs a command that needs no access token. No profile is resolved and no token is fetched
+/// here; each command loads only what it needs.
+pub async fn route_offline_command_async(
+    command: OfflineSubcommands,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+    output: &OutputArgs,
+) -> color_eyre::Result<()> {
+    match command {
+        OfflineSubcommands::Config(command) => {
+            route_config_command_async(command, config_path, profile, output).await
+        }
 
-            write_output(outfile, resp)?;
+        OfflineSubcommands::Login(options) => {
+            let config = load_app_config(config_path, profile)?;
+            login_async(&config, config_path, &options).await
         }
 
-        OutputOptions::Json => {
-            if filename.is_none() {
-                println!("{}", serde_json::to_string_pretty(&resp).unwrap());
-                return Ok(());
+        OfflineSubcommands::Logout(options) => logout(config_path, profile, &options),
+
+        OfflineSubcommands::Completions(options) => {
+            write_completions(options.shell, &mut std::io::stdout())?;
+            Ok(())
+        }
+
+        OfflineSubcommands::CsvTemplate(options) => {
+            if options.example {
+                print!("{}", options.command.example());
+            } else {
+                println!("{}", options.command.header());
+            }
+            Ok(())
+        }
+
+        OfflineSubcommands::ValidateSensorDefinitionCsv(options) => {
+            let problems = validate_sensor_definition_csv(&options.filename, options.kind)?;
+            for problem in &problems {
+                println!("{problem}");
             }
 
-            let file_handle = File::create(outfile)?;
-            serde_json::to_writer_pretty(file_handle, &resp)?;
+            if problems.is_empty() {
+                println!("{}: no problems found", options.filename.display());
+                Ok(())
+            } else {
+                Err(AppError::InvalidCsvRows(problems.len()).into())
+            }
         }
+    }
+}
 
-        OutputOptions::Record => {
-            if filename.is_none() {
-                for (i, s) in resp.iter().enumerate() {
-                    println!("---- [{i}] ----\n{s}\n");
-                }
-                return Ok(());
+pub fn write_completions(shell: Shell, out: &mut impl Write) -> std::io::Result<()> {
+    let mut command = AppArgs::command();
+    let bin_name = command.get_name().to_string();
+
+    // `generate` panics on a failed write (e.g. piped into `head`), so render to memory first.
+    let mut script = Vec::new();
+    clap_complete::generate(shell, &mut command, bin_name, &mut script);
+
+    out.write_all(&script)?;
+    out.flush()
+}
+
+/// Runs a `config` subcommand. These manage the config file itself, so they are routed before
+/// any profile is resolved or token fetched.
+pub async fn route_config_command_async(
+    command: ConfigSubcommands,
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+    output: &OutputArgs,
+) -> color_eyre::Result<()> {
+    match command {
+        ConfigSubcommands::ListProfiles(options) => {
+            let config_file = load_config_file(config_path)?;
+            let resp = list_profiles(&config_file);
+
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
+        }
+
+        ConfigSubcommands::Init(options) => {
+            let path = init_config_file(config_path, profile, &options)?;
+            println!("Wrote {}", path.display());
+        }
+
+        ConfigSubcommands::Validate => {
+            let checks = validate_config_async(config_path, profile).await;
+            for check in &checks {
+                println!("{check}");
             }
 
-            let mut file_handle = File::create(outfile)?;
-            for (i, s) in resp.iter().enumerate() {
-                write!(file_handle, "---- [{i}] ----\n{s}\n\n")?;
+            if checks.iter().any(|c| !c.passed) {
+                return Err(AppError::ConfigValidationFailed.into());
             }
         }
     }
@@ -149,77 +476,106 @@ pub fn handle_output_choice<T: Display + Serialize>(
     Ok(())
 }
 
+/// Removes cached access tokens for the selected profile, or for every profile with `--all`. Like
+/// the `config` subcommands this needs no credentials.
+pub fn logout(
+    config_path: Option<&Path>,
+    profile: Option<&str>,
+    options: &LogoutArgs,
+) -> color_eyre::Result<()> {
+    let dir = config_path
+        .and_then(Path::parent)
+        .ok_or(AppError::NoConfigPath)?;
+
+    let profile_name = if options.all {
+        None
+    } else {
+        let config_file = load_config_file(config_path)?;
+        Some(selected_profile_name(&config_file, profile).to_string())
+    };
+
+    let removed = clear_token_cache(dir, profile_name.as_deref())?;
+    println!("Removed {removed} cached token(s)");
+
+    Ok(())
+}
+
 #[allow(clippy::too_many_lines)]
 pub async fn route_command_async(
     command: AppArgsSubcommands,
-    config: AppConfig,
-    mut auth_token: AuthToken,
-    req: reqwest::Client,
+    client: &HyperviewClient,
+    concurrency: usize,
+    output: &OutputArgs,
 ) -> color_eyre::Result<()> {
     match command {
         AppArgsSubcommands::ListAssetProperties(options) => {
-            let resp = get_asset_property_list_async(&config, &req, &auth_token.header, options.id)
-                .await?;
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            let resp = get_asset_property_list_async(client, options.id).await?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::ListCustomAssetProperties(options) => {
-            let resp =
-                get_custom_asset_property_list_async(&config, &req, &auth_token.header, options.id)
-                    .await?;
+            let resp = get_custom_asset_property_list_async(client, options.id).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::SearchAssets(options) => {
-            let resp =
-                search_assets_async(&config, &req, &auth_token.header, options.clone()).await?;
-
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            let search = AssetSearch::from(&options);
+
+            write_search_pages_async(
+                search_asset_pages(client, &search),
+                search.all,
+                options.output_type,
+                options.filename.as_ref(),
+                output,
+            )
+            .await?;
         }
 
         AppArgsSubcommands::ListAnyOf(options) => {
-            let resp =
-                list_any_of_async(&config, &req, &auth_token.header, options.clone()).await?;
-
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            let search = AssetSearch::from(&options);
+
+            write_search_pages_async(
+                list_any_of_pages(
+                    client,
+                    &options.property_key,
+                    &options.property_value,
+                    &search,
+                ),
+                search.all,
+                options.output_type,
+                options.filename.as_ref(),
+                output,
+            )
+            .await?;
         }
 
         AppArgsSubcommands::UpdateAssetName(options) => {
-            update_asset_name_by_id_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.id,
-                options.new_name.clone(),
-            )
-            .await?;
+            update_asset_name_by_id_async(client, options.id, options.new_name.clone()).await?;
         }
 
         AppArgsSubcommands::BulkUpdateAssetName(options) => {
-            bulk_update_asset_name_async(&config, &req, &mut auth_token, options.filename.clone())
-                .await?;
+            bulk_update_asset_name_async(client, options.filename.clone(), concurrency).await?;
         }
 
         AppArgsSubcommands::UpdateAssetLocation(options) => {
-            update_asset_location_async(&config, &req, &auth_token.header, options.clone()).await?;
+            let asset_location_dto = AssetLocationDTO {
+                parent_id: options.new_location_id,
+                rack_position: options.rack_position,
+                rack_side: options.rack_side,
+                rack_u_location: options.rack_u_location,
+            };
+
+            update_asset_location_async(client, options.id, asset_location_dto).await?;
         }
 
         AppArgsSubcommands::BulkUpdateAssetLocation(options) => {
-            bulk_update_asset_location_async(
-                &config,
-                &req,
-                &mut auth_token,
-                options.filename.clone(),
-            )
-            .await?;
+            bulk_update_asset_location_async(client, options.filename.clone(), concurrency).await?;
         }
 
         AppArgsSubcommands::UpdateAssetSerialNumber(options) => {
             update_asset_property_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 options.id,
                 options.new_value.clone(),
                 ASSET_PROPERTY_SERIAL_NUMBER.to_string(),
@@ -229,20 +585,17 @@ pub async fn route_command_async(
 
         AppArgsSubcommands::BulkUpdateAssetSerialNumber(options) => {
             bulk_update_asset_property_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 options.filename.clone(),
                 ASSET_PROPERTY_SERIAL_NUMBER.to_string(),
+                concurrency,
             )
             .await?;
         }
 
         AppArgsSubcommands::UpdateAssetTag(options) => {
             update_asset_property_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 options.id,
                 options.new_value.clone(),
                 ASSET_PROPERTY_ASSET_TAG.to_string(),
@@ -252,20 +605,17 @@ pub async fn route_command_async(
 
         AppArgsSubcommands::BulkUpdateAssetTag(options) => {
             bulk_update_asset_property_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 options.filename.clone(),
                 ASSET_PROPERTY_ASSET_TAG.to_string(),
+                concurrency,
             )
             .await?;
         }
 
         AppArgsSubcommands::UpdatePowerDesignValue(options) => {
             update_asset_property_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 options.id,
                 options.new_value.clone(),
                 ASSET_PROPERTY_DESIGN_VALUE.to_string(),
@@ -275,49 +625,31 @@ pub async fn route_command_async(
 
         AppArgsSubcommands::BulkUpdatePowerDesignValue(options) => {
             bulk_update_asset_property_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 options.filename.clone(),
                 ASSET_PROPERTY_DESIGN_VALUE.to_string(),
+                concurrency,
             )
             .await?;
         }
 
         AppArgsSubcommands::ListAssetPorts(options) => {
-            let resp =
-                list_asset_ports_async(&config, &req, &auth_token.header, options.clone()).await?;
+            let resp = list_asset_ports_async(client, options.id).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::BulkUpdatePatchPanelPorts(options) => {
-            bulk_update_ports_async(
-                &config,
-                &req,
-                &mut auth_token,
-                options.filename.clone(),
-                true,
-            )
-            .await?;
+            bulk_update_ports_async(client, options.filename.clone(), true, concurrency).await?;
         }
 
         AppArgsSubcommands::BulkUpdateAssetPorts(options) => {
-            bulk_update_ports_async(
-                &config,
-                &req,
-                &mut auth_token,
-                options.filename.clone(),
-                false,
-            )
-            .await?;
+            bulk_update_ports_async(client, options.filename.clone(), false, concurrency).await?;
         }
 
         AppArgsSubcommands::UpdateCustomAssetProperty(options) => {
             update_custom_property_by_name_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 options.id,
                 options.custom_property.clone(),
                 options.new_custom_property_value.clone(),
@@ -327,33 +659,29 @@ pub async fn route_command_async(
 
         AppArgsSubcommands::BulkUpdateCustomAssetProperty(options) => {
             bulk_update_custom_property_by_name_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 options.filename.clone(),
+                concurrency,
             )
             .await?;
         }
 
         AppArgsSubcommands::ListAlarms(options) => {
-            let resp = list_alarm_events_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.skip,
-                options.limit,
-                options.alarm_filter,
-            )
-            .await?;
+            let resp =
+                list_alarm_events_async(client, options.skip, options.limit, options.alarm_filter)
+                    .await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp.data)?;
+            handle_output_choice(
+                options.output_type,
+                options.filename.as_ref(),
+                output,
+                resp.data,
+            )?;
         }
 
         AppArgsSubcommands::ManageAlarms(options) => {
             manage_asset_alarm_events_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 options.filename.clone(),
                 options.manage_action,
             )
@@ -362,9 +690,7 @@ pub async fn route_command_async(
 
         AppArgsSubcommands::AddRackAccessory(options) => {
             add_rack_accessory_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 &options.id,
                 &options.panel_type,
                 &options.rack_side,
@@ -374,66 +700,52 @@ pub async fn route_command_async(
         }
 
         AppArgsSubcommands::BulkAddRackAccessory(options) => {
-            bulk_add_rack_accessory_async(&config, &req, &mut auth_token, &options.filename)
-                .await?;
+            bulk_add_rack_accessory_async(client, &options.filename, concurrency).await?;
         }
 
         AppArgsSubcommands::ListAssetSensors(options) => {
-            let resp =
-                get_asset_sensor_list_async(&config, &req, &auth_token.header, options.id).await?;
+            let resp = get_asset_sensor_list_async(client, options.id).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::BulkUpdateAssetSensor(options) => {
-            bulk_update_asset_sensor_async(&config, &req, &mut auth_token, &options.filename)
-                .await?;
+            bulk_update_asset_sensor_async(client, &options.filename, concurrency).await?;
         }
 
         AppArgsSubcommands::ListRackPduOutlets(options) => {
             let resp = get_power_provider_components_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 RACK_PDU_OUTLETS_API_PREFIX,
                 options.id,
             )
             .await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::ListBuswayTapoffs(options) => {
-            let resp = get_power_provider_components_async(
-                &config,
-                &req,
-                &auth_token.header,
-                BUSWAY_TAPOFF_API_PREFIX,
-                options.id,
-            )
-            .await?;
+            let resp =
+                get_power_provider_components_async(client, BUSWAY_TAPOFF_API_PREFIX, options.id)
+                    .await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::ListPduRppBreakers(options) => {
             let resp = get_power_provider_components_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 PDU_RPP_BREAKERS_API_PREFIX,
                 options.id,
             )
             .await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::AddPowerAssociation(options) => {
             add_power_association_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 options.power_consuming_asset_id,
                 options.power_providing_asset_id,
             )
@@ -441,35 +753,25 @@ pub async fn route_command_async(
         }
 
         AppArgsSubcommands::BulkAddPowerAssociation(options) => {
-            bulk_add_power_association_async(&config, &req, &mut auth_token, &options.filename)
-                .await?;
+            bulk_add_power_association_async(client, &options.filename, concurrency).await?;
         }
 
         AppArgsSubcommands::GenerateSensorReport(options) => {
-            let resp =
-                generate_sensor_report_async(&config, &req, &mut auth_token, options.clone())
-                    .await?;
+            let query = SensorReportQuery::try_from(&options)?;
+            let resp = generate_sensor_report_async(client, &query).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::ListBacnetDefinitions(options) => {
-            let resp = list_definitions_async(
-                &config,
-                &req,
-                &auth_token.header,
-                BACNET_DEFINITION_API_PREFIX,
-            )
-            .await?;
+            let resp = list_definitions_async(client, BACNET_DEFINITION_API_PREFIX).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::AddBacnetDefinition(options) => {
             let id = add_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 BACNET_DEFINITION_API_PREFIX,
                 options.name.clone(),
                 options.asset_type.clone(),
@@ -480,74 +782,55 @@ pub async fn route_command_async(
         }
 
         AppArgsSubcommands::ListBacnetNumericSensorDefinitions(options) => {
-            let resp = list_bacnet_numeric_sensor_definitions_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.definition_id,
-            )
-            .await?;
+            let resp =
+                list_bacnet_numeric_sensor_definitions_async(client, options.definition_id).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::ListBacnetNonNumericSensorDefinitions(options) => {
-            let resp = list_bacnet_non_numeric_sensor_definitions_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.definition_id,
-            )
-            .await?;
+            let resp =
+                list_bacnet_non_numeric_sensor_definitions_async(client, options.definition_id)
+                    .await?;
             let resp: Vec<BacnetNonNumericSensorDefinitionExportWrapper> = resp
                 .into_iter()
                 .map(BacnetNonNumericSensorDefinitionExportWrapper)
                 .collect();
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::BulkImportBacnetNumericSensorDefinitions(options) => {
             bulk_import_bacnet_numeric_sensor_definitions_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 &options.filename,
                 options.definition_id,
                 options.create_as_new,
+                concurrency,
             )
             .await?;
         }
 
         AppArgsSubcommands::BulkImportBacnetNonNumericSensorDefinitions(options) => {
             bulk_import_bacnet_non_numeric_sensor_definitions_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 &options.filename,
                 options.definition_id,
                 options.create_as_new,
+                concurrency,
             )
             .await?;
         }
 
         AppArgsSubcommands::ListModbusDefinitions(options) => {
-            let resp = list_definitions_async(
-                &config,
-                &req,
-                &auth_token.header,
-                MODBUS_DEFINITION_API_PREFIX,
-            )
-            .await?;
+            let resp = list_definitions_async(client, MODBUS_DEFINITION_API_PREFIX).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::AddModbusDefinition(options) => {
             let id = add_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 MODBUS_DEFINITION_API_PREFIX,
                 options.name.clone(),
                 options.asset_type.clone(),
@@ -558,99 +841,73 @@ pub async fn route_command_async(
         }
 
         AppArgsSubcommands::ListModbusNumericSensorDefinitions(options) => {
-            let resp = list_modbus_numeric_sensor_definitions_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.definition_id,
-            )
-            .await?;
+            let resp =
+                list_modbus_numeric_sensor_definitions_async(client, options.definition_id).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::ListModbusNonNumericSensorDefinitions(options) => {
-            let resp = list_modbus_non_numeric_sensor_definitions_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.definition_id,
-            )
-            .await?;
+            let resp =
+                list_modbus_non_numeric_sensor_definitions_async(client, options.definition_id)
+                    .await?;
             let resp: Vec<ModbusNonNumericSensorDefinitionExportWrapper> = resp
                 .into_iter()
                 .map(ModbusNonNumericSensorDefinitionExportWrapper)
                 .collect();
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::BulkImportModbusNumericSensorDefinitions(options) => {
             bulk_import_modbus_numeric_sensor_definitions_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 &options.filename,
                 options.definition_id,
                 options.create_as_new,
+                concurrency,
             )
             .await?;
         }
 
         AppArgsSubcommands::BulkImportModbusNonNumericSensorDefinitions(options) => {
             bulk_import_modbus_non_numeric_sensor_definitions_async(
-                &config,
-                &req,
-                &mut auth_token,
+                client,
                 &options.filename,
                 options.definition_id,
                 options.create_as_new,
+                concurrency,
             )
             .await?;
         }
 
         AppArgsSubcommands::ListSensorDefinitionTypes(options) => {
             let resp = list_sensor_definition_types_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 options.asset_type.clone(),
                 options.sensor_class,
             )
             .await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::ListModbusComponents(options) => {
-            let resp = list_modbus_components_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.definition_id,
-            )
-            .await?;
+            let resp = list_modbus_components_async(client, options.definition_id).await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), resp)?;
+            handle_output_choice(options.output_type, options.filename.as_ref(), output, resp)?;
         }
 
         AppArgsSubcommands::AddModbusComponent(options) => {
-            let id = add_modbus_component_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.definition_id,
-                options.name.clone(),
-            )
-            .await?;
+            let id =
+                add_modbus_component_async(client, options.definition_id, options.name.clone())
+                    .await?;
             println!("{id}");
         }
 
         AppArgsSubcommands::UpdateModbusComponent(options) => {
             update_modbus_component_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 options.definition_id,
                 options.component_id,
                 options.name.clone(),
@@ -659,91 +916,73 @@ pub async fn route_command_async(
         }
 
         AppArgsSubcommands::DeleteModbusComponent(options) => {
-            delete_modbus_component_async(
-                &config,
-                &req,
-                &auth_token.header,
-                options.definition_id,
-                options.component_id,
-            )
-            .await?;
+            delete_modbus_component_async(client, options.definition_id, options.component_id)
+                .await?;
         }
 
         AppArgsSubcommands::GetBacnetDefinition(options) => {
-            let resp = get_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
-                BACNET_DEFINITION_API_PREFIX,
-                options.definition_id,
-            )
-            .await?;
+            let resp =
+                get_definition_async(client, BACNET_DEFINITION_API_PREFIX, options.definition_id)
+                    .await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), vec![resp])?;
+            handle_output_choice(
+                options.output_type,
+                options.filename.as_ref(),
+                output,
+                vec![resp],
+            )?;
         }
 
         AppArgsSubcommands::UpdateBacnetDefinition(options) => {
             update_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 BACNET_DEFINITION_API_PREFIX,
-                &options,
+                options.definition_id,
+                options.name,
+                options.asset_type,
+                options.description,
             )
             .await?;
         }
 
         AppArgsSubcommands::DeleteBacnetDefinition(options) => {
-            delete_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
-                BACNET_DEFINITION_API_PREFIX,
-                options.definition_id,
-            )
-            .await?;
+            delete_definition_async(client, BACNET_DEFINITION_API_PREFIX, options.definition_id)
+                .await?;
         }
 
         AppArgsSubcommands::GetModbusDefinition(options) => {
-            let resp = get_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
-                MODBUS_DEFINITION_API_PREFIX,
-                options.definition_id,
-            )
-            .await?;
+            let resp =
+                get_definition_async(client, MODBUS_DEFINITION_API_PREFIX, options.definition_id)
+                    .await?;
 
-            handle_output_choice(options.output_type, options.filename.as_ref(), vec![resp])?;
+            handle_output_choice(
+                options.output_type,
+                options.filename.as_ref(),
+                output,
+                vec![resp],
+            )?;
         }
 
         AppArgsSubcommands::UpdateModbusDefinition(options) => {
             update_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 MODBUS_DEFINITION_API_PREFIX,
-                &options,
+                options.definition_id,
+                options.name,
+                options.asset_type,
+                options.description,
             )
             .await?;
         }
 
         AppArgsSubcommands::DeleteModbusDefinition(options) => {
-            delete_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
-                MODBUS_DEFINITION_API_PREFIX,
-                options.definition_id,
-            )
-            .await?;
+            delete_definition_async(client, MODBUS_DEFINITION_API_PREFIX, options.definition_id)
+                .await?;
         }
 
         AppArgsSubcommands::DeleteBacnetNumericSensorDefinition(options) => {
             delete_sensor_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 BACNET_DEFINITION_API_PREFIX,
                 "bacnetIpNumericSensors",
                 options.definition_id,
@@ -754,9 +993,7 @@ pub async fn route_command_async(
 
         AppArgsSubcommands::DeleteBacnetNonNumericSensorDefinition(options) => {
             delete_sensor_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 BACNET_DEFINITION_API_PREFIX,
                 "bacnetIpNonNumericSensors",
                 options.definition_id,
@@ -767,9 +1004,7 @@ pub async fn route_command_async(
 
         AppArgsSubcommands::DeleteModbusNumericSensorDefinition(options) => {
             delete_sensor_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 MODBUS_DEFINITION_API_PREFIX,
                 "modbusTcpNumericSensors",
                 options.definition_id,
@@ -780,9 +1015,7 @@ pub async fn route_command_async(
 
         AppArgsSubcommands::DeleteModbusNonNumericSensorDefinition(options) => {
             delete_sensor_definition_async(
-                &config,
-                &req,
-                &auth_token.header,
+                client,
                 MODBUS_DEFINITION_API_PREFIX,
                 "modbusTcpNonNumericSensors",
                 options.definition_id,
@@ -799,22 +1032,103 @@ pub async fn route_command_async(
 mod tests {
     use super::*;
     use crate::AppConfig;
+    use crate::hyperview::asset_api_data::AssetTypes;
+    use crate::hyperview::asset_api_data::SortKey;
+    use crate::hyperview::cli_data::{AppCommand, CompletionsArgs, ListProfilesArgs};
+    use clap::Parser;
     use std::fs::File;
-    use std::io::{BufReader, Read, Write};
+    use std::io::{Read, Write};
     use tempfile::NamedTempFile;
 
+    fn report_args() -> GenerateSensorReportArgs {
+        GenerateSensorReportArgs {
+            asset_type: AssetTypes::Rack,
+            sensor: "s".to_string(),
+            year: Some(2026),
+            month: Some(2),
+            start: None,
+            end: None,
+            custom_property: None,
+            location_path: None,
+            manufacturer: None,
+            product: None,
+            skip: 0,
+            limit: 100,
+            all: false,
+            max_results: None,
+            filter: None,
+            raw_filter: None,
+            output_type: OutputOptions::Record,
+            filename: None,
+        }
+    }
+
     #[test]
-    fn test_get_config_path() {
-        let config_path = get_config_path();
-        let home_path = dirs::home_dir().unwrap();
-        let expected_path = format!(
-            "{}{}.hyperview{}hyperview.toml",
-            home_path.to_str().unwrap(),
-            MAIN_SEPARATOR_STR,
-            MAIN_SEPARATOR_STR
-        );
+    fn test_resolve_date_range_year_month_happy_path() {
+        let mut args = report_args();
+        args.year = Some(2026);
+        args.month = Some(2);
+        let (start, end) = resolve_date_range(&args).unwrap();
+        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 2, 1).unwrap());
+        assert_eq!(end, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
+    }
+
+    #[test]
+    fn test_resolve_date_range_december_rollover() {
+        let mut args = report_args();
+        args.year = Some(2026);
+        args.month = Some(12);
+        let (start, end) = resolve_date_range(&args).unwrap();
+        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 12, 1).unwrap());
+        assert_eq!(end, NaiveDate::from_ymd_opt(2027, 1, 1).unwrap());
+    }
+
+    #[test]
+    fn test_resolve_date_range_start_end_overrides() {
+        let mut args = report_args();
+        args.year = None;
+        args.month = None;
+        args.start = Some("2026-02-15".to_string());
+        args.end = Some("2026-02-20".to_string());
+        let (start, end) = resolve_date_range(&args).unwrap();
+        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 2, 15).unwrap());
+        assert_eq!(end, NaiveDate::from_ymd_opt(2026, 2, 20).unwrap());
+    }
+
+    #[test]
+    fn test_resolve_date_range_rejects_mixing_ym_and_range() {
+        let mut args = report_args();
+        args.start = Some("2026-02-15".to_string());
+        args.end = Some("2026-02-20".to_string());
+        // year/month still set from base_args
+        assert!(resolve_date_range(&args).is_err());
+    }
+
+    #[test]
+    fn test_resolve_date_range_rejects_when_nothing_supplied() {
+        let mut args = report_args();
+        args.year = None;
+        args.month = None;
+        assert!(resolve_date_range(&args).is_err());
+    }
 
-        assert_eq!(config_path, expected_path);
+    #[test]
+    fn test_resolve_date_range_rejects_start_without_end() {
+        let mut args = report_args();
+        args.year = None;
+        args.month = None;
+        args.start = Some("2026-02-15".to_string());
+        assert!(resolve_date_range(&args).is_err());
+    }
+
+    #[test]
+    fn test_resolve_date_range_rejects_bad_date_format() {
+        let mut args = report_args();
+        args.year = None;
+        args.month = None;
+        args.start = Some("not-a-date".to_string());
+        args.end = Some("2026-02-20".to_string());
+        assert!(resolve_date_range(&args).is_err());
     }
 
     #[test]
@@ -852,36 +1166,11 @@ mod tests {
     }
 
     #[test]
-    fn test_write_output() {
-        // Create test data
-        let data = vec![1, 2, 3, 4, 5];
-
-        // Create a temporary file
-        let temp_file = NamedTempFile::new().unwrap();
-        let temp_file_path = temp_file.path().to_str().unwrap().to_string();
-
-        // Call the function with the test data and the temporary file path
-        let result = write_output(temp_file_path.clone(), data);
-        assert!(result.is_ok());
-
-        // Read back the file
-        let file = File::open(temp_file_path).unwrap();
-        let mut reader = BufReader::new(file);
-        let mut contents = String::new();
-        reader.read_to_string(&mut contents).unwrap();
-
-        assert_eq!("1\n2\n3\n4\n5\n", contents);
-    }
-
-    #[test]
-    fn test_handle_output_choice_no_filename() {
-        let output_type = OutputOptions::CsvFile;
-        let filename = None;
-        let resp: Vec<i32> = vec![1, 2, 3, 4, 5];
+    fn test_handle_output_choice_no_filename_writes_to_stdout() {
+        for output_type in [OutputOptions::CsvFile, OutputOptions::Ndjson] {
+            let resp: Vec<i32> = vec![1, 2, 3, 4, 5];
 
-        match handle_output_choice(output_type, filename, resp) {
-            Err(e) => assert_eq!(e.to_string(), AppError::NoOutputFilename.to_string()),
-            _ => panic!("Expected Err, but got Ok"),
+            handle_output_choice(output_type, None, &OutputArgs::default(), resp).unwrap();
         }
     }
 
@@ -892,7 +1181,7 @@ mod tests {
         let filename = Some(temp_file.path().to_str().unwrap().to_string());
         let resp: Vec<i32> = vec![1, 2, 3, 4, 5];
 
-        match handle_output_choice(output_type, filename.as_ref(), resp) {
+        match handle_output_choice(output_type, filename.as_ref(), &OutputArgs::default(), resp) {
             Err(e) => assert_eq!(e.to_string(), AppError::FileExists.to_string()),
             _ => panic!("Expected Err, but got Ok"),
         }
@@ -906,7 +1195,8 @@ mod tests {
         let filename = temp_file_path.clone() + "_new";
         let resp: Vec<i32> = vec![1, 2, 3, 4, 5];
 
-        let result = handle_output_choice(output_type, Some(&filename), resp);
+        let result =
+            handle_output_choice(output_type, Some(&filename), &OutputArgs::default(), resp);
         assert!(result.is_ok());
 
         let mut file = File::open(filename).unwrap();
@@ -916,4 +1206,277 @@ mod tests {
         // Check the contents of the file
         assert_eq!(contents, "1\n2\n3\n4\n5\n");
     }
+
+    #[test]
+    fn test_handle_output_choice_overwrites_and_appends_csv() {
+        #[derive(Serialize)]
+        struct Row {
+            id: u32,
+            name: &'static str,
+        }
+        impl Display for Row {
+            fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "{}", self.name)
+            }
+        }
+
+        let dir = tempfile::tempdir().unwrap();
+        let filename = dir.path().join("report.csv").to_str().unwrap().to_string();
+        let write = |output: &OutputArgs, id| {
+            handle_output_choice(
+                OutputOptions::CsvFile,
+                Some(&filename),
+                output,
+                vec![Row { id, name: "rack" }],
+            )
+        };
+        let overwrite = OutputArgs {
+            overwrite: true,
+            ..Default::default()
+        };
+        let append = OutputArgs {
+            append: true,
+            ..Default::default()
+        };
+
+        write(&OutputArgs::default(), 1).unwrap();
+        write(&overwrite, 2).unwrap();
+        write(&append, 3).unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&filename).unwrap(),
+            "id,name\n2,rack\n3,rack\n"
+        );
+
+        let mismatched = OutputArgs {
+            append: true,
+            columns: Some(vec!["name".to_string()]),
+            ..Default::default()
+        };
+        let err = write(&mismatched, 4).unwrap_err();
+        assert!(err.to_string().contains("id,name"));
+        assert_eq!(
+            std::fs::read_to_string(&filename).unwrap(),
+            "id,name\n2,rack\n3,rack\n"
+        );
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+        let err = handle_output_choice(OutputOptions::Json, Some(&filename), &append, vec![1])
+            .unwrap_err()
+            .downcast::<AppError>()
+            .unwrap();
+        assert!(matches!(err, AppError::AppendUnsupported(name) if name == "json"));
+    }
+
+    #[test]
+    fn test_handle_output_choice_writes_full_table_to_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let filename = dir.path().join("racks.txt").to_str().unwrap().to_string();
+        let resp = vec![serde_json::json!({
+            "name": "rack-1",
+            "path": "All/Building A/Floor 1/Room 101/Row 4",
+        })];
+
+        handle_output_choice(
+            OutputOptions::Table,
+            Some(&filename),
+            &OutputArgs::default(),
+            resp,
+        )
+        .unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(filename).unwrap(),
+            "name    path\nrack-1  All/Building A/Floor 1/Room 101/Row 4\n"
+        );
+    }
+
+    #[test]
+    fn test_asset_search_from_args_splits_filters_and_skips_malformed() {
+        let args = AppArgs::parse_from([
+            "hvcli",
+            "search-assets",
+            "-p",
+            "ups",
+            "-P",
+            "serialNumber = SN-1",
+            "-P",
+            "no-equals-sign",
+            "-C",
+            "Owner=Ops",
+            "-l",
+            "25",
+        ]);
+        let AppCommand::Api(AppArgsSubcommands::SearchAssets(options)) = args.command else {
+            panic!("expected search-assets");
+        };
+
+        let search = AssetSearch::from(&options);
+
+        assert_eq!(
+            search,
+            AssetSearch::new()
+                .search_pattern("ups")
+                .property("serialNumber ", " SN-1")
+                .custom_property("Owner", "Ops")
+                .limit(25)
+        );
+    }
+
+    #[test]
+    fn test_filter_expression_is_checked_when_parsing_args() {
+        let args = AppArgs::try_parse_from([
+            "hvcli",
+            "search-assets",
+            "--filter",
+            "property.designValue > 5000 AND NOT assetLifecycleState = retired",
+        ])
+        .unwrap();
+        let AppCommand::Api(AppArgsSubcommands::SearchAssets(options)) = args.command else {
+            panic!("expected search-assets");
+        };
+        assert_eq!(
+            AssetSearch::from(&options).filter.unwrap().search_syntax(),
+            "assetProperty_designValue > 5000 AND NOT assetLifecycleState = 'retired'"
+        );
+
+        let Err(err) =
+            AppArgs::try_parse_from(["hvcli", "search-assets", "--filter", "designValue > high"])
+        else {
+            panic!("an invalid --filter must be rejected");
+        };
+        assert!(err.to_string().contains("'designValue >' needs a number"));
+    }
+
+    #[test]
+    fn test_sort_keys_are_parsed_and_checked() {
+        let args = AppArgs::try_parse_from([
+            "hvcli",
+            "list-any-of",
+            "-k",
+            "assetTag",
+            "-v",
+            "A1",
+            "--sort",
+            "path:DESC,name",
+            "--sort",
+            "id:asc",
+        ])
+        .unwrap();
+        let AppCommand::Api(AppArgsSubcommands::ListAnyOf(options)) = args.command else {
+            panic!("expected list-any-of");
+        };
+        assert_eq!(
+            AssetSearch::from(&options).sort,
+            [
+                SortKey::descending("path"),
+                SortKey::ascending("name"),
+                SortKey::ascending("id")
+            ]
+        );
+
+        for value in ["size", "name:up", "name:"] {
+            let err = value.parse::<SortKey>().unwrap_err();
+            assert!(matches!(err, AppError::InvalidSort { .. }), "{value}");
+        }
+    }
+
+    #[test]
+    fn test_all_conflicts_with_limit() {
+        for command in [
+            &["search-assets"][..],
+            &["list-any-of", "-k", "assetTag", "-v", "A1"],
+            &[
+                "generate-sensor-report",
+                "-t",
+                "rack",
+                "-s",
+                "s",
+                "-y",
+                "2026",
+                "-m",
+                "1",
+            ],
+        ] {
+            let args = [&["hvcli"], command, &["--all"]].concat();
+            assert!(AppArgs::try_parse_from(&args).is_ok(), "{args:?}");
+
+            let args = [&args[..], &["--limit", "10"]].concat();
+            assert!(AppArgs::try_parse_from(&args).is_err(), "{args:?}");
+        }
+    }
+
+    #[test]
+    fn test_only_api_commands_parse_as_api_commands() {
+        let parse = |args: &[&str]| {
+            AppArgs::try_parse_from(std::iter::once("hvcli").chain(args.iter().copied()))
+                .unwrap()
+                .command
+        };
+
+        assert!(matches!(
+            parse(&["search-assets", "-p", "ups"]),
+            AppCommand::Api(_)
+        ));
+        assert!(matches!(parse(&["list-alarms"]), AppCommand::Api(_)));
+        assert!(matches!(
+            parse(&["completions", "bash"]),
+            AppCommand::Offline(_)
+        ));
+        assert!(matches!(parse(&["logout"]), AppCommand::Offline(_)));
+        assert!(matches!(
+            parse(&["csv-template", "bulk-update-asset-tag"]),
+            AppCommand::Offline(_)
+        ));
+        assert!(matches!(
+            parse(&[
+                "validate-sensor-definition-csv",
+                "-k",
+                "modbus-numeric",
+                "-f",
+                "x.csv"
+            ]),
+            AppCommand::Offline(_)
+        ));
+        assert!(matches!(
+            parse(&["config", "list-profiles"]),
+            AppCommand::Offline(_)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_route_offline_command_async_needs_no_config() {
+        let missing = Path::new("/nonexistent/.hyperview/hyperview.toml");
+        let command = OfflineSubcommands::Completions(CompletionsArgs { shell: Shell::Bash });
+
+        route_offline_command_async(
+            command,
+            Some(missing),
+            Some("no-such-profile"),
+            &OutputArgs::default(),
+        )
+        .await
+        .unwrap();
+        route_offline_command_async(
+            OfflineSubcommands::Config(ConfigSubcommands::ListProfiles(ListProfilesArgs {
+                output_type: OutputOptions::Json,
+                filename: None,
+            })),
+            None,
+            None,
+            &OutputArgs::default(),
+        )
+        .await
+        .unwrap();
+    }
+
+    #[test]
+    fn test_write_completions_covers_subcommands() {
+        let mut out = Vec::new();
+        write_completions(Shell::Bash, &mut out).unwrap();
+        let script = String::from_utf8(out).unwrap();
+
+        assert!(script.contains("hvcli"));
+        assert!(script.contains("search-assets"));
+        assert!(script.contains("list-profiles"));
+    }
 }
@@ -0,0 +1,182 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Name of the implicit profile formed by the connection settings at the top level of the config
+/// file. Files written before named profiles existed load as this profile unchanged.
+pub const DEFAULT_PROFILE_NAME: &str = "default";
+
+// Environment variables that override the matching `AppConfig` field of the selected profile.
+pub const ENV_CLIENT_ID: &str = "HVCLI_CLIENT_ID";
+pub const ENV_CLIENT_ID_FILE: &str = "HVCLI_CLIENT_ID_FILE";
+pub const ENV_CLIENT_ID_COMMAND: &str = "HVCLI_CLIENT_ID_COMMAND";
+pub const ENV_CLIENT_SECRET: &str = "HVCLI_CLIENT_SECRET";
+pub const ENV_CLIENT_SECRET_FILE: &str = "HVCLI_CLIENT_SECRET_FILE";
+pub const ENV_CLIENT_SECRET_COMMAND: &str = "HVCLI_CLIENT_SECRET_COMMAND";
+pub const ENV_SCOPE: &str = "HVCLI_SCOPE";
+pub const ENV_AUTH_URL: &str = "HVCLI_AUTH_URL";
+pub const ENV_TOKEN_URL: &str = "HVCLI_TOKEN_URL";
+pub const ENV_DEVICE_AUTH_URL: &str = "HVCLI_DEVICE_AUTH_URL";
+pub const ENV_INSTANCE_URL: &str = "HVCLI_INSTANCE_URL";
+pub const ENV_TOKEN_CACHE: &str = "HVCLI_TOKEN_CACHE";
+pub const ENV_CONNECT_TIMEOUT_SECS: &str = "HVCLI_CONNECT_TIMEOUT_SECS";
+pub const ENV_READ_TIMEOUT_SECS: &str = "HVCLI_READ_TIMEOUT_SECS";
+pub const ENV_PROXY: &str = "HVCLI_PROXY";
+pub const ENV_NO_PROXY: &str = "HVCLI_NO_PROXY";
+pub const ENV_CA_FILES: &str = "HVCLI_CA_FILES";
+pub const ENV_INSECURE: &str = "HVCLI_INSECURE";
+pub const ENV_USER_AGENT_SUFFIX: &str = "HVCLI_USER_AGENT_SUFFIX";
+pub const ENV_MAX_ATTEMPTS: &str = "HVCLI_MAX_ATTEMPTS";
+pub const ENV_RETRY_BASE_DELAY_MS: &str = "HVCLI_RETRY_BASE_DELAY_MS";
+pub const ENV_RETRY_MAX_DELAY_SECS: &str = "HVCLI_RETRY_MAX_DELAY_SECS";
+pub const ENV_RATE_LIMIT_PER_SEC: &str = "HVCLI_RATE_LIMIT_PER_SEC";
+pub const ENV_RATE_LIMIT_BURST: &str = "HVCLI_RATE_LIMIT_BURST";
+
+/// File, next to the config file, that holds cached access tokens when `token_cache` is enabled.
+pub const TOKEN_CACHE_FILENAME: &str = "token_cache.json";
+
+/// On-disk layout of `hyperview.toml`. Each `[profiles.<name>]` table holds one set of connection
+/// settings; `default_profile` names the one used when `--profile` is not given.
+#[derive(Debug, Serialize, Deserialize, Default, Clone)]
+pub struct AppConfigFile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_profile: Option<String>,
+
+    #[serde(flatten)]
+    pub top_level: Option<AppConfig>,
+
+    #[serde(default)]
+    pub profiles: BTreeMap<String, AppConfig>,
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ProfileSummaryDto {
+    pub name: String,
+    pub instance_url: String,
+    pub is_default: bool,
+}
+
+impl fmt::Display for ProfileSummaryDto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "name: {}\ninstance url: {}\ndefault: {}",
+            self.name, self.instance_url, self.is_default
+        )
+    }
+}
+
+/// Outcome of one `config validate` step.
+#[derive(Debug, Serialize, Clone)]
+pub struct ConfigCheckDto {
+    pub step: String,
+    pub passed: bool,
+    pub detail: String,
+}
+
+impl fmt::Display for ConfigCheckDto {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let outcome = if self.passed { "PASS" } else { "FAIL" };
+
+        write!(f, "[{outcome}] {}: {}", self.step, self.detail)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct AppConfig {
+    #[serde(default)]
+    pub client_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id_command: Option<String>,
+    #[serde(default)]
+    pub client_secret: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret_file: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret_command: Option<String>,
+    pub scope: String,
+    pub auth_url: String,
+    pub token_url: String,
+    pub instance_url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub device_auth_url: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub token_cache: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read_timeout_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ca_files: Vec<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub insecure: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub user_agent_suffix: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_attempts: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_base_delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_max_delay_secs: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_per_sec: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit_burst: Option<u32>,
+    /// Name of the profile these settings were resolved from. Set at load time, never stored.
+    #[serde(skip)]
+    pub profile: String,
+}
+
+impl AppConfig {
+    /// True if any client secret source is set. Profiles used only with `hvcli login` need none.
+    pub fn has_client_secret(&self) -> bool {
+        !self.client_secret.is_empty()
+            || self.client_secret_file.is_some()
+            || self.client_secret_command.is_some()
+    }
+}
+
+// Hand-written so the client secret never ends up in a log line or error report.
+impl fmt::Debug for AppConfig {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let client_secret = if self.client_secret.is_empty() {
+            ""
+        } else {
+            "<redacted>"
+        };
+
+        f.debug_struct("AppConfig")
+            .field("client_id", &self.client_id)
+            .field("client_id_file", &self.client_id_file)
+            .field("client_id_command", &self.client_id_command)
+            .field("client_secret", &client_secret)
+            .field("client_secret_file", &self.client_secret_file)
+            .field("client_secret_command", &self.client_secret_command)
+            .field("scope", &self.scope)
+            .field("auth_url", &self.auth_url)
+            .field("token_url", &self.token_url)
+            .field("instance_url", &self.instance_url)
+            .field("device_auth_url", &self.device_auth_url)
+            .field("token_cache", &self.token_cache)
+            .field("connect_timeout_secs", &self.connect_timeout_secs)
+            .field("read_timeout_secs", &self.read_timeout_secs)
+            .field("proxy", &self.proxy)
+            .field("no_proxy", &self.no_proxy)
+            .field("ca_files", &self.ca_files)
+            .field("insecure", &self.insecure)
+            .field("user_agent_suffix", &self.user_agent_suffix)
+            .field("max_attempts", &self.max_attempts)
+            .field("retry_base_delay_ms", &self.retry_base_delay_ms)
+            .field("retry_max_delay_secs", &self.retry_max_delay_secs)
+            .field("rate_limit_per_sec", &self.rate_limit_per_sec)
+            .field("rate_limit_burst", &self.rate_limit_burst)
+            .field("profile", &self.profile)
+            .finish()
+    }
+}
@@ -0,0 +1,1165 @@
+use log::{debug, warn};
+use reqwest::Url;
+use serde::de::IgnoredAny;
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+use super::{
+    api_client::HyperviewClient,
+    api_constants::{ALL_LOCATION_ASSET_ID, ASSET_ASSETS_API_PREFIX},
+    app_errors::AppError,
+    auth::AuthToken,
+    cli_data::ConfigInitArgs,
+    config_data::{
+        AppConfig, AppConfigFile, ConfigCheckDto, DEFAULT_PROFILE_NAME, ENV_AUTH_URL, ENV_CA_FILES,
+        ENV_CLIENT_ID, ENV_CLIENT_ID_COMMAND, ENV_CLIENT_ID_FILE, ENV_CLIENT_SECRET,
+        ENV_CLIENT_SECRET_COMMAND, ENV_CLIENT_SECRET_FILE, ENV_CONNECT_TIMEOUT_SECS,
+        ENV_DEVICE_AUTH_URL, ENV_INSECURE, ENV_INSTANCE_URL, ENV_MAX_ATTEMPTS, ENV_NO_PROXY,
+        ENV_PROXY, ENV_RATE_LIMIT_BURST, ENV_RATE_LIMIT_PER_SEC, ENV_READ_TIMEOUT_SECS,
+        ENV_RETRY_BASE_DELAY_MS, ENV_RETRY_MAX_DELAY_SECS, ENV_SCOPE, ENV_TOKEN_CACHE,
+        ENV_TOKEN_URL, ENV_USER_AGENT_SUFFIX, ProfileSummaryDto,
+    },
+    token_cache::TokenCache,
+};
+
+/// Default config file location, `$HOME/.hyperview/hyperview.toml`. Returns `None` when the
+/// process has no home directory (e.g. a minimal container user).
+pub fn get_config_path() -> Option<PathBuf> {
+    dirs::home_dir().map(|home_path| home_path.join(".hyperview").join("hyperview.toml"))
+}
+
+/// Reads the config file at `path`. A missing file, or no path at all, is not an error: it yields
+/// an empty top-level profile so the settings can come entirely from the environment.
+pub fn load_config_file(path: Option<&Path>) -> color_eyre::Result<AppConfigFile> {
+    match path {
+        Some(p) if p.exists() => {
+            debug!("Loading config file: {}", p.display());
+            Ok(confy::load_path(p)?)
+        }
+
+        Some(p) => {
+            debug!(
+                "Config file {} not found; using environment settings only",
+                p.display()
+            );
+            Ok(empty_config_file())
+        }
+
+        None => {
+            warn!("No home directory and no --config path; using environment settings only");
+            Ok(empty_config_file())
+        }
+    }
+}
+
+fn empty_config_file() -> AppConfigFile {
+    AppConfigFile {
+        top_level: Some(AppConfig::default()),
+        ..Default::default()
+    }
+}
+
+/// Resolves the connection settings for a run. Values are merged in this order, later sources
+/// winning: the config file (the selected profile), then `HVCLI_*` environment variables, then
+/// command-line flags (`--config` and `--profile` pick the file and profile in the first place).
+pub fn load_app_config(
+    path: Option<&Path>,
+    profile: Option<&str>,
+) -> color_eyre::Result<AppConfig> {
+    let config_file = load_config_file(path)?;
+
+    Ok(resolve_app_config(&config_file, profile)?)
+}
+
+fn resolve_app_config(
+    config_file: &AppConfigFile,
+    profile: Option<&str>,
+) -> Result<AppConfig, AppError> {
+    let mut config = resolve_profile(config_file, profile)?;
+
+    apply_env_overrides(&mut config, |key| std::env::var(key).ok());
+    check_required_values(&config)?;
+
+    Ok(config)
+}
+
+/// Overwrites each field of `config` whose `HVCLI_*` variable is set to a non-empty value.
+/// `lookup` is `std::env::var` in production and a fixed map in tests.
+pub fn apply_env_overrides(config: &mut AppConfig, lookup: impl Fn(&str) -> Option<String>) {
+    let lookup = |key: &str| lookup(key).filter(|v| !v.is_empty());
+
+    override_credential(
+        &lookup,
+        [ENV_CLIENT_ID, ENV_CLIENT_ID_FILE, ENV_CLIENT_ID_COMMAND],
+        &mut config.client_id,
+        &mut config.client_id_file,
+        &mut config.client_id_command,
+    );
+    override_credential(
+        &lookup,
+        [
+            ENV_CLIENT_SECRET,
+            ENV_CLIENT_SECRET_FILE,
+            ENV_CLIENT_SECRET_COMMAND,
+        ],
+        &mut config.client_secret,
+        &mut config.client_secret_file,
+        &mut config.client_secret_command,
+    );
+
+    let fields: [(&str, &mut String); 4] = [
+        (ENV_SCOPE, &mut config.scope),
+        (ENV_AUTH_URL, &mut config.auth_url),
+        (ENV_TOKEN_URL, &mut config.token_url),
+        (ENV_INSTANCE_URL, &mut config.instance_url),
+    ];
+
+    for (key, field) in fields {
+        if let Some(value) = lookup(key) {
+            debug!("Overriding config value from {key}");
+            *field = value;
+        }
+    }
+
+    let optional_fields: [(&str, &mut Option<String>); 4] = [
+        (ENV_DEVICE_AUTH_URL, &mut config.device_auth_url),
+        (ENV_PROXY, &mut config.proxy),
+        (ENV_NO_PROXY, &mut config.no_proxy),
+        (ENV_USER_AGENT_SUFFIX, &mut config.user_agent_suffix),
+    ];
+
+    for (key, field) in optional_fields {
+        if let Some(value) = lookup(key) {
+            debug!("Overriding config value from {key}");
+            *field = Some(value);
+        }
+    }
+
+    if let Some(value) = lookup(ENV_CA_FILES) {
+        debug!("Overriding config value from {ENV_CA_FILES}");
+        config.ca_files = std::env::split_paths(&value)
+            .map(|p| p.display().to_string())
+            .collect();
+    }
+
+    let flags: [(&str, &mut bool); 2] = [
+        (ENV_TOKEN_CACHE, &mut config.token_cache),
+        (ENV_INSECURE, &mut config.insecure),
+    ];
+
+    for (key, field) in flags {
+        if let Some(value) = lookup(key) {
+            match parse_env_flag(&value) {
+                Some(enabled) => {
+                    debug!("Overriding config value from {key}");
+                    *field = enabled;
+                }
+                None => warn!("Ignoring {key}={value:?}; expected true/false or 1/0"),
+            }
+        }
+    }
+
+    let timeouts: [(&str, &mut Option<u64>); 2] = [
+        (ENV_CONNECT_TIMEOUT_SECS, &mut config.connect_timeout_secs),
+        (ENV_READ_TIMEOUT_SECS, &mut config.read_timeout_secs),
+    ];
+
+    for (key, field) in timeouts {
+        if let Some(value) = lookup(key) {
+            match value.trim().parse() {
+                Ok(secs) => {
+                    debug!("Overriding config value from {key}");
+                    *field = Some(secs);
+                }
+                Err(_) => warn!("Ignoring {key}={value:?}; expected a number of seconds"),
+            }
+        }
+    }
+
+    if let Some(value) = lookup(ENV_MAX_ATTEMPTS) {
+        match value.trim().parse() {
+            Ok(attempts) if attempts > 0 => {
+                debug!("Overriding config value from {ENV_MAX_ATTEMPTS}");
+                config.max_attempts = Some(attempts);
+            }
+            _ => warn!("Ignoring {ENV_MAX_ATTEMPTS}={value:?}; expected a number of at least 1"),
+        }
+    }
+
+    let retry_delays: [(&str, &mut Option<u64>); 2] = [
+        (ENV_RETRY_BASE_DELAY_MS, &mut config.retry_base_delay_ms),
+        (ENV_RETRY_MAX_DELAY_SECS, &mut config.retry_max_delay_secs),
+    ];
+
+    for (key, field) in retry_delays {
+        if let Some(value) = lookup(key) {
+            match value.trim().parse() {
+                Ok(delay) => {
+                    debug!("Overriding config value from {key}");
+                    *field = Some(delay);
+                }
+                Err(_) => warn!("Ignoring {key}={value:?}; expected a whole number"),
+            }
+        }
+    }
+
+    if let Some(value) = lookup(ENV_RATE_LIMIT_PER_SEC) {
+        match value.trim().parse::<f64>() {
+            Ok(rate) if rate.is_finite() && rate >= 0.0 => {
+                debug!("Overriding config value from {ENV_RATE_LIMIT_PER_SEC}");
+                config.rate_limit_per_sec = Some(rate);
+            }
+            _ => warn!(
+                "Ignoring {ENV_RATE_LIMIT_PER_SEC}={value:?}; expected a number of requests per second"
+            ),
+        }
+    }
+
+    if let Some(value) = lookup(ENV_RATE_LIMIT_BURST) {
+        match value.trim().parse() {
+            Ok(burst) => {
+                debug!("Overriding config value from {ENV_RATE_LIMIT_BURST}");
+                config.rate_limit_burst = Some(burst);
+            }
+            Err(_) => warn!("Ignoring {ENV_RATE_LIMIT_BURST}={value:?}; expected a whole number"),
+        }
+    }
+}
+
+fn parse_env_flag(value: &str) -> Option<bool> {
+    match value.trim().to_ascii_lowercase().as_str() {
+        "1" | "true" | "yes" | "on" => Some(true),
+        "0" | "false" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// A credential can come from an inline value, a file or a command. If the environment sets any
+/// of the three, it replaces all of them, so `HVCLI_CLIENT_SECRET` wins over a
+/// `client_secret_file` from the config file rather than conflicting with it.
+fn override_credential(
+    lookup: &impl Fn(&str) -> Option<String>,
+    [value_key, file_key, command_key]: [&str; 3],
+    value: &mut String,
+    file: &mut Option<String>,
+    command: &mut Option<String>,
+) {
+    let (env_value, env_file, env_command) =
+        (lookup(value_key), lookup(file_key), lookup(command_key));
+
+    if env_value.is_none() && env_file.is_none() && env_command.is_none() {
+        return;
+    }
+
+    debug!("Overriding {value_key} source from the environment");
+    *value = env_value.unwrap_or_default();
+    *file = env_file;
+    *command = env_command;
+}
+
+fn check_required_values(config: &AppConfig) -> Result<(), AppError> {
+    let has_client_id = !config.client_id.is_empty()
+        || config.client_id_file.is_some()
+        || config.client_id_command.is_some();
+
+    // The client secret is checked when a token is requested with it: after `hvcli login` a
+    // profile may have none.
+    let fields = [
+        ("client_id", ENV_CLIENT_ID, has_client_id),
+        ("scope", ENV_SCOPE, !config.scope.is_empty()),
+        ("auth_url", ENV_AUTH_URL, !config.auth_url.is_empty()),
+        ("token_url", ENV_TOKEN_URL, !config.token_url.is_empty()),
+        (
+            "instance_url",
+            ENV_INSTANCE_URL,
+            !config.instance_url.is_empty(),
+        ),
+    ];
+
+    match fields.iter().find(|(_, _, is_set)| !is_set) {
+        Some((field, env_var, _)) => Err(AppError::MissingConfigValue {
+            field: (*field).to_string(),
+            env_var: (*env_var).to_string(),
+        }),
+        None => Ok(()),
+    }
+}
+
+pub fn resolve_profile(
+    config_file: &AppConfigFile,
+    profile: Option<&str>,
+) -> Result<AppConfig, AppError> {
+    let name = selected_profile_name(config_file, profile);
+    debug!("Using config profile: {name}");
+
+    let mut config = match (config_file.profiles.get(name), &config_file.top_level) {
+        (Some(config), _) => config.clone(),
+        (None, Some(config)) if name == DEFAULT_PROFILE_NAME => config.clone(),
+        _ => return Err(AppError::ProfileNotFound(name.to_string())),
+    };
+    config.profile = name.to_string();
+
+    Ok(config)
+}
+
+/// The profile a run uses: `--profile`, else the file's `default_profile`, else `default`.
+pub fn selected_profile_name<'a>(
+    config_file: &'a AppConfigFile,
+    profile: Option<&'a str>,
+) -> &'a str {
+    profile
+        .or(config_file.default_profile.as_deref())
+        .unwrap_or(DEFAULT_PROFILE_NAME)
+}
+
+pub fn list_profiles(config_file: &AppConfigFile) -> Vec<ProfileSummaryDto> {
+    let default_name = config_file
+        .default_profile
+        .as_deref()
+        .unwrap_or(DEFAULT_PROFILE_NAME);
+
+    let mut profiles = Vec::new();
+
+    if let Some(config) = &config_file.top_level
+        && !config_file.profiles.contains_key(DEFAULT_PROFILE_NAME)
+    {
+        profiles.push(ProfileSummaryDto {
+            name: DEFAULT_PROFILE_NAME.to_string(),
+            instance_url: config.instance_url.clone(),
+            is_default: default_name == DEFAULT_PROFILE_NAME,
+        });
+    }
+
+    for (name, config) in &config_file.profiles {
+        profiles.push(ProfileSummaryDto {
+            name: name.clone(),
+            instance_url: config.instance_url.clone(),
+            is_default: name == default_name,
+        });
+    }
+
+    profiles
+}
+
+/// Derives the OAuth authorize and token endpoints from an instance URL, e.g.
+/// `https://example.hyperviewhq.com` -> `https://example.hyperviewhq.com/connect/token`.
+pub fn derive_oauth_urls(instance_url: &str) -> Result<(String, String), AppError> {
+    let instance_url = instance_url.trim().trim_end_matches('/');
+
+    match Url::parse(instance_url) {
+        Ok(url) if matches!(url.scheme(), "http" | "https") && url.has_host() => Ok((
+            format!("{instance_url}/connect/authorize"),
+            format!("{instance_url}/connect/token"),
+        )),
+        _ => Err(AppError::InvalidInstanceUrl(instance_url.to_string())),
+    }
+}
+
+/// Interactively creates the config file at `path`. Values given as options are not prompted for;
+/// the client secret is always read from the terminal without echo.
+pub(crate) fn init_config_file(
+    path: Option<&Path>,
+    profile: Option<&str>,
+    options: &ConfigInitArgs,
+) -> color_eyre::Result<PathBuf> {
+    let path = path.ok_or(AppError::NoConfigPath)?;
+
+    // Checked up front as well as on write so the user isn't prompted for nothing.
+    if path.exists() && !options.force {
+        return Err(AppError::ConfigFileExists(path.display().to_string()).into());
+    }
+
+    let instance_url = match &options.instance_url {
+        Some(u) => u.clone(),
+        None => prompt_line("Instance URL (e.g. https://example.hyperviewhq.com)")?,
+    };
+    let (auth_url, token_url) = derive_oauth_urls(&instance_url)?;
+
+    let client_id = match &options.client_id {
+        Some(id) => id.clone(),
+        None => prompt_line("Client id")?,
+    };
+    let client_secret =
+        rpassword::prompt_password("Client secret (leave empty to sign in with `hvcli login`): ")?;
+
+    let config = AppConfig {
+        client_id: client_id.trim().to_string(),
+        client_secret: client_secret.trim().to_string(),
+        scope: options.scope.clone(),
+        auth_url,
+        token_url,
+        instance_url: instance_url.trim().trim_end_matches('/').to_string(),
+        ..Default::default()
+    };
+    check_required_values(&config)?;
+
+    let config_file = if options.force && path.exists() {
+        merge_config_file(load_config_file(Some(path))?, profile, config)
+    } else {
+        new_config_file(profile, config)
+    };
+    write_config_file(path, &config_file, options.force)?;
+
+    Ok(path.to_path_buf())
+}
+
+fn prompt_line(label: &str) -> io::Result<String> {
+    print!("{label}: ");
+    io::stdout().flush()?;
+
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+
+    Ok(line.trim().to_string())
+}
+
+/// Lays out a fresh config file: top-level settings, or a single named profile made the default.
+pub fn new_config_file(profile: Option<&str>, config: AppConfig) -> AppConfigFile {
+    match profile {
+        Some(name) => AppConfigFile {
+            default_profile: Some(name.to_string()),
+            top_level: None,
+            profiles: BTreeMap::from([(name.to_string(), config)]),
+        },
+        None => AppConfigFile {
+            top_level: Some(config),
+            ..Default::default()
+        },
+    }
+}
+
+/// Puts `config` into an existing config file as the settings of `profile`, or as the top-level
+/// settings without one, keeping every other profile. A new named profile becomes the default
+/// only if the file has none.
+pub fn merge_config_file(
+    mut config_file: AppConfigFile,
+    profile: Option<&str>,
+    config: AppConfig,
+) -> AppConfigFile {
+    match profile {
+        Some(name) => {
+            config_file.profiles.insert(name.to_string(), config);
+            config_file
+                .default_profile
+                .get_or_insert_with(|| name.to_string());
+        }
+        None => config_file.top_level = Some(config),
+    }
+
+    config_file
+}
+
+/// Writes `config_file` to `path`, readable and writable by the owner only. Refuses to replace an
+/// existing file unless `force` is set.
+pub fn write_config_file(
+    path: &Path,
+    config_file: &AppConfigFile,
+    force: bool,
+) -> color_eyre::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+
+    let contents = toml::to_string_pretty(config_file)?;
+    if force {
+        // Written beside the config and renamed over it, so a failed write leaves the old file
+        // intact. Temporary files are created owner-only (0600).
+        let mut temp = NamedTempFile::new_in(dir)?;
+        temp.write_all(contents.as_bytes())?;
+        temp.as_file().sync_all()?;
+        temp.persist(path)?;
+    } else {
+        // Created in the same step as the check, so a file that appears meanwhile is not
+        // replaced.
+        let mut file = open_private_file(path, false).map_err(|e| match e.kind() {
+            io::ErrorKind::AlreadyExists => {
+                color_eyre::Report::new(AppError::ConfigFileExists(path.display().to_string()))
+            }
+            _ => e.into(),
+        })?;
+        file.write_all(contents.as_bytes())?;
+    }
+
+    Ok(())
+}
+
+/// Creates `path` for writing, truncating any existing file, readable and writable by the owner
+/// only.
+pub fn create_private_file(path: &Path) -> io::Result<fs::File> {
+    open_private_file(path, true)
+}
+
+/// Opens `path` for writing, readable and writable by the owner only. An existing file is
+/// truncated if `replace` is set, and is an `AlreadyExists` error otherwise.
+fn open_private_file(path: &Path, replace: bool) -> io::Result<fs::File> {
+    let mut open_options = OpenOptions::new();
+    open_options.write(true);
+    if replace {
+        open_options.create(true).truncate(true);
+    } else {
+        open_options.create_new(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        open_options.mode(0o600);
+    }
+
+    let file = open_options.open(path)?;
+
+    // `mode` only applies when the file is created; also tighten an overwritten one.
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+
+    Ok(file)
+}
+
+/// Runs the `config validate` steps in order, stopping at the first failure since every step
+/// depends on the one before it.
+pub async fn validate_config_async(
+    path: Option<&Path>,
+    profile: Option<&str>,
+) -> Vec<ConfigCheckDto> {
+    let mut checks = Vec::new();
+
+    let file_detail = match path {
+        Some(p) if p.exists() => p.display().to_string(),
+        Some(p) => format!("{} not found; using environment settings only", p.display()),
+        None => "no config path; using environment settings only".to_string(),
+    };
+    let Some(config_file) = record_check(
+        &mut checks,
+        "Load config file",
+        load_config_file(path),
+        |_| file_detail,
+    ) else {
+        return checks;
+    };
+
+    let profile_name = selected_profile_name(&config_file, profile).to_string();
+    let Some(config) = record_check(
+        &mut checks,
+        "Resolve profile",
+        resolve_app_config(&config_file, profile).map_err(Into::into),
+        |config| format!("{profile_name} -> {}", config.instance_url),
+    ) else {
+        return checks;
+    };
+
+    if record_check(&mut checks, "Parse URLs", check_urls(&config), |()| {
+        "auth_url, token_url and instance_url are valid".to_string()
+    })
+    .is_none()
+    {
+        return checks;
+    }
+
+    // Client credentials are always sent to the token endpoint, so a rotated or revoked secret
+    // fails here even while a cached token is still valid. Only a profile with no secret, which
+    // runs on a `login` session, needs the cache.
+    let cache = if config.has_client_secret() {
+        None
+    } else {
+        TokenCache::for_config(path, &config)
+    };
+    let Some(auth_token) = record_check(
+        &mut checks,
+        "Fetch access token",
+        AuthToken::fetch_cached_async(&config, cache).await,
+        |_| format!("token issued by {}", config.token_url),
+    ) else {
+        return checks;
+    };
+
+    let target_path = format!("{ASSET_ASSETS_API_PREFIX}/{ALL_LOCATION_ASSET_ID}");
+    let target_url = format!("{}{target_path}", config.instance_url);
+    record_check(
+        &mut checks,
+        "Authenticated request",
+        authenticated_get_async(config, auth_token, &target_path).await,
+        |()| format!("GET {target_url} succeeded"),
+    );
+
+    checks
+}
+
+fn record_check<T>(
+    checks: &mut Vec<ConfigCheckDto>,
+    step: &str,
+    result: color_eyre::Result<T>,
+    detail: impl FnOnce(&T) -> String,
+) -> Option<T> {
+    let (passed, detail, value) = match result {
+        Ok(value) => (true, detail(&value), Some(value)),
+        Err(e) => (false, format!("{e:#}"), None),
+    };
+
+    checks.push(ConfigCheckDto {
+        step: step.to_string(),
+        passed,
+        detail,
+    });
+
+    value
+}
+
+fn check_urls(config: &AppConfig) -> color_eyre::Result<()> {
+    for (field, value) in [
+        ("auth_url", &config.auth_url),
+        ("token_url", &config.token_url),
+        ("instance_url", &config.instance_url),
+    ] {
+        Url::parse(value).map_err(|e| color_eyre::eyre::eyre!("{field} {value:?}: {e}"))?;
+    }
+
+    Ok(())
+}
+
+async fn authenticated_get_async(
+    config: AppConfig,
+    auth_token: AuthToken,
+    target_path: &str,
+) -> color_eyre::Result<()> {
+    HyperviewClient::new(config, auth_token)?
+        .get::<IgnoredAny>(target_path)
+        .await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::io::Write;
+    use tempfile::NamedTempFile;
+
+    fn write_config(contents: &str) -> NamedTempFile {
+        let mut tmp_file = NamedTempFile::new().unwrap();
+        write!(tmp_file, "{contents}").unwrap();
+        tmp_file
+    }
+
+    const PROFILES_CONFIG: &str = r#"
+default_profile = "staging"
+
+[profiles.staging]
+client_id = "staging_id"
+client_secret = "staging_secret"
+scope = "HyperviewManagerApi"
+auth_url = "https://staging.example.test/connect/authorize"
+token_url = "https://staging.example.test/connect/token"
+instance_url = "https://staging.example.test"
+
+[profiles.production]
+client_id = "production_id"
+client_secret = "production_secret"
+scope = "HyperviewManagerApi"
+auth_url = "https://production.example.test/connect/authorize"
+token_url = "https://production.example.test/connect/token"
+instance_url = "https://production.example.test"
+"#;
+
+    const FLAT_CONFIG: &str = r#"
+client_id = "test_id"
+client_secret = "test_secret"
+scope = "test_scope"
+auth_url = "https://test_auth_url"
+token_url = "https://test_token_url"
+instance_url = "https://test_instance_url"
+"#;
+
+    #[test]
+    fn test_load_app_config_uses_default_profile() {
+        let tmp_file = write_config(PROFILES_CONFIG);
+
+        let config = load_app_config(Some(tmp_file.path()), None).unwrap();
+
+        assert_eq!(config.client_id, "staging_id");
+        assert_eq!(config.instance_url, "https://staging.example.test");
+    }
+
+    #[test]
+    fn test_load_app_config_selects_named_profile() {
+        let tmp_file = write_config(PROFILES_CONFIG);
+
+        let config = load_app_config(Some(tmp_file.path()), Some("production")).unwrap();
+
+        assert_eq!(config.client_id, "production_id");
+        assert_eq!(config.client_secret, "production_secret");
+    }
+
+    #[test]
+    fn test_load_app_config_unknown_profile_is_error() {
+        let tmp_file = write_config(PROFILES_CONFIG);
+
+        let err = load_app_config(Some(tmp_file.path()), Some("missing")).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            AppError::ProfileNotFound("missing".to_string()).to_string()
+        );
+    }
+
+    #[test]
+    fn test_load_app_config_flat_file_is_default_profile() {
+        let tmp_file = write_config(FLAT_CONFIG);
+
+        let config = load_app_config(Some(tmp_file.path()), None).unwrap();
+        assert_eq!(config.client_id, "test_id");
+
+        let config = load_app_config(Some(tmp_file.path()), Some(DEFAULT_PROFILE_NAME)).unwrap();
+        assert_eq!(config.instance_url, "https://test_instance_url");
+    }
+
+    #[test]
+    fn test_get_config_path() {
+        let config_path = get_config_path().unwrap();
+        let expected_path = dirs::home_dir()
+            .unwrap()
+            .join(".hyperview")
+            .join("hyperview.toml");
+
+        assert_eq!(config_path, expected_path);
+    }
+
+    #[test]
+    fn test_load_config_file_missing_file_yields_empty_default_profile() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyperview.toml");
+
+        let config_file = load_config_file(Some(&path)).unwrap();
+        let config = resolve_profile(&config_file, None).unwrap();
+
+        assert!(config.instance_url.is_empty());
+        assert!(!path.exists(), "a missing config file must not be created");
+
+        let config_file = load_config_file(None).unwrap();
+        assert!(resolve_profile(&config_file, None).is_ok());
+    }
+
+    #[test]
+    fn test_resolve_profile_records_profile_name() {
+        let tmp_file = write_config(PROFILES_CONFIG);
+
+        let named = load_app_config(Some(tmp_file.path()), Some("production")).unwrap();
+        let flat_file = write_config(FLAT_CONFIG);
+        let flat = load_app_config(Some(flat_file.path()), None).unwrap();
+
+        assert_eq!(named.profile, "production");
+        assert_eq!(flat.profile, DEFAULT_PROFILE_NAME);
+    }
+
+    #[test]
+    fn test_apply_env_overrides_token_cache_flag() {
+        let mut config = AppConfig::default();
+
+        apply_env_overrides(&mut config, |key| {
+            (key == ENV_TOKEN_CACHE).then(|| "TRUE".to_string())
+        });
+        assert!(config.token_cache);
+
+        apply_env_overrides(&mut config, |key| {
+            (key == ENV_TOKEN_CACHE).then(|| "maybe".to_string())
+        });
+        assert!(config.token_cache);
+
+        apply_env_overrides(&mut config, |key| {
+            (key == ENV_TOKEN_CACHE).then(|| "0".to_string())
+        });
+        assert!(!config.token_cache);
+    }
+
+    #[test]
+    fn test_apply_env_overrides_transport_settings() {
+        let mut config = AppConfig {
+            read_timeout_secs: Some(60),
+            ..Default::default()
+        };
+
+        let ca_files =
+            std::env::join_paths(["/etc/ssl/corp-root.pem", "/etc/ssl/corp-issuing.pem"])
+                .unwrap()
+                .into_string()
+                .unwrap();
+        let env: HashMap<&str, String> = HashMap::from([
+            (ENV_CONNECT_TIMEOUT_SECS, "5".to_string()),
+            (ENV_READ_TIMEOUT_SECS, "soon".to_string()),
+            (ENV_PROXY, "http://proxy.corp.test:3128".to_string()),
+            (ENV_NO_PROXY, "localhost,.corp.test".to_string()),
+            (ENV_CA_FILES, ca_files),
+            (ENV_INSECURE, "1".to_string()),
+            (ENV_USER_AGENT_SUFFIX, "jump-host".to_string()),
+            (ENV_MAX_ATTEMPTS, "0".to_string()),
+            (ENV_RETRY_BASE_DELAY_MS, "250".to_string()),
+            (ENV_RETRY_MAX_DELAY_SECS, "10".to_string()),
+            (ENV_RATE_LIMIT_PER_SEC, "2.5".to_string()),
+            (ENV_RATE_LIMIT_BURST, "-1".to_string()),
+        ]);
+        apply_env_overrides(&mut config, |key| env.get(key).cloned());
+
+        assert_eq!(config.connect_timeout_secs, Some(5));
+        assert_eq!(config.read_timeout_secs, Some(60));
+        assert_eq!(config.proxy.as_deref(), Some("http://proxy.corp.test:3128"));
+        assert_eq!(config.no_proxy.as_deref(), Some("localhost,.corp.test"));
+        assert_eq!(
+            config.ca_files,
+            ["/etc/ssl/corp-root.pem", "/etc/ssl/corp-issuing.pem"]
+        );
+        assert!(config.insecure);
+        assert_eq!(config.user_agent_suffix.as_deref(), Some("jump-host"));
+        assert_eq!(config.max_attempts, None);
+        assert_eq!(config.retry_base_delay_ms, Some(250));
+        assert_eq!(config.retry_max_delay_secs, Some(10));
+        assert_eq!(config.rate_limit_per_sec, Some(2.5));
+        assert_eq!(config.rate_limit_burst, None);
+    }
+
+    #[test]
+    fn test_apply_env_overrides_replaces_set_values_only() {
+        let tmp_file = write_config(FLAT_CONFIG);
+        let config_file = load_config_file(Some(tmp_file.path())).unwrap();
+        let mut config = resolve_profile(&config_file, None).unwrap();
+
+        let env: HashMap<&str, &str> = HashMap::from([
+            (ENV_INSTANCE_URL, "https://env.example.test"),
+            (
+                ENV_DEVICE_AUTH_URL,
+                "https://env.example.test/oauth2/v1/device",
+            ),
+            (ENV_CLIENT_SECRET, "env_secret"),
+            (ENV_SCOPE, ""),
+        ]);
+        apply_env_overrides(&mut config, |key| env.get(key).map(ToString::to_string));
+
+        assert_eq!(config.instance_url, "https://env.example.test");
+        assert_eq!(
+            config.device_auth_url.as_deref(),
+            Some("https://env.example.test/oauth2/v1/device")
+        );
+        assert_eq!(config.client_secret, "env_secret");
+        assert_eq!(config.client_id, "test_id");
+        assert_eq!(config.scope, "test_scope");
+    }
+
+    #[test]
+    fn test_apply_env_overrides_completes_missing_file() {
+        let config_file = load_config_file(None).unwrap();
+        let mut config = resolve_profile(&config_file, None).unwrap();
+        assert!(check_required_values(&config).is_err());
+
+        let env: HashMap<&str, &str> = HashMap::from([
+            (ENV_CLIENT_ID, "id"),
+            (ENV_CLIENT_SECRET, "secret"),
+            (ENV_SCOPE, "HyperviewManagerApi"),
+            (ENV_AUTH_URL, "https://example.test/connect/authorize"),
+            (ENV_TOKEN_URL, "https://example.test/connect/token"),
+            (ENV_INSTANCE_URL, "https://example.test"),
+        ]);
+        apply_env_overrides(&mut config, |key| env.get(key).map(ToString::to_string));
+
+        assert!(check_required_values(&config).is_ok());
+    }
+
+    #[test]
+    fn test_check_required_values_names_field_and_variable() {
+        let config = AppConfig {
+            instance_url: String::new(),
+            ..test_complete_config()
+        };
+
+        let err = check_required_values(&config).unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            AppError::MissingConfigValue {
+                field: "instance_url".to_string(),
+                env_var: ENV_INSTANCE_URL.to_string(),
+            }
+            .to_string()
+        );
+    }
+
+    fn test_complete_config() -> AppConfig {
+        AppConfig {
+            client_id: "id".to_string(),
+            client_secret: "secret".to_string(),
+            scope: "scope".to_string(),
+            auth_url: "https://example.test/connect/authorize".to_string(),
+            token_url: "https://example.test/connect/token".to_string(),
+            instance_url: "https://example.test".to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_check_required_values_does_not_require_client_secret() {
+        let config = AppConfig {
+            client_secret: String::new(),
+            client_secret_file: Some("/run/secrets/hyperview".to_string()),
+            ..test_complete_config()
+        };
+        assert!(check_required_values(&config).is_ok());
+
+        let config = AppConfig {
+            client_secret: String::new(),
+            ..test_complete_config()
+        };
+        assert!(check_required_values(&config).is_ok());
+        assert!(!config.has_client_secret());
+    }
+
+    #[test]
+    fn test_apply_env_overrides_credential_replaces_all_file_sources() {
+        let mut config = AppConfig {
+            client_secret: String::new(),
+            client_secret_file: Some("/run/secrets/hyperview".to_string()),
+            ..test_complete_config()
+        };
+
+        let env: HashMap<&str, &str> = HashMap::from([(ENV_CLIENT_SECRET, "env_secret")]);
+        apply_env_overrides(&mut config, |key| env.get(key).map(ToString::to_string));
+
+        assert_eq!(config.client_secret, "env_secret");
+        assert!(config.client_secret_file.is_none());
+
+        let env: HashMap<&str, &str> =
+            HashMap::from([(ENV_CLIENT_SECRET_COMMAND, "pass show hyperview")]);
+        apply_env_overrides(&mut config, |key| env.get(key).map(ToString::to_string));
+
+        assert!(config.client_secret.is_empty());
+        assert_eq!(
+            config.client_secret_command.as_deref(),
+            Some("pass show hyperview")
+        );
+    }
+
+    #[test]
+    fn test_derive_oauth_urls() {
+        let (auth_url, token_url) = derive_oauth_urls("https://example.hyperviewhq.com/").unwrap();
+
+        assert_eq!(
+            auth_url,
+            "https://example.hyperviewhq.com/connect/authorize"
+        );
+        assert_eq!(token_url, "https://example.hyperviewhq.com/connect/token");
+
+        assert!(derive_oauth_urls("example.hyperviewhq.com").is_err());
+        assert!(derive_oauth_urls("ftp://example.hyperviewhq.com").is_err());
+    }
+
+    #[test]
+    fn test_write_config_file_round_trips_and_refuses_overwrite() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".hyperview").join("hyperview.toml");
+
+        write_config_file(
+            &path,
+            &new_config_file(Some("staging"), test_complete_config()),
+            false,
+        )
+        .unwrap();
+
+        let config = load_app_config(Some(&path), None).unwrap();
+        assert_eq!(config.client_secret, "secret");
+        assert_eq!(config.instance_url, "https://example.test");
+
+        let err = write_config_file(&path, &new_config_file(None, test_complete_config()), false)
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            AppError::ConfigFileExists(path.display().to_string()).to_string()
+        );
+
+        write_config_file(&path, &new_config_file(None, test_complete_config()), true).unwrap();
+        let config_file = load_config_file(Some(&path)).unwrap();
+        assert!(config_file.profiles.is_empty());
+        assert!(config_file.top_level.is_some());
+    }
+
+    #[test]
+    fn test_merge_config_file_keeps_other_profiles() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyperview.toml");
+        fs::write(&path, PROFILES_CONFIG).unwrap();
+
+        let existing = load_config_file(Some(&path)).unwrap();
+        let merged = merge_config_file(existing, Some("lab"), test_complete_config());
+        write_config_file(&path, &merged, true).unwrap();
+
+        let names: Vec<String> = list_profiles(&load_config_file(Some(&path)).unwrap())
+            .into_iter()
+            .map(|p| p.name)
+            .collect();
+        assert_eq!(names, ["lab", "production", "staging"]);
+        assert_eq!(
+            load_app_config(Some(&path), None).unwrap().profile,
+            "staging"
+        );
+        let lab = load_app_config(Some(&path), Some("lab")).unwrap();
+        assert_eq!(lab.instance_url, "https://example.test");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_write_config_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyperview.toml");
+        fs::write(&path, "").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+
+        write_config_file(&path, &new_config_file(None, test_complete_config()), true).unwrap();
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[tokio::test]
+    async fn test_validate_config_async_passes_every_step() {
+        use httpmock::prelude::*;
+        use serde_json::json;
+
+        let server = MockServer::start();
+        let token_mock = server.mock(|when, then| {
+            when.method(POST).path("/connect/token");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({
+                    "access_token": "validate_token",
+                    "token_type": "bearer",
+                    "expires_in": 3600
+                }));
+        });
+        let asset_mock = server.mock(|when, then| {
+            when.method(GET)
+                .path(format!("{ASSET_ASSETS_API_PREFIX}/{ALL_LOCATION_ASSET_ID}"))
+                .header("Authorization", "Bearer validate_token");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({"name": "All"}));
+        });
+
+        let instance_url = format!("http://{}", server.address());
+        let (auth_url, token_url) = derive_oauth_urls(&instance_url).unwrap();
+        let config = AppConfig {
+            auth_url,
+            token_url,
+            instance_url,
+            ..test_complete_config()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyperview.toml");
+        write_config_file(&path, &new_config_file(None, config), false).unwrap();
+
+        let checks = validate_config_async(Some(&path), None).await;
+
+        token_mock.assert();
+        asset_mock.assert();
+        assert_eq!(checks.len(), 5);
+        assert!(checks.iter().all(|c| c.passed), "{checks:#?}");
+    }
+
+    #[tokio::test]
+    async fn test_validate_config_async_checks_secret_despite_cached_token() {
+        use crate::hyperview::auth::IssuedToken;
+        use httpmock::prelude::*;
+        use std::time::Duration;
+
+        let server = MockServer::start();
+        let token_mock = server.mock(|when, then| {
+            when.method(POST).path("/connect/token");
+            then.status(401)
+                .header("Content-Type", "application/json")
+                .body(r#"{"error": "invalid_client"}"#);
+        });
+
+        let instance_url = format!("http://{}", server.address());
+        let (auth_url, token_url) = derive_oauth_urls(&instance_url).unwrap();
+        let config = AppConfig {
+            auth_url,
+            token_url,
+            instance_url,
+            token_cache: true,
+            ..test_complete_config()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyperview.toml");
+        write_config_file(&path, &new_config_file(None, config), false).unwrap();
+        let config = load_app_config(Some(&path), None).unwrap();
+        assert!(config.token_cache);
+        TokenCache::for_config(Some(&path), &config)
+            .unwrap()
+            .store(&IssuedToken {
+                access_token: "still_valid".to_string(),
+                lifetime: Duration::from_hours(1),
+                refresh_token: None,
+            });
+
+        let checks = validate_config_async(Some(&path), None).await;
+
+        token_mock.assert();
+        assert_eq!(checks.len(), 4);
+        assert!(!checks[3].passed, "{checks:#?}");
+        assert_eq!(checks[3].step, "Fetch access token");
+    }
+
+    #[tokio::test]
+    async fn test_validate_config_async_stops_at_bad_url() {
+        let config = AppConfig {
+            token_url: "not a url".to_string(),
+            ..test_complete_config()
+        };
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("hyperview.toml");
+        write_config_file(&path, &new_config_file(None, config), false).unwrap();
+
+        let checks = validate_config_async(Some(&path), None).await;
+
+        assert_eq!(checks.len(), 3);
+        assert!(checks[0].passed);
+        assert!(checks[1].passed);
+        assert!(!checks[2].passed);
+        assert!(checks[2].detail.contains("token_url"));
+    }
+
+    #[test]
+    fn test_list_profiles_marks_default() {
+        let tmp_file = write_config(PROFILES_CONFIG);
+        let config_file: AppConfigFile = confy::load_path(tmp_file.path()).unwrap();
+
+        let profiles = list_profiles(&config_file);
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "production");
+        assert!(!profiles[0].is_default);
+        assert_eq!(profiles[1].name, "staging");
+        assert_eq!(profiles[1].instance_url, "https://staging.example.test");
+        assert!(profiles[1].is_default);
+    }
+
+    #[test]
+    fn test_list_profiles_includes_top_level_settings() {
+        let tmp_file = write_config(FLAT_CONFIG);
+        let config_file: AppConfigFile = confy::load_path(tmp_file.path()).unwrap();
+
+        let profiles = list_profiles(&config_file);
+
+        assert_eq!(profiles.len(), 1);
+        assert_eq!(profiles[0].name, DEFAULT_PROFILE_NAME);
+        assert_eq!(profiles[0].instance_url, "https://test_instance_url");
+        assert!(profiles[0].is_default);
+    }
+}
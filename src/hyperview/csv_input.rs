@@ -0,0 +1,253 @@
+use clap::ValueEnum;
+use csv::StringRecord;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::path::Path;
+
+use super::{
+    bacnet_definition_api_data::{
+        BacnetNonNumericSensorDefinitionImportCsv, BacnetNonNumericSensorDefinitionImportDto,
+        BacnetNumericSensorDefinitionImportDto,
+    },
+    modbus_definition_api_data::{
+        ModbusNonNumericSensorDefinitionImportCsv, ModbusNonNumericSensorDefinitionImportDto,
+        ModbusNumericSensorDefinitionImportDto,
+    },
+};
+
+/// The CSV file a bulk command reads, named after the command.
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum CsvTemplate {
+    BulkUpdateAssetName,
+    BulkUpdateAssetLocation,
+    BulkUpdateAssetSerialNumber,
+    BulkUpdateAssetTag,
+    BulkUpdatePowerDesignValue,
+    BulkUpdatePatchPanelPorts,
+    BulkUpdateAssetPorts,
+    BulkUpdateCustomAssetProperty,
+    BulkAddRackAccessory,
+    BulkUpdateAssetSensor,
+    BulkAddPowerAssociation,
+    BulkImportBacnetNumericSensorDefinitions,
+    BulkImportBacnetNonNumericSensorDefinitions,
+    BulkImportModbusNumericSensorDefinitions,
+    BulkImportModbusNonNumericSensorDefinitions,
+}
+
+impl CsvTemplate {
+    /// The example file in `example_input`, header and sample rows.
+    pub fn example(self) -> &'static str {
+        match self {
+            Self::BulkUpdateAssetName => {
+                include_str!("../../example_input/update_asset_name.csv")
+            }
+            Self::BulkUpdateAssetLocation => {
+                include_str!("../../example_input/update_asset_location.csv")
+            }
+            Self::BulkUpdateAssetSerialNumber => {
+                include_str!("../../example_input/update_serial_number.csv")
+            }
+            Self::BulkUpdateAssetTag => include_str!("../../example_input/update_asset_tag.csv"),
+            Self::BulkUpdatePowerDesignValue => {
+                include_str!("../../example_input/update_power_design_value.csv")
+            }
+            Self::BulkUpdatePatchPanelPorts => {
+                include_str!("../../example_input/update_patch_panel_ports.csv")
+            }
+            Self::BulkUpdateAssetPorts => {
+                include_str!("../../example_input/update_asset_ports.csv")
+            }
+            Self::BulkUpdateCustomAssetProperty => {
+                include_str!("../../example_input/update_custom_asset_property.csv")
+            }
+            Self::BulkAddRackAccessory => {
+                include_str!("../../example_input/add_rack_accessory.csv")
+            }
+            Self::BulkUpdateAssetSensor => {
+                include_str!("../../example_input/update_asset_sensor.csv")
+            }
+            Self::BulkAddPowerAssociation => {
+                include_str!("../../example_input/update_power_association.csv")
+            }
+            Self::BulkImportBacnetNumericSensorDefinitions => {
+                include_str!("../../example_input/bacnet_numeric_sensor_definitions.csv")
+            }
+            Self::BulkImportBacnetNonNumericSensorDefinitions => {
+                include_str!("../../example_input/bacnet_non_numeric_sensor_definitions.csv")
+            }
+            Self::BulkImportModbusNumericSensorDefinitions => {
+                include_str!("../../example_input/modbus_numeric_sensor_definitions.csv")
+            }
+            Self::BulkImportModbusNonNumericSensorDefinitions => {
+                include_str!("../../example_input/modbus_non_numeric_sensor_definitions.csv")
+            }
+        }
+    }
+
+    /// The header line the command expects, without a line break.
+    pub fn header(self) -> &'static str {
+        self.example().lines().next().unwrap_or_default().trim_end()
+    }
+}
+
+/// The kinds of sensor definition CSV the `bulk-import-*-sensor-definitions` commands read.
+#[derive(Debug, ValueEnum, Clone, Copy, PartialEq, Eq)]
+pub enum SensorDefinitionCsv {
+    BacnetNumeric,
+    BacnetNonNumeric,
+    ModbusNumeric,
+    ModbusNonNumeric,
+}
+
+/// A row of an input CSV that the matching bulk command would reject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvRowProblem {
+    /// Line of the file the row starts on, counting the header as line 1.
+    pub line: u64,
+    pub message: String,
+}
+
+impl fmt::Display for CsvRowProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+/// Parses every row of a sensor definition CSV the way the import command does, without calling
+/// the API, and returns the rows it would reject. A file that cannot be read at all is an error.
+pub fn validate_sensor_definition_csv(
+    path: &Path,
+    kind: SensorDefinitionCsv,
+) -> color_eyre::Result<Vec<CsvRowProblem>> {
+    let mut reader = csv::Reader::from_path(path)?;
+
+    match kind {
+        SensorDefinitionCsv::BacnetNumeric => {
+            check_rows::<BacnetNumericSensorDefinitionImportDto>(&mut reader, |_| Ok(()))
+        }
+        SensorDefinitionCsv::BacnetNonNumeric => {
+            check_rows::<BacnetNonNumericSensorDefinitionImportCsv>(&mut reader, |row| {
+                BacnetNonNumericSensorDefinitionImportDto::try_from(row).map(drop)
+            })
+        }
+        SensorDefinitionCsv::ModbusNumeric => {
+            check_rows::<ModbusNumericSensorDefinitionImportDto>(&mut reader, |_| Ok(()))
+        }
+        SensorDefinitionCsv::ModbusNonNumeric => {
+            check_rows::<ModbusNonNumericSensorDefinitionImportCsv>(&mut reader, |row| {
+                ModbusNonNumericSensorDefinitionImportDto::try_from(row).map(drop)
+            })
+        }
+    }
+}
+
+/// Deserializes each row as `R` and runs `check` on it, collecting the rows that fail either.
+fn check_rows<R: DeserializeOwned>(
+    reader: &mut csv::Reader<std::fs::File>,
+    check: impl Fn(&R) -> color_eyre::Result<()>,
+) -> color_eyre::Result<Vec<CsvRowProblem>> {
+    let headers = reader.headers()?.clone();
+    let mut problems = Vec::new();
+    let mut record = StringRecord::new();
+
+    loop {
+        let line = reader.position().line();
+        match reader.read_record(&mut record) {
+            Ok(false) => break,
+            Ok(true) => {
+                let result = record
+                    .deserialize::<R>(Some(&headers))
+                    .map_err(color_eyre::Report::from)
+                    .and_then(|row| check(&row));
+                if let Err(e) = result {
+                    problems.push(CsvRowProblem {
+                        line,
+                        message: e.to_string(),
+                    });
+                }
+            }
+            Err(e) => problems.push(CsvRowProblem {
+                line,
+                message: e.to_string(),
+            }),
+        }
+    }
+
+    Ok(problems)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn test_templates_are_the_example_headers() {
+        assert_eq!(
+            CsvTemplate::BulkUpdateAssetName.header(),
+            "asset_id,new_name"
+        );
+        assert_eq!(
+            CsvTemplate::BulkAddRackAccessory.header(),
+            "id,panel_type,side,u_location"
+        );
+
+        for template in CsvTemplate::value_variants() {
+            let header = template.header();
+            assert!(!header.is_empty(), "{template:?}");
+            assert!(!header.contains('\r'), "{template:?}");
+        }
+    }
+
+    #[test]
+    fn test_sensor_definition_examples_are_valid() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("example_input");
+        let cases = [
+            (
+                "bacnet_numeric_sensor_definitions.csv",
+                SensorDefinitionCsv::BacnetNumeric,
+            ),
+            (
+                "bacnet_non_numeric_sensor_definitions.csv",
+                SensorDefinitionCsv::BacnetNonNumeric,
+            ),
+            (
+                "modbus_numeric_sensor_definitions.csv",
+                SensorDefinitionCsv::ModbusNumeric,
+            ),
+            (
+                "modbus_non_numeric_sensor_definitions.csv",
+                SensorDefinitionCsv::ModbusNonNumeric,
+            ),
+        ];
+
+        for (file, kind) in cases {
+            let problems = validate_sensor_definition_csv(&root.join(file), kind).unwrap();
+            assert_eq!(problems, [], "{file}");
+        }
+    }
+
+    #[test]
+    fn test_validate_reports_bad_rows_by_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("sensors.csv");
+        let header = CsvTemplate::BulkImportBacnetNonNumericSensorDefinitions.header();
+        fs::write(
+            &path,
+            format!(
+                "{header}\n\
+                 ,Filter 1,0,analogInput,cloggedFilter,type-1,\"Inactive:0,Active:1\"\n\
+                 ,Filter 2,not-a-number,analogInput,cloggedFilter,type-1,\"Inactive:0\"\n\
+                 ,Filter 3,2,analogInput,cloggedFilter,type-1,\"Inactive\"\n"
+            ),
+        )
+        .unwrap();
+
+        let problems =
+            validate_sensor_definition_csv(&path, SensorDefinitionCsv::BacnetNonNumeric).unwrap();
+
+        let lines: Vec<u64> = problems.iter().map(|p| p.line).collect();
+        assert_eq!(lines, [3, 4], "{problems:?}");
+    }
+}
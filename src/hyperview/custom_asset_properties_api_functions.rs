@@ -1,15 +1,12 @@
-use log::{debug, error, trace};
-use reqwest::{Client, header::AUTHORIZATION};
+use log::{debug, trace};
 use serde_json::Value;
 use uuid::Uuid;
 
-use crate::retry_on_unauthorized_async;
-
 use super::{
+    api_client::HyperviewClient,
     api_constants::CUSTOM_ASSET_PROPERTIES_API_PREFIX,
     app_errors::AppError,
-    auth::AuthToken,
-    cli_data::AppConfig,
+    bulk::run_rows_async,
     custom_asset_properties_api_data::CustomAssetPropertyDto,
     custom_asset_properties_api_data::{
         CustomAssetPropertyFileImportDto, CustomAssetPropertyUpdateDto,
@@ -17,39 +14,21 @@ use super::{
 };
 
 pub async fn get_custom_asset_property_list_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     asset_id: Uuid,
 ) -> color_eyre::Result<Vec<CustomAssetPropertyDto>> {
-    let target_url = format!(
-        "{}{}/{}",
-        config.instance_url, CUSTOM_ASSET_PROPERTIES_API_PREFIX, asset_id
-    );
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<CustomAssetPropertyDto>>()
-        .await?;
-
-    Ok(resp)
+    client
+        .get(&format!("{CUSTOM_ASSET_PROPERTIES_API_PREFIX}/{asset_id}"))
+        .await
 }
 
 pub async fn update_custom_property_by_name_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     asset_id: Uuid,
     custom_asset_property_name: String,
     new_custom_property_value: String,
 ) -> color_eyre::Result<()> {
-    let custom_asset_property_list =
-        get_custom_asset_property_list_async(config, req, auth_header, asset_id).await?;
+    let custom_asset_property_list = get_custom_asset_property_list_async(client, asset_id).await?;
 
     let custom_asset_property = custom_asset_property_list
         .into_iter()
@@ -65,14 +44,6 @@ pub async fn update_custom_property_by_name_async(
         .expect("Asset should have a custom property that can be updated");
     debug!("Custom property to update: {custom_property:#?}");
 
-    let target_url = format!(
-        "{}{}/{}",
-        config.instance_url,
-        CUSTOM_ASSET_PROPERTIES_API_PREFIX,
-        custom_property.id.clone()
-    );
-    debug!("Request URL: {target_url}");
-
     let update_dto = CustomAssetPropertyUpdateDto {
         custom_asset_property_key_id: custom_property.custom_asset_property_key_id.clone(),
         data_type: custom_property.data_type.clone(),
@@ -85,13 +56,15 @@ pub async fn update_custom_property_by_name_async(
         serde_json::to_string_pretty(&update_dto).expect("Could not serialize update_dto to JSON")
     );
 
-    let resp = req
-        .put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&update_dto)
-        .send()
+    let resp = client
+        .put(
+            &format!(
+                "{CUSTOM_ASSET_PROPERTIES_API_PREFIX}/{}",
+                custom_property.id
+            ),
+            &update_dto,
+        )
         .await?
-        .error_for_status()?
         .json::<Value>()
         .await?;
 
@@ -105,38 +78,38 @@ pub async fn update_custom_property_by_name_async(
 }
 
 pub async fn bulk_update_custom_property_by_name_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: String,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
-    let mut reader = csv::Reader::from_path(filename)?;
-    while let Some(Ok(record)) = reader
-        .deserialize::<CustomAssetPropertyFileImportDto>()
-        .next()
-    {
-        auth_token.refresh_if_needed_async(config).await?;
-
-        debug!("Update custom asset property record: {record:?}");
-        if let Err(e) = retry_on_unauthorized_async!(
-            config,
-            auth_token,
+    let records: Vec<CustomAssetPropertyFileImportDto> = csv::Reader::from_path(filename)?
+        .deserialize()
+        .map_while(Result::ok)
+        .collect();
+
+    run_rows_async(
+        records,
+        concurrency,
+        |record| Some(record.asset_id),
+        |record| async move {
+            debug!("Update custom asset property record: {record:?}");
             update_custom_property_by_name_async(
-                config,
-                req,
-                &auth_token.header,
+                client,
                 record.asset_id,
                 record.custom_asset_property_name.clone(),
-                record.new_custom_property_value.clone(),
+                record.new_custom_property_value,
             )
             .await
-        ) {
-            error!(
-                "Failed to update custom property '{}' for asset id {}: {e}",
-                record.custom_asset_property_name, record.asset_id
-            );
-        }
-    }
+            .map_err(|e| {
+                format!(
+                    "Failed to update custom property '{}' for asset id {}: {e}",
+                    record.custom_asset_property_name, record.asset_id
+                )
+            })
+        },
+    )
+    .await;
+
     Ok(())
 }
 
@@ -145,6 +118,7 @@ mod tests {
     use std::str::FromStr;
 
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
 
@@ -197,12 +171,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = reqwest::Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
         // Act
-        let result =
-            get_custom_asset_property_list_async(&config, &client, &auth_header, asset_id).await;
+        let result = get_custom_asset_property_list_async(&client, asset_id).await;
 
         // Assert
         m.assert();
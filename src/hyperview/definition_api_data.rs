@@ -1,3 +1,4 @@
+use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
 use serde_with::{DefaultOnError, serde_as};
 use std::fmt;
@@ -117,6 +118,12 @@ pub fn format_value_mapping(mappings: &[ValueMapping]) -> String {
         .join(",")
 }
 
+#[derive(Debug, ValueEnum, Clone, Copy)]
+pub enum SensorValueClass {
+    Numeric,
+    Enum,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -1,46 +1,27 @@
-use log::debug;
-use reqwest::{Client, header::AUTHORIZATION};
 use uuid::Uuid;
 
 use super::{
+    api_client::HyperviewClient,
     api_constants::SENSOR_DEFINITION_TYPE_API_PREFIX,
-    cli_data::{AppConfig, AssetTypes, SensorValueClass, UpdateDefinitionArgs},
+    asset_api_data::AssetTypes,
+    definition_api_data::SensorValueClass,
     definition_api_data::{Definition, SensorType},
 };
 
 pub async fn list_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     api_prefix: &str,
 ) -> color_eyre::Result<Vec<Definition>> {
-    let target_url = format!("{}{}", config.instance_url, api_prefix);
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<Definition>>()
-        .await?;
-
-    Ok(resp)
+    client.get(api_prefix).await
 }
 
 pub async fn add_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     api_prefix: &str,
     name: String,
     asset_type: AssetTypes,
     description: Option<String>,
 ) -> color_eyre::Result<Uuid> {
-    let target_url = format!("{}{}", config.instance_url, api_prefix);
-    debug!("Request URL: {target_url}");
-
     let definition = Definition {
         id: None,
         name,
@@ -49,13 +30,9 @@ pub async fn add_definition_async(
         associated_assets: 0,
     };
 
-    let id = req
-        .post(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&definition)
-        .send()
+    let id = client
+        .post(api_prefix, &definition)
         .await?
-        .error_for_status()?
         .json::<Uuid>()
         .await?;
 
@@ -63,18 +40,10 @@ pub async fn add_definition_async(
 }
 
 pub async fn list_sensor_definition_types_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     asset_type: AssetTypes,
     sensor_class: SensorValueClass,
 ) -> color_eyre::Result<Vec<SensorType>> {
-    let target_url = format!(
-        "{}{}",
-        config.instance_url, SENSOR_DEFINITION_TYPE_API_PREFIX
-    );
-    debug!("Request URL: {target_url}");
-
     let sensor_type_value_type = match sensor_class {
         SensorValueClass::Numeric => "numeric",
         SensorValueClass::Enum => "enum",
@@ -85,87 +54,50 @@ pub async fn list_sensor_definition_types_async(
         ("sensorTypeValueType", sensor_type_value_type.to_string()),
     ];
 
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .query(&query)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<SensorType>>()
-        .await?;
-
-    Ok(resp)
+    client
+        .get_with_query(SENSOR_DEFINITION_TYPE_API_PREFIX, &query)
+        .await
 }
 
 pub async fn get_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     api_prefix: &str,
     definition_id: Uuid,
 ) -> color_eyre::Result<Definition> {
-    let target_url = format!("{}{}/{}", config.instance_url, api_prefix, definition_id);
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Definition>()
-        .await?;
-
-    Ok(resp)
+    client.get(&format!("{api_prefix}/{definition_id}")).await
 }
 
 pub async fn update_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     api_prefix: &str,
-    options: &UpdateDefinitionArgs,
+    definition_id: Uuid,
+    name: String,
+    asset_type: AssetTypes,
+    description: Option<String>,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/{}",
-        config.instance_url, api_prefix, options.definition_id
-    );
-    debug!("Request URL: {target_url}");
-
     let definition = Definition {
-        id: Some(options.definition_id),
-        name: options.name.clone(),
-        asset_type: options.asset_type.to_string(),
-        description: options.description.clone(),
+        id: Some(definition_id),
+        name,
+        asset_type: asset_type.to_string(),
+        description,
         associated_assets: 0,
     };
 
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&definition)
-        .send()
-        .await?
-        .error_for_status()?;
+    client
+        .put(&format!("{api_prefix}/{definition_id}"), &definition)
+        .await?;
 
     Ok(())
 }
 
 pub async fn delete_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     api_prefix: &str,
     definition_id: Uuid,
 ) -> color_eyre::Result<()> {
-    let target_url = format!("{}{}/{}", config.instance_url, api_prefix, definition_id);
-    debug!("Request URL: {target_url}");
-
-    req.delete(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?;
+    client
+        .delete(&format!("{api_prefix}/{definition_id}"))
+        .await?;
 
     Ok(())
 }
@@ -173,25 +105,17 @@ pub async fn delete_definition_async(
 /// Deletes a single sensor from a definition. `sub_resource` is the protocol/class path segment,
 /// e.g. `"bacnetIpNumericSensors"` or `"modbusTcpNonNumericSensors"`.
 pub async fn delete_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     api_prefix: &str,
     sub_resource: &str,
     definition_id: Uuid,
     sensor_id: Uuid,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/{}/{}/{}",
-        config.instance_url, api_prefix, sub_resource, definition_id, sensor_id
-    );
-    debug!("Request URL: {target_url}");
-
-    req.delete(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?;
+    client
+        .delete(&format!(
+            "{api_prefix}/{sub_resource}/{definition_id}/{sensor_id}"
+        ))
+        .await?;
 
     Ok(())
 }
@@ -199,6 +123,7 @@ pub async fn delete_sensor_definition_async(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
     use uuid::Uuid;
@@ -223,17 +148,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = list_definitions_async(
-            &config,
-            &client,
-            &auth_header,
-            "/api/setting/bacnetIpDefinitions",
-        )
-        .await
-        .unwrap();
+        let resp = list_definitions_async(&client, "/api/setting/bacnetIpDefinitions")
+            .await
+            .unwrap();
 
         m.assert();
         assert_eq!(resp.len(), 1);
@@ -262,17 +181,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = list_definitions_async(
-            &config,
-            &client,
-            &auth_header,
-            "/api/setting/bacnetIpDefinitions",
-        )
-        .await
-        .unwrap();
+        let resp = list_definitions_async(&client, "/api/setting/bacnetIpDefinitions")
+            .await
+            .unwrap();
 
         m.assert();
         assert_eq!(resp.len(), 2);
@@ -302,13 +215,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
         let id = add_definition_async(
-            &config,
             &client,
-            &auth_header,
             "/api/setting/modbusTcpDefinitions",
             "New Definition".to_string(),
             AssetTypes::Ups,
@@ -344,13 +254,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
         let id = add_definition_async(
-            &config,
             &client,
-            &auth_header,
             "/api/setting/bacnetIpDefinitions",
             "Described".to_string(),
             AssetTypes::Crah,
@@ -385,13 +292,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
         let resp = list_sensor_definition_types_async(
-            &config,
             &client,
-            &auth_header,
             AssetTypes::Crah,
             SensorValueClass::Numeric,
         )
@@ -421,18 +325,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = list_sensor_definition_types_async(
-            &config,
-            &client,
-            &auth_header,
-            AssetTypes::Rack,
-            SensorValueClass::Enum,
-        )
-        .await
-        .unwrap();
+        let resp =
+            list_sensor_definition_types_async(&client, AssetTypes::Rack, SensorValueClass::Enum)
+                .await
+                .unwrap();
 
         m.assert();
         assert!(resp.is_empty());
@@ -450,18 +348,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let result = list_definitions_async(
-            &config,
-            &client,
-            &auth_header,
-            "/api/setting/bacnetIpDefinitions",
-        )
-        .await;
+        let result = list_definitions_async(&client, "/api/setting/bacnetIpDefinitions").await;
 
-        m.assert();
+        m.assert_calls(4);
         assert!(result.is_err());
     }
 
@@ -488,18 +379,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = get_definition_async(
-            &config,
-            &client,
-            &auth_header,
-            "/api/setting/modbusTcpDefinitions",
-            definition_id,
-        )
-        .await
-        .unwrap();
+        let resp =
+            get_definition_async(&client, "/api/setting/modbusTcpDefinitions", definition_id)
+                .await
+                .unwrap();
 
         m.assert();
         assert_eq!(resp.id, Some(definition_id));
@@ -528,22 +413,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
-
-        let options = UpdateDefinitionArgs {
-            definition_id,
-            name: "Renamed".to_string(),
-            asset_type: AssetTypes::Crah,
-            description: Some("d".to_string()),
-        };
+        let client = HyperviewClient::for_test(config);
 
         update_definition_async(
-            &config,
             &client,
-            &auth_header,
             "/api/setting/bacnetIpDefinitions",
-            &options,
+            definition_id,
+            "Renamed".to_string(),
+            AssetTypes::Crah,
+            Some("d".to_string()),
         )
         .await
         .unwrap();
@@ -566,18 +444,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        delete_definition_async(
-            &config,
-            &client,
-            &auth_header,
-            "/api/setting/modbusTcpDefinitions",
-            definition_id,
-        )
-        .await
-        .unwrap();
+        delete_definition_async(&client, "/api/setting/modbusTcpDefinitions", definition_id)
+            .await
+            .unwrap();
 
         m.assert();
     }
@@ -600,13 +471,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
         delete_sensor_definition_async(
-            &config,
             &client,
-            &auth_header,
             "/api/setting/bacnetIpDefinitions",
             "bacnetIpNumericSensors",
             definition_id,
@@ -636,13 +504,10 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
         let result = delete_sensor_definition_async(
-            &config,
             &client,
-            &auth_header,
             "/api/setting/modbusTcpDefinitions",
             "modbusTcpNonNumericSensors",
             definition_id,
@@ -0,0 +1,510 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use super::{
+    app_errors::AppError,
+    search_filter::{quote_field, quote_value},
+};
+
+/// A search condition given with `--filter`, checked when it is parsed and translated into the
+/// search API's own filter syntax.
+///
+/// - Conditions are `field = value`, `!=`, `<`, `<=`, `>`, `>=`, `field CONTAINS value`,
+///   `field STARTS WITH value` and `field EXISTS`.
+/// - They combine with `AND`, `OR` and `NOT`, in that order of precedence, and parentheses.
+///   `field NOT EXISTS` and `field NOT CONTAINS value` are also accepted.
+/// - Values are numbers, words, or text in single or double quotes, with `\` escaping a quote or
+///   backslash. `<`, `<=`, `>` and `>=` need a number.
+/// - Field names are the ones in the JSON output, such as `name` or `path`, `property.<type>`
+///   for an asset property, `custom.<name>` for a custom property, or a search field name such
+///   as `assetProperty_serialNumber`. Names with spaces can be quoted.
+/// - Keywords are not case sensitive.
+///
+/// Values of `path` use `/` between locations, like `--location-path`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilterExpression {
+    root: Expr,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Or(Vec<Expr>),
+    And(Vec<Expr>),
+    Not(Box<Expr>),
+    Exists(String),
+    Compare {
+        field: String,
+        operator: Operator,
+        value: Literal,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operator {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    Contains,
+    StartsWith,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Literal {
+    Number(String),
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    Symbol(Operator),
+    Word(String),
+    Quoted(String),
+}
+
+const KEYWORDS: [&str; 7] = ["AND", "OR", "NOT", "EXISTS", "CONTAINS", "STARTS", "WITH"];
+
+impl FilterExpression {
+    pub fn parse(expression: &str) -> Result<Self, AppError> {
+        let invalid = |reason: String| AppError::InvalidFilter {
+            expression: expression.to_string(),
+            reason,
+        };
+
+        let tokens = tokenize(expression).map_err(invalid)?;
+        if tokens.is_empty() {
+            return Err(invalid("the expression is empty".to_string()));
+        }
+        let mut parser = Parser { tokens, next: 0 };
+        let root = parser.or().map_err(invalid)?;
+        if let Some(token) = parser.peek() {
+            return Err(invalid(format!("unexpected {}", describe(token))));
+        }
+
+        Ok(Self { root })
+    }
+
+    /// The expression in the search API's filter syntax.
+    pub fn search_syntax(&self) -> String {
+        let mut out = String::new();
+        write_expr(&self.root, &mut out);
+        out
+    }
+
+    /// Whether the expression is more than one condition, and so needs parentheses when joined
+    /// to others.
+    pub fn is_compound(&self) -> bool {
+        matches!(self.root, Expr::Or(_) | Expr::And(_))
+    }
+}
+
+impl FromStr for FilterExpression {
+    type Err = AppError;
+
+    fn from_str(expression: &str) -> Result<Self, Self::Err> {
+        Self::parse(expression)
+    }
+}
+
+impl Display for FilterExpression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.search_syntax())
+    }
+}
+
+fn tokenize(expression: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = expression.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '=' | '!' | '<' | '>' => {
+                chars.next();
+                let or_equal = chars.next_if_eq(&'=').is_some();
+                let operator = match (c, or_equal) {
+                    ('=', false) => Operator::Eq,
+                    ('!', true) => Operator::Ne,
+                    ('<', false) => Operator::Lt,
+                    ('<', true) => Operator::Le,
+                    ('>', false) => Operator::Gt,
+                    ('>', true) => Operator::Ge,
+                    _ => return Err(format!("'{c}' is not an operator; use '!=' or NOT")),
+                };
+                tokens.push(Token::Symbol(operator));
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut text = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\\') => match chars.next() {
+                            Some(escaped) => text.push(escaped),
+                            None => return Err(format!("{c}{text}\\ is not closed with {c}")),
+                        },
+                        Some(q) if q == c => break,
+                        Some(other) => text.push(other),
+                        None => return Err(format!("{c}{text} is not closed with {c}")),
+                    }
+                }
+                tokens.push(Token::Quoted(text));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(c) = chars.next_if(|&c| {
+                    !c.is_whitespace()
+                        && !matches!(c, '(' | ')' | '=' | '!' | '<' | '>' | '\'' | '"')
+                }) {
+                    word.push(c);
+                }
+                tokens.push(Token::Word(word));
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Open => "'('".to_string(),
+        Token::Close => "')'".to_string(),
+        Token::Symbol(operator) => format!("'{}'", operator.symbol()),
+        Token::Word(word) => format!("'{word}'"),
+        Token::Quoted(text) => format!("'{}'", text.replace('\'', "\\'")),
+    }
+}
+
+fn is_keyword(word: &str) -> bool {
+    KEYWORDS.iter().any(|k| k.eq_ignore_ascii_case(word))
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn advance(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    /// Consumes the next token if it is `keyword`.
+    fn keyword(&mut self, keyword: &str) -> bool {
+        let found = matches!(self.peek(), Some(Token::Word(w)) if w.eq_ignore_ascii_case(keyword));
+        if found {
+            self.next += 1;
+        }
+        found
+    }
+
+    fn or(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.and()?];
+        while self.keyword("OR") {
+            terms.push(self.and()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            Expr::Or(terms)
+        })
+    }
+
+    fn and(&mut self) -> Result<Expr, String> {
+        let mut terms = vec![self.not()?];
+        while self.keyword("AND") {
+            terms.push(self.not()?);
+        }
+        Ok(if terms.len() == 1 {
+            terms.remove(0)
+        } else {
+            Expr::And(terms)
+        })
+    }
+
+    fn not(&mut self) -> Result<Expr, String> {
+        if self.keyword("NOT") {
+            return Ok(Expr::Not(Box::new(self.not()?)));
+        }
+        self.condition()
+    }
+
+    fn condition(&mut self) -> Result<Expr, String> {
+        let field = match self.advance() {
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                return match self.advance() {
+                    Some(Token::Close) => Ok(expr),
+                    Some(token) => Err(format!("expected ')' but found {}", describe(&token))),
+                    None => Err("'(' is never closed with ')'".to_string()),
+                };
+            }
+            Some(Token::Word(word)) if !is_keyword(&word) => word,
+            Some(Token::Quoted(name)) if !name.is_empty() => name,
+            Some(token) => {
+                return Err(format!(
+                    "expected a field name but found {}",
+                    describe(&token)
+                ));
+            }
+            None => return Err("the expression ends where a condition was expected".to_string()),
+        };
+
+        let negated = self.keyword("NOT");
+        let condition = if self.keyword("EXISTS") {
+            Expr::Exists(field)
+        } else {
+            let operator = if self.keyword("CONTAINS") {
+                Operator::Contains
+            } else if self.keyword("STARTS") {
+                if !self.keyword("WITH") {
+                    return Err(format!("expected WITH after '{field} STARTS'"));
+                }
+                Operator::StartsWith
+            } else if !negated && let Some(Token::Symbol(operator)) = self.peek() {
+                let operator = *operator;
+                self.next += 1;
+                operator
+            } else {
+                return Err(match self.peek() {
+                    Some(token) => format!(
+                        "expected an operator after '{field}' but found {}",
+                        describe(token)
+                    ),
+                    None => format!("expected an operator after '{field}'"),
+                });
+            };
+            let value = self.value(&field, operator)?;
+            Expr::Compare {
+                field,
+                operator,
+                value,
+            }
+        };
+
+        Ok(if negated {
+            Expr::Not(Box::new(condition))
+        } else {
+            condition
+        })
+    }
+
+    fn value(&mut self, field: &str, operator: Operator) -> Result<Literal, String> {
+        let value = match self.advance() {
+            Some(Token::Quoted(text)) => Literal::Text(text),
+            Some(Token::Word(word)) if !is_keyword(&word) => {
+                if word.parse::<f64>().is_ok_and(f64::is_finite) {
+                    Literal::Number(word)
+                } else {
+                    Literal::Text(word)
+                }
+            }
+            Some(token) => {
+                return Err(format!(
+                    "expected a value after '{field} {}' but found {}",
+                    operator.symbol(),
+                    describe(&token)
+                ));
+            }
+            None => {
+                return Err(format!(
+                    "expected a value after '{field} {}'",
+                    operator.symbol()
+                ));
+            }
+        };
+
+        if operator.is_ordering() && !matches!(value, Literal::Number(_)) {
+            return Err(format!("'{field} {}' needs a number", operator.symbol()));
+        }
+
+        Ok(value)
+    }
+}
+
+impl Operator {
+    fn symbol(self) -> &'static str {
+        match self {
+            Self::Eq => "=",
+            Self::Ne => "!=",
+            Self::Lt => "<",
+            Self::Le => "<=",
+            Self::Gt => ">",
+            Self::Ge => ">=",
+            Self::Contains => "CONTAINS",
+            Self::StartsWith => "STARTS WITH",
+        }
+    }
+
+    fn is_ordering(self) -> bool {
+        matches!(self, Self::Lt | Self::Le | Self::Gt | Self::Ge)
+    }
+}
+
+/// The search field behind a field name in the JSON output, or `property.<type>` and
+/// `custom.<name>`. Other names are taken to be search fields already.
+pub fn search_field(name: &str) -> String {
+    if let Some(property_type) = name.strip_prefix("property.") {
+        return format!("assetProperty_{property_type}");
+    }
+    if let Some(custom) = name.strip_prefix("custom.") {
+        return format!("customProperty_{custom}");
+    }
+
+    match name {
+        "name" => "displayName",
+        "assetTypeId" => "assetType",
+        "parentName" => "parentDisplayName",
+        "path" => "delimitedPath",
+        "serialNumber" => "assetProperty_serialNumber",
+        other => other,
+    }
+    .to_string()
+}
+
+fn write_expr(expr: &Expr, out: &mut String) {
+    let write_term = |term: &Expr, out: &mut String| {
+        if matches!(term, Expr::Or(_) | Expr::And(_)) {
+            out.push('(');
+            write_expr(term, out);
+            out.push(')');
+        } else {
+            write_expr(term, out);
+        }
+    };
+
+    match expr {
+        Expr::Or(terms) | Expr::And(terms) => {
+            let joiner = if matches!(expr, Expr::Or(_)) {
+                " OR "
+            } else {
+                " AND "
+            };
+            for (i, term) in terms.iter().enumerate() {
+                if i > 0 {
+                    out.push_str(joiner);
+                }
+                write_term(term, out);
+            }
+        }
+        Expr::Not(term) => {
+            out.push_str("NOT ");
+            write_term(term, out);
+        }
+        Expr::Exists(field) => {
+            out.push_str(&quote_field(&search_field(field)));
+            out.push_str(" EXISTS");
+        }
+        Expr::Compare {
+            field,
+            operator,
+            value,
+        } => {
+            let search_field = search_field(field);
+            out.push_str(&quote_field(&search_field));
+            out.push(' ');
+            out.push_str(operator.symbol());
+            out.push(' ');
+            match value {
+                Literal::Number(number) => out.push_str(number),
+                Literal::Text(text) if search_field == "delimitedPath" => {
+                    out.push_str(&quote_value(&text.replace('/', "~")))
+                }
+                Literal::Text(text) => out.push_str(&quote_value(text)),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn translate(expression: &str) -> String {
+        FilterExpression::parse(expression).unwrap().search_syntax()
+    }
+
+    #[test]
+    fn test_translates_conditions_and_precedence() {
+        assert_eq!(
+            translate(
+                "(path CONTAINS 'Building A' OR path contains \"Building B\") \
+                 and property.designValue > 5000 AND NOT assetLifecycleState = retired"
+            ),
+            "(delimitedPath CONTAINS 'Building A' OR delimitedPath CONTAINS 'Building B') \
+             AND assetProperty_designValue > 5000 AND NOT assetLifecycleState = 'retired'"
+        );
+        assert_eq!(
+            translate("a = 1 OR b != 2 AND c <= -1.5 OR d >= 0"),
+            "a = 1 OR (b != 2 AND c <= -1.5) OR d >= 0"
+        );
+        assert_eq!(
+            translate("custom.Owner EXISTS AND serialNumber NOT EXISTS"),
+            "customProperty_Owner EXISTS AND NOT assetProperty_serialNumber EXISTS"
+        );
+        assert_eq!(
+            translate("NOT (name STARTS WITH rack OR path = 'All/DC 1')"),
+            "NOT (displayName STARTS WITH 'rack' OR delimitedPath = 'All~DC 1')"
+        );
+    }
+
+    #[test]
+    fn test_escapes_values_and_field_names() {
+        assert_eq!(
+            translate(r#"'custom.Cost Center' = 'O\'Brien' OR name CONTAINS "x' OR id EXISTS""#),
+            r"'customProperty_Cost Center' = 'O\'Brien' OR displayName CONTAINS 'x\' OR id EXISTS'"
+        );
+        assert_eq!(translate(r"name = 'a\\b'"), r"displayName = 'a\\b'");
+        assert_eq!(translate("name = '5000'"), "displayName = '5000'");
+    }
+
+    #[test]
+    fn test_rejects_malformed_expressions() {
+        for expression in [
+            "",
+            "name",
+            "name =",
+            "= rack",
+            "(name = a",
+            "name = a)",
+            "name = a OR",
+            "name = 'unclosed",
+            "name ! a",
+            "name < big",
+            "name STARTS rack",
+            "name NOT = a",
+            "name = AND",
+            "AND = a",
+            "name = a b = c",
+        ] {
+            let err = FilterExpression::parse(expression).unwrap_err();
+            assert!(
+                matches!(err, AppError::InvalidFilter { .. }),
+                "{expression}: {err}"
+            );
+        }
+
+        assert_eq!(
+            FilterExpression::parse("designValue > high")
+                .unwrap_err()
+                .to_string(),
+            "Invalid --filter \"designValue > high\": 'designValue >' needs a number"
+        );
+    }
+}
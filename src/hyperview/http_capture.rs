@@ -0,0 +1,352 @@
+use chrono::{DateTime, Utc};
+use log::warn;
+use reqwest::{
+    Request, Response, ResponseBuilderExt,
+    header::{CONTENT_TYPE, HeaderMap},
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use std::time::Instant;
+
+use super::{app_errors::AppError, config_functions::create_private_file};
+
+/// Stands in for every redacted value.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Headers that carry credentials.
+const SECRET_HEADERS: [&str; 4] = [
+    "authorization",
+    "proxy-authorization",
+    "cookie",
+    "set-cookie",
+];
+
+/// JSON keys and form fields that carry credentials: the token endpoint's responses and the
+/// secrets, refresh tokens and PKCE verifiers sent to it.
+const SECRET_FIELDS: [&str; 6] = [
+    "access_token",
+    "refresh_token",
+    "id_token",
+    "client_secret",
+    "code_verifier",
+    "device_code",
+];
+
+static ACTIVE: OnceLock<HttpCapture> = OnceLock::new();
+
+/// Starts capturing to `path` for the rest of the run. Every API and token request sent after
+/// this is recorded.
+pub fn install(path: &Path) -> color_eyre::Result<()> {
+    let capture = HttpCapture::create(path)?;
+    if ACTIVE.set(capture).is_err() {
+        warn!(
+            "HTTP capture is already running; ignoring {}",
+            path.display()
+        );
+    }
+
+    Ok(())
+}
+
+/// The capture started by [`install`], if any.
+pub fn active() -> Option<&'static HttpCapture> {
+    ACTIVE.get()
+}
+
+/// One request and its response, as written to a capture file. Bodies are kept as JSON when they
+/// parse as JSON and as text otherwise.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CapturedExchange {
+    pub started_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    pub method: String,
+    pub url: String,
+    #[serde(default)]
+    pub request_headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_body: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    #[serde(default)]
+    pub response_headers: BTreeMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_body: Option<Value>,
+    /// Why no response was received, for requests that failed to connect or timed out.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Records HTTP exchanges to a file, one JSON object per line, with credentials redacted. The
+/// file is readable by its owner only, as it still holds asset data.
+pub struct HttpCapture {
+    file: Mutex<File>,
+}
+
+impl HttpCapture {
+    pub fn create(path: &Path) -> Result<Self, AppError> {
+        let file = create_private_file(path).map_err(|e| AppError::CaptureFileUnwritable {
+            path: path.display().to_string(),
+            reason: e.to_string(),
+        })?;
+
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Sends `request` with `http` and records it. The response body is read in full to record
+    /// it; the returned response can still be read as usual.
+    pub async fn execute_async(
+        &self,
+        http: &reqwest::Client,
+        request: Request,
+    ) -> reqwest::Result<Response> {
+        let started_at = Utc::now();
+        let start = Instant::now();
+        let mut exchange = CapturedExchange {
+            started_at,
+            duration_ms: 0,
+            method: request.method().to_string(),
+            url: request.url().to_string(),
+            request_headers: capture_headers(request.headers()),
+            request_body: request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .and_then(|bytes| capture_body(bytes, request.headers())),
+            status: None,
+            response_headers: BTreeMap::new(),
+            response_body: None,
+            error: None,
+        };
+
+        let result = read_response_async(http, request).await;
+        exchange.duration_ms = u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX);
+
+        match &result {
+            Ok(resp) => {
+                exchange.status = Some(resp.status().as_u16());
+                exchange.response_headers = capture_headers(resp.headers());
+                exchange.response_body = capture_body(resp.body(), resp.headers());
+            }
+            Err(e) => exchange.error = Some(e.to_string()),
+        }
+        self.record(&exchange);
+
+        Ok(Response::from(result?))
+    }
+
+    /// Appends `exchange` to the file. A capture that cannot be written is logged rather than
+    /// failing the request it describes.
+    fn record(&self, exchange: &CapturedExchange) {
+        let mut line = match serde_json::to_vec(exchange) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Unable to serialize HTTP capture: {e}");
+                return;
+            }
+        };
+        line.push(b'\n');
+
+        let mut file = self.file.lock().expect("HTTP capture lock poisoned");
+        if let Err(e) = file.write_all(&line) {
+            warn!("Unable to write HTTP capture: {e}");
+        }
+    }
+}
+
+/// Sends `request` and reads its whole body, keeping the status, version, headers and URL so the
+/// response can be turned back into a [`Response`].
+async fn read_response_async(
+    http: &reqwest::Client,
+    request: Request,
+) -> reqwest::Result<http::Response<Vec<u8>>> {
+    let resp = http.execute(request).await?;
+
+    let mut builder = http::Response::builder()
+        .status(resp.status())
+        .version(resp.version())
+        .url(resp.url().clone());
+    if let Some(headers) = builder.headers_mut() {
+        *headers = resp.headers().clone();
+    }
+    let body = resp.bytes().await?.to_vec();
+
+    Ok(builder
+        .body(body)
+        .expect("status and version came from a response"))
+}
+
+/// Header names and values, with credentials redacted.
+fn capture_headers(headers: &HeaderMap) -> BTreeMap<String, String> {
+    headers
+        .iter()
+        .map(|(name, value)| {
+            let value = if SECRET_HEADERS.contains(&name.as_str()) {
+                REDACTED.to_string()
+            } else {
+                String::from_utf8_lossy(value.as_bytes()).into_owned()
+            };
+            (name.to_string(), value)
+        })
+        .collect()
+}
+
+/// A body as JSON when it parses as JSON and as text otherwise, with credentials redacted.
+/// Empty bodies are left out.
+pub fn capture_body(bytes: &[u8], headers: &HeaderMap) -> Option<Value> {
+    if bytes.is_empty() {
+        return None;
+    }
+
+    if let Ok(mut value) = serde_json::from_slice::<Value>(bytes) {
+        redact_json(&mut value);
+        return Some(value);
+    }
+
+    let text = String::from_utf8_lossy(bytes);
+    let is_form = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.starts_with("application/x-www-form-urlencoded"));
+
+    Some(Value::String(if is_form {
+        redact_form(&text)
+    } else {
+        text.into_owned()
+    }))
+}
+
+fn redact_json(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map.iter_mut() {
+                if SECRET_FIELDS.contains(&key.as_str()) {
+                    *value = Value::String(REDACTED.to_string());
+                } else {
+                    redact_json(value);
+                }
+            }
+        }
+        Value::Array(values) => values.iter_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+fn redact_form(form: &str) -> String {
+    form.split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if SECRET_FIELDS.contains(&key) => format!("{key}={REDACTED}"),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use reqwest::StatusCode;
+    use reqwest::header::{AUTHORIZATION, HeaderValue};
+    use serde_json::json;
+
+    fn read_exchanges(path: &Path) -> Vec<CapturedExchange> {
+        std::fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_redacts_secret_headers_json_keys_and_form_fields() {
+        let mut headers = HeaderMap::new();
+        headers.insert(AUTHORIZATION, HeaderValue::from_static("Bearer secret"));
+        headers.insert(
+            reqwest::header::ACCEPT,
+            HeaderValue::from_static("application/json"),
+        );
+        let captured = capture_headers(&headers);
+        assert_eq!(captured["authorization"], REDACTED);
+        assert_eq!(captured["accept"], "application/json");
+
+        let token =
+            br#"{"access_token":"abc","expires_in":300,"nested":[{"refresh_token":"def"}]}"#;
+        assert_eq!(
+            capture_body(token, &HeaderMap::new()),
+            Some(json!({
+                "access_token": REDACTED,
+                "expires_in": 300,
+                "nested": [{"refresh_token": REDACTED}],
+            }))
+        );
+
+        let mut form_headers = HeaderMap::new();
+        form_headers.insert(
+            CONTENT_TYPE,
+            HeaderValue::from_static("application/x-www-form-urlencoded"),
+        );
+        assert_eq!(
+            capture_body(
+                b"grant_type=client_credentials&client_secret=s3cret&scope=api",
+                &form_headers
+            ),
+            Some(json!(
+                "grant_type=client_credentials&client_secret=[REDACTED]&scope=api"
+            ))
+        );
+        assert_eq!(capture_body(b"", &form_headers), None);
+    }
+
+    #[tokio::test]
+    async fn test_execute_records_exchange_and_returns_readable_response() {
+        let server = MockServer::start();
+        server.mock(|when, then| {
+            when.method(PUT).path("/api/asset/assets/1");
+            then.status(400)
+                .header("content-type", "application/json")
+                .json_body(json!({"error": "bad name"}));
+        });
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("capture.jsonl");
+        let capture = HttpCapture::create(&path).unwrap();
+        let http = reqwest::Client::new();
+
+        let request = http
+            .put(format!("http://{}/api/asset/assets/1", server.address()))
+            .header(AUTHORIZATION, "Bearer secret")
+            .json(&json!({"name": "rack-1"}))
+            .build()
+            .unwrap();
+        let resp = capture.execute_async(&http, request).await.unwrap();
+
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        assert!(resp.url().path().ends_with("/api/asset/assets/1"));
+        assert_eq!(
+            resp.json::<Value>().await.unwrap(),
+            json!({"error": "bad name"})
+        );
+
+        let exchanges = read_exchanges(&path);
+        assert_eq!(exchanges.len(), 1);
+        let exchange = &exchanges[0];
+        assert_eq!(exchange.method, "PUT");
+        assert_eq!(exchange.status, Some(400));
+        assert_eq!(exchange.request_headers["authorization"], REDACTED);
+        assert_eq!(exchange.request_body, Some(json!({"name": "rack-1"})));
+        assert_eq!(exchange.response_body, Some(json!({"error": "bad name"})));
+        assert_eq!(exchange.error, None);
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = std::fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
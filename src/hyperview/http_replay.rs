@@ -0,0 +1,302 @@
+use log::debug;
+use reqwest::{
+    Request, Response, ResponseBuilderExt, StatusCode, Url,
+    header::{CONTENT_LENGTH, CONTENT_TYPE, HeaderMap, HeaderName, HeaderValue, TRANSFER_ENCODING},
+};
+use serde_json::Value;
+use std::path::Path;
+use std::sync::Mutex;
+
+use super::{
+    app_errors::AppError,
+    http_capture::{CapturedExchange, capture_body},
+};
+
+/// Instance URL used in replay mode. Requests are matched on everything but the host, so a
+/// capture taken against any instance can be replayed.
+pub const REPLAY_INSTANCE_URL: &str = "http://replay.invalid";
+
+/// Answers requests from a file written by `--capture-http` instead of the network.
+///
+/// A request is answered by a captured exchange with the same method, path, query parameters
+/// (in any order) and body. Bodies are compared after the same redaction the capture applied.
+/// When several exchanges match, as with paging through the same search or a request that was
+/// retried, they are served in the order they were captured and the last one is repeated once
+/// all have been served. Exchanges that got no response, such as timeouts, are skipped.
+pub struct HttpReplay {
+    exchanges: Vec<CapturedExchange>,
+    served: Mutex<Vec<bool>>,
+}
+
+impl HttpReplay {
+    pub fn load(path: &Path) -> Result<Self, AppError> {
+        let unreadable = |reason: String| AppError::ReplayFileUnreadable {
+            path: path.display().to_string(),
+            reason,
+        };
+
+        let contents = std::fs::read_to_string(path).map_err(|e| unreadable(e.to_string()))?;
+        let mut exchanges = Vec::new();
+        for (index, line) in contents.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let exchange: CapturedExchange = serde_json::from_str(line)
+                .map_err(|e| unreadable(format!("line {}: {e}", index + 1)))?;
+            let Some(status) = exchange.status else {
+                continue;
+            };
+            StatusCode::from_u16(status)
+                .map_err(|e| unreadable(format!("line {}: {e}", index + 1)))?;
+            exchanges.push(exchange);
+        }
+
+        debug!(
+            "Loaded {} captured exchange(s) from {}",
+            exchanges.len(),
+            path.display()
+        );
+
+        Ok(Self {
+            served: Mutex::new(vec![false; exchanges.len()]),
+            exchanges,
+        })
+    }
+
+    /// The captured response to `request`, or an error naming the request if the file has none.
+    pub fn respond(&self, request: &Request) -> Result<Response, AppError> {
+        let body = request
+            .body()
+            .and_then(|body| body.as_bytes())
+            .and_then(|bytes| capture_body(bytes, request.headers()));
+
+        let matching: Vec<usize> = self
+            .exchanges
+            .iter()
+            .enumerate()
+            .filter(|(_, exchange)| {
+                exchange.method == request.method().as_str()
+                    && Url::parse(&exchange.url)
+                        .is_ok_and(|url| same_path_and_query(&url, request.url()))
+                    && exchange.request_body == body
+            })
+            .map(|(index, _)| index)
+            .collect();
+
+        let Some(&last) = matching.last() else {
+            return Err(AppError::ReplayUnmatched {
+                method: request.method().to_string(),
+                url: request.url().to_string(),
+            });
+        };
+
+        let mut served = self.served.lock().expect("replay lock poisoned");
+        let index = matching
+            .into_iter()
+            .find(|&index| !served[index])
+            .unwrap_or(last);
+        served[index] = true;
+
+        Ok(replayed_response(&self.exchanges[index], request.url()))
+    }
+}
+
+fn same_path_and_query(captured: &Url, requested: &Url) -> bool {
+    let sorted_query = |url: &Url| {
+        let mut pairs: Vec<(String, String)> = url.query_pairs().into_owned().collect();
+        pairs.sort();
+        pairs
+    };
+
+    captured.path() == requested.path() && sorted_query(captured) == sorted_query(requested)
+}
+
+/// Rebuilds the captured response. JSON bodies are written back out as JSON and text bodies as
+/// they were, so the length may differ from the original and the length headers are dropped.
+fn replayed_response(exchange: &CapturedExchange, url: &Url) -> Response {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &exchange.response_headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) && name != CONTENT_LENGTH
+            && name != TRANSFER_ENCODING
+        {
+            headers.append(name, value);
+        }
+    }
+
+    let is_json = headers
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("json"));
+    let body = match &exchange.response_body {
+        None => Vec::new(),
+        Some(Value::String(text)) if !is_json => text.clone().into_bytes(),
+        Some(value) => value.to_string().into_bytes(),
+    };
+
+    let mut builder = http::Response::builder()
+        .status(exchange.status.unwrap_or_default())
+        .url(url.clone());
+    if let Some(h) = builder.headers_mut() {
+        *h = headers;
+    }
+
+    Response::from(
+        builder
+            .body(body)
+            .expect("status codes are checked when the file is loaded"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::io::Write;
+
+    fn replay_of(lines: &[Value]) -> HttpReplay {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        for line in lines {
+            writeln!(file, "{line}").unwrap();
+        }
+        HttpReplay::load(file.path()).unwrap()
+    }
+
+    fn exchange(method: &str, url: &str, request_body: Value, status: u16, body: Value) -> Value {
+        json!({
+            "started_at": "2026-10-17T09:00:00Z",
+            "duration_ms": 12,
+            "method": method,
+            "url": url,
+            "request_headers": {"authorization": "[REDACTED]"},
+            "request_body": request_body,
+            "status": status,
+            "response_headers": {"content-type": "application/json", "content-length": "999"},
+            "response_body": body,
+        })
+    }
+
+    fn request(method: reqwest::Method, url: &str, body: Option<Value>) -> Request {
+        let builder = reqwest::Client::new().request(method, url);
+        match body {
+            Some(body) => builder.json(&body),
+            None => builder,
+        }
+        .build()
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_respond_matches_method_path_query_and_body_on_any_host() {
+        let replay = replay_of(&[
+            exchange(
+                "GET",
+                "https://prod.test/api/asset/assets?a=1&b=2",
+                Value::Null,
+                200,
+                json!({"query": true}),
+            ),
+            exchange(
+                "POST",
+                "https://prod.test/api/asset/search",
+                json!({"size": 10}),
+                200,
+                json!({"search": 10}),
+            ),
+            exchange(
+                "POST",
+                "https://prod.test/api/asset/search",
+                json!({"size": 20}),
+                200,
+                json!({"search": 20}),
+            ),
+        ]);
+
+        let resp = replay
+            .respond(&request(
+                reqwest::Method::GET,
+                "http://replay.invalid/api/asset/assets?b=2&a=1",
+                None,
+            ))
+            .unwrap();
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.json::<Value>().await.unwrap(), json!({"query": true}));
+
+        let resp = replay
+            .respond(&request(
+                reqwest::Method::POST,
+                "http://replay.invalid/api/asset/search",
+                Some(json!({"size": 20})),
+            ))
+            .unwrap();
+        assert_eq!(resp.json::<Value>().await.unwrap(), json!({"search": 20}));
+
+        for unmatched in [
+            request(
+                reqwest::Method::GET,
+                "http://replay.invalid/api/asset/assets?a=1",
+                None,
+            ),
+            request(
+                reqwest::Method::PUT,
+                "http://replay.invalid/api/asset/search",
+                Some(json!({"size": 10})),
+            ),
+            request(
+                reqwest::Method::POST,
+                "http://replay.invalid/api/asset/search",
+                Some(json!({"size": 30})),
+            ),
+        ] {
+            let err = replay.respond(&unmatched).unwrap_err();
+            assert!(matches!(err, AppError::ReplayUnmatched { .. }));
+            assert!(err.to_string().contains("/api/asset/"));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_respond_serves_repeats_in_order_then_repeats_last() {
+        let url = "https://prod.test/api/asset/assets/1";
+        let mut timed_out = exchange("GET", url, Value::Null, 0, Value::Null);
+        timed_out["status"] = Value::Null;
+        timed_out["error"] = json!("operation timed out");
+        let replay = replay_of(&[
+            timed_out,
+            exchange("GET", url, Value::Null, 503, json!("down")),
+            exchange("GET", url, Value::Null, 200, json!({"name": "rack-1"})),
+        ]);
+        let get = || {
+            request(
+                reqwest::Method::GET,
+                "http://replay.invalid/api/asset/assets/1",
+                None,
+            )
+        };
+
+        assert_eq!(
+            replay.respond(&get()).unwrap().status(),
+            StatusCode::SERVICE_UNAVAILABLE
+        );
+        for _ in 0..2 {
+            let resp = replay.respond(&get()).unwrap();
+            assert_eq!(resp.status(), StatusCode::OK);
+            assert_eq!(
+                resp.json::<Value>().await.unwrap(),
+                json!({"name": "rack-1"})
+            );
+        }
+    }
+
+    #[test]
+    fn test_load_names_bad_line() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        writeln!(file, "not json").unwrap();
+
+        let err = HttpReplay::load(file.path()).err().unwrap();
+
+        assert!(err.to_string().contains("line 1"));
+        assert!(HttpReplay::load(Path::new("/nonexistent/capture.jsonl")).is_err());
+    }
+}
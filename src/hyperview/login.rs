@@ -0,0 +1,419 @@
+use log::{debug, info};
+use oauth2::{
+    AuthorizationCode, CsrfToken, DeviceAuthorizationUrl, PkceCodeChallenge, RedirectUrl, Scope,
+    StandardDeviceAuthorizationResponse,
+};
+use reqwest::Url;
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+
+use super::{
+    app_errors::AppError,
+    auth::{IssuedToken, oauth_client_async},
+    cli_data::LoginArgs,
+    config_data::AppConfig,
+    http_client::oauth_http_client,
+    token_cache::TokenCache,
+};
+
+/// Path of the loopback redirect URI, `http://127.0.0.1:<port>/callback`.
+const CALLBACK_PATH: &str = "/callback";
+/// How long to wait for the browser to come back to the loopback listener.
+const LOGIN_TIMEOUT: Duration = Duration::from_mins(5);
+/// Scope that asks the identity server for a refresh token.
+const OFFLINE_ACCESS_SCOPE: &str = "offline_access";
+
+/// Runs `hvcli login`: signs the user in with the browser or device code flow and stores the
+/// session in the token cache, where `AuthToken` picks it up for later commands.
+pub(crate) async fn login_async(
+    config: &AppConfig,
+    config_path: Option<&Path>,
+    options: &LoginArgs,
+) -> color_eyre::Result<()> {
+    let cache = TokenCache::for_config(config_path, config).ok_or(AppError::NoConfigPath)?;
+
+    let issued = if options.device {
+        login_with_device_code_async(config, |details| {
+            match details.verification_uri_complete() {
+                Some(uri) => println!("Open {} to sign in", uri.secret()),
+                None => println!(
+                    "Open {} and enter the code {}",
+                    details.verification_uri().as_str(),
+                    details.user_code().secret()
+                ),
+            }
+        })
+        .await?
+    } else {
+        login_with_browser_async(config, options.port, |url| {
+            println!("Open this URL in your browser to sign in:\n\n{url}\n");
+        })
+        .await?
+    };
+
+    if issued.refresh_token.is_none() {
+        return Err(AppError::LoginFailed(format!(
+            "the identity server did not issue a refresh token; check that the client may request the {OFFLINE_ACCESS_SCOPE} scope"
+        ))
+        .into());
+    }
+
+    cache.store(&issued);
+    println!(
+        "Logged in; profile {} will use this session",
+        config.profile
+    );
+
+    Ok(())
+}
+
+/// Authorization code flow with PKCE (RFC 7636) and a loopback redirect (RFC 8252). `open_url`
+/// is given the authorize URL to show the user; the code arrives on a listener bound to
+/// 127.0.0.1, on `port` or a free one if it is 0.
+pub async fn login_with_browser_async(
+    config: &AppConfig,
+    port: u16,
+    open_url: impl FnOnce(&str),
+) -> color_eyre::Result<IssuedToken> {
+    let listener = TcpListener::bind(("127.0.0.1", port)).await?;
+    let redirect_url = format!(
+        "http://127.0.0.1:{}{CALLBACK_PATH}",
+        listener.local_addr()?.port()
+    );
+    debug!("Waiting for the login redirect on {redirect_url}");
+
+    let client = oauth_client_async(config)
+        .await?
+        .set_redirect_uri(RedirectUrl::new(redirect_url)?);
+
+    let (pkce_challenge, pkce_verifier) = PkceCodeChallenge::new_random_sha256();
+    let (authorize_url, csrf_state) = client
+        .authorize_url(CsrfToken::new_random)
+        .add_scopes(login_scopes(config))
+        .set_pkce_challenge(pkce_challenge)
+        .url();
+
+    open_url(authorize_url.as_str());
+
+    let code = tokio::time::timeout(
+        LOGIN_TIMEOUT,
+        receive_redirect_async(&listener, &csrf_state),
+    )
+    .await
+    .map_err(|_| AppError::LoginFailed("timed out waiting for the browser".to_string()))??;
+
+    let token_response = client
+        .exchange_code(code)
+        .set_pkce_verifier(pkce_verifier)
+        .request_async(&oauth_http_client(config)?)
+        .await?;
+
+    Ok(IssuedToken::from_response(&token_response))
+}
+
+/// Device authorization flow (RFC 8628) for hosts without a browser. `show_code` is given the
+/// verification URI and user code to show the user; the token endpoint is then polled until the
+/// user approves, denies, or the code expires.
+pub async fn login_with_device_code_async(
+    config: &AppConfig,
+    show_code: impl FnOnce(&StandardDeviceAuthorizationResponse),
+) -> color_eyre::Result<IssuedToken> {
+    let client = oauth_client_async(config)
+        .await?
+        .set_device_authorization_url(DeviceAuthorizationUrl::new(device_authorization_url(
+            config,
+        )?)?);
+    let http_client = oauth_http_client(config)?;
+
+    let details: StandardDeviceAuthorizationResponse = client
+        .exchange_device_code()
+        .add_scopes(login_scopes(config))
+        .request_async(&http_client)
+        .await?;
+
+    show_code(&details);
+
+    let token_response = client
+        .exchange_device_access_token(&details)
+        .request_async(&http_client, tokio::time::sleep, None)
+        .await?;
+
+    Ok(IssuedToken::from_response(&token_response))
+}
+
+/// The profile's `device_auth_url`, or the IdentityServer default next to `token_url`, e.g.
+/// `https://example.hyperviewhq.com/connect/deviceauthorization`.
+pub fn device_authorization_url(config: &AppConfig) -> Result<String, AppError> {
+    if let Some(url) = &config.device_auth_url {
+        return Ok(url.clone());
+    }
+
+    config
+        .token_url
+        .trim_end_matches('/')
+        .strip_suffix("/token")
+        .map(|base| format!("{base}/deviceauthorization"))
+        .ok_or_else(|| AppError::NoDeviceAuthUrl(config.token_url.clone()))
+}
+
+/// The profile's scopes plus `offline_access`, so the session can be refreshed.
+fn login_scopes(config: &AppConfig) -> Vec<Scope> {
+    let mut scopes: Vec<&str> = config.scope.split_whitespace().collect();
+    if !scopes.contains(&OFFLINE_ACCESS_SCOPE) {
+        scopes.push(OFFLINE_ACCESS_SCOPE);
+    }
+
+    scopes
+        .into_iter()
+        .map(|s| Scope::new(s.to_string()))
+        .collect()
+}
+
+/// Serves the loopback listener until the browser is redirected to `CALLBACK_PATH`, answering
+/// anything else (e.g. a favicon request) with a 404. Returns the authorization code once the
+/// `state` parameter has been checked against the one sent.
+async fn receive_redirect_async(
+    listener: &TcpListener,
+    expected_state: &CsrfToken,
+) -> color_eyre::Result<AuthorizationCode> {
+    loop {
+        let (mut stream, _) = listener.accept().await?;
+        let target = read_request_target_async(&mut stream).await?;
+        let url = Url::parse("http://127.0.0.1")?.join(&target)?;
+
+        if url.path() != CALLBACK_PATH {
+            write_response_async(&mut stream, "404 Not Found", "Not found").await?;
+            continue;
+        }
+
+        let params: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let result = match (params.get("error"), params.get("code"), params.get("state")) {
+            (Some(error), _, _) => Err(AppError::LoginFailed(
+                match params.get("error_description") {
+                    Some(description) => format!("{error}: {description}"),
+                    None => error.clone(),
+                },
+            )),
+            (None, Some(code), Some(state)) if state == expected_state.secret() => {
+                Ok(AuthorizationCode::new(code.clone()))
+            }
+            (None, Some(_), _) => Err(AppError::LoginFailed(
+                "the redirect's state parameter did not match; try again".to_string(),
+            )),
+            (None, None, _) => Err(AppError::LoginFailed(
+                "the redirect carried no authorization code".to_string(),
+            )),
+        };
+
+        let page = match &result {
+            Ok(_) => "Signed in to hvcli. You can close this window.".to_string(),
+            Err(e) => format!("hvcli sign-in failed. {e}"),
+        };
+        write_response_async(&mut stream, "200 OK", &page).await?;
+
+        info!("Received the login redirect");
+        return Ok(result?);
+    }
+}
+
+/// Reads an HTTP request's head and returns its target, e.g. `/callback?code=...&state=...`.
+async fn read_request_target_async(stream: &mut TcpStream) -> color_eyre::Result<String> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+
+    // Drain the headers so the browser sees its request fully read before the response.
+    let mut header = String::new();
+    while reader.read_line(&mut header).await? > 0 && header.trim_end() != "" {
+        header.clear();
+    }
+
+    Ok(request_line
+        .split_whitespace()
+        .nth(1)
+        .unwrap_or("/")
+        .to_string())
+}
+
+async fn write_response_async(
+    stream: &mut TcpStream,
+    status: &str,
+    body: &str,
+) -> std::io::Result<()> {
+    let response = format!(
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain; charset=utf-8\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    );
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use httpmock::prelude::*;
+    use serde_json::json;
+
+    fn test_config(server: &MockServer) -> AppConfig {
+        AppConfig {
+            client_id: "hvcli".to_string(),
+            scope: "HyperviewManagerApi".to_string(),
+            auth_url: format!("http://{}/connect/authorize", server.address()),
+            token_url: format!("http://{}/connect/token", server.address()),
+            instance_url: format!("http://{}", server.address()),
+            profile: "default".to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn query_param(url: &Url, name: &str) -> String {
+        url.query_pairs()
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+            .unwrap()
+    }
+
+    /// Plays the browser: follows the authorize URL straight to the redirect URI.
+    fn redirect_with(authorize_url: &str, code: &'static str, state: Option<&'static str>) {
+        let url = Url::parse(authorize_url).unwrap();
+        let redirect_uri = query_param(&url, "redirect_uri");
+        let state = state.map_or_else(|| query_param(&url, "state"), ToString::to_string);
+
+        assert_eq!(query_param(&url, "code_challenge_method"), "S256");
+        assert_eq!(
+            query_param(&url, "scope"),
+            "HyperviewManagerApi offline_access"
+        );
+
+        tokio::spawn(async move {
+            reqwest::get(format!("{redirect_uri}?code={code}&state={state}"))
+                .await
+                .unwrap();
+        });
+    }
+
+    #[tokio::test]
+    async fn test_login_with_browser_async_exchanges_code_with_pkce_verifier() {
+        let server = MockServer::start();
+        let token_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/connect/token")
+                .form_urlencoded_tuple("grant_type", "authorization_code")
+                .form_urlencoded_tuple("code", "test_code")
+                .form_urlencoded_tuple("client_id", "hvcli")
+                .form_urlencoded_tuple_exists("code_verifier")
+                .form_urlencoded_tuple_exists("redirect_uri");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({
+                    "access_token": "user_access_token",
+                    "refresh_token": "user_refresh_token",
+                    "token_type": "bearer",
+                    "expires_in": 3600
+                }));
+        });
+
+        let issued = login_with_browser_async(&test_config(&server), 0, |url| {
+            redirect_with(url, "test_code", None);
+        })
+        .await
+        .unwrap();
+
+        token_mock.assert();
+        assert_eq!(issued.access_token, "user_access_token");
+        assert_eq!(issued.refresh_token.as_deref(), Some("user_refresh_token"));
+    }
+
+    #[tokio::test]
+    async fn test_login_with_browser_async_rejects_state_mismatch() {
+        let server = MockServer::start();
+        let token_mock = server.mock(|when, then| {
+            when.method(POST).path("/connect/token");
+            then.status(500);
+        });
+
+        let err = login_with_browser_async(&test_config(&server), 0, |url| {
+            redirect_with(url, "test_code", Some("forged_state"));
+        })
+        .await
+        .unwrap_err();
+
+        token_mock.assert_calls(0);
+        assert!(err.to_string().contains("state parameter did not match"));
+    }
+
+    #[tokio::test]
+    async fn test_login_with_device_code_async_polls_token_endpoint() {
+        let server = MockServer::start();
+        let device_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/connect/deviceauthorization")
+                .form_urlencoded_tuple("client_id", "hvcli");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({
+                    "device_code": "test_device_code",
+                    "user_code": "ABCD-EFGH",
+                    "verification_uri": "https://example.test/device",
+                    "expires_in": 300,
+                    "interval": 1
+                }));
+        });
+        let token_mock = server.mock(|when, then| {
+            when.method(POST)
+                .path("/connect/token")
+                .form_urlencoded_tuple("grant_type", "urn:ietf:params:oauth:grant-type:device_code")
+                .form_urlencoded_tuple("device_code", "test_device_code");
+            then.status(200)
+                .header("Content-Type", "application/json")
+                .json_body(json!({
+                    "access_token": "device_access_token",
+                    "refresh_token": "device_refresh_token",
+                    "token_type": "bearer",
+                    "expires_in": 3600
+                }));
+        });
+
+        let mut shown_code = String::new();
+        let issued = login_with_device_code_async(&test_config(&server), |details| {
+            shown_code = details.user_code().secret().clone();
+        })
+        .await
+        .unwrap();
+
+        device_mock.assert();
+        token_mock.assert();
+        assert_eq!(shown_code, "ABCD-EFGH");
+        assert_eq!(issued.access_token, "device_access_token");
+        assert_eq!(
+            issued.refresh_token.as_deref(),
+            Some("device_refresh_token")
+        );
+    }
+
+    #[test]
+    fn test_device_authorization_url_defaults_next_to_token_url() {
+        let mut config = AppConfig {
+            token_url: "https://example.test/connect/token".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            device_authorization_url(&config).unwrap(),
+            "https://example.test/connect/deviceauthorization"
+        );
+
+        config.token_url = "https://example.test/oauth2/v1/issue".to_string();
+        assert!(device_authorization_url(&config).is_err());
+
+        config.device_auth_url = Some("https://example.test/oauth2/v1/device".to_string());
+        assert_eq!(
+            device_authorization_url(&config).unwrap(),
+            "https://example.test/oauth2/v1/device"
+        );
+    }
+}
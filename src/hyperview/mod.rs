@@ -1,3 +1,4 @@
+pub mod api_client;
 pub mod api_constants;
 pub mod app_errors;
 pub mod asset_alarm_events_data;
@@ -13,16 +14,34 @@ pub mod asset_sensor_api_functions;
 pub mod auth;
 pub mod bacnet_definition_api_data;
 pub mod bacnet_definition_api_functions;
-pub mod cli_data;
-pub mod cli_functions;
+pub mod bulk;
+pub(crate) mod cli_data;
+pub(crate) mod cli_functions;
 pub mod common_types;
+pub mod config_data;
+pub mod config_functions;
+pub mod csv_input;
 pub mod custom_asset_properties_api_data;
 pub mod custom_asset_properties_api_functions;
 pub mod definition_api_data;
 pub mod definition_api_functions;
+pub mod filter_expression;
+pub mod http_capture;
+pub mod http_client;
+pub mod http_replay;
+pub mod login;
 pub mod modbus_component_api_data;
 pub mod modbus_component_api_functions;
 pub mod modbus_definition_api_data;
 pub mod modbus_definition_api_functions;
+pub mod output_file;
+pub mod output_template;
+pub mod progress;
+pub mod rate_limit;
+pub mod record_writer;
+pub mod retry;
+pub mod search_filter;
 pub mod sensor_report_data;
 pub mod sensor_report_functions;
+pub mod table_output;
+pub mod token_cache;
@@ -1,59 +1,35 @@
-use log::debug;
-use reqwest::{Client, header::AUTHORIZATION};
 use uuid::Uuid;
 
 use super::{
+    api_client::HyperviewClient,
     api_constants::MODBUS_DEFINITION_API_PREFIX,
-    cli_data::AppConfig,
     modbus_component_api_data::{ModbusComponentCreateDto, ModbusComponentDto},
 };
 
 pub async fn list_modbus_components_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
 ) -> color_eyre::Result<Vec<ModbusComponentDto>> {
-    let target_url = format!(
-        "{}{}/modbusTcpComponents/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<ModbusComponentDto>>()
-        .await?;
-
-    Ok(resp)
+    client
+        .get(&format!(
+            "{MODBUS_DEFINITION_API_PREFIX}/modbusTcpComponents/{definition_id}"
+        ))
+        .await
 }
 
 pub async fn add_modbus_component_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     name: String,
 ) -> color_eyre::Result<Uuid> {
-    let target_url = format!(
-        "{}{}/modbusTcpComponents/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
-
     let component = ModbusComponentCreateDto { id: None, name };
 
-    let id = req
-        .post(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&component)
-        .send()
+    let id = client
+        .post(
+            &format!("{MODBUS_DEFINITION_API_PREFIX}/modbusTcpComponents/{definition_id}"),
+            &component,
+        )
         .await?
-        .error_for_status()?
         .json::<Uuid>()
         .await?;
 
@@ -61,53 +37,37 @@ pub async fn add_modbus_component_async(
 }
 
 pub async fn update_modbus_component_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     component_id: Uuid,
     name: String,
 ) -> color_eyre::Result<()> {
-    // Update is a PUT to the collection path; the component id travels in the body.
-    let target_url = format!(
-        "{}{}/modbusTcpComponents/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
-
     let component = ModbusComponentCreateDto {
         id: Some(component_id),
         name,
     };
 
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(&component)
-        .send()
-        .await?
-        .error_for_status()?;
+    // Update is a PUT to the collection path; the component id travels in the body.
+    client
+        .put(
+            &format!("{MODBUS_DEFINITION_API_PREFIX}/modbusTcpComponents/{definition_id}"),
+            &component,
+        )
+        .await?;
 
     Ok(())
 }
 
 pub async fn delete_modbus_component_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     component_id: Uuid,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/modbusTcpComponents/{}/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id, component_id
-    );
-    debug!("Request URL: {target_url}");
-
-    req.delete(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?;
+    client
+        .delete(&format!(
+            "{MODBUS_DEFINITION_API_PREFIX}/modbusTcpComponents/{definition_id}/{component_id}"
+        ))
+        .await?;
 
     Ok(())
 }
@@ -115,6 +75,7 @@ pub async fn delete_modbus_component_async(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
 
@@ -142,10 +103,9 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = list_modbus_components_async(&config, &client, &auth_header, definition_id)
+        let resp = list_modbus_components_async(&client, definition_id)
             .await
             .unwrap();
 
@@ -178,18 +138,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
-
-        let id = add_modbus_component_async(
-            &config,
-            &client,
-            &auth_header,
-            definition_id,
-            "PDU 2".to_string(),
-        )
-        .await
-        .unwrap();
+        let client = HyperviewClient::for_test(config);
+
+        let id = add_modbus_component_async(&client, definition_id, "PDU 2".to_string())
+            .await
+            .unwrap();
 
         m.assert();
         assert_eq!(id, new_id);
@@ -214,19 +167,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
-
-        update_modbus_component_async(
-            &config,
-            &client,
-            &auth_header,
-            definition_id,
-            component_id,
-            "Renamed".to_string(),
-        )
-        .await
-        .unwrap();
+        let client = HyperviewClient::for_test(config);
+
+        update_modbus_component_async(&client, definition_id, component_id, "Renamed".to_string())
+            .await
+            .unwrap();
 
         m.assert();
     }
@@ -249,10 +194,9 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        delete_modbus_component_async(&config, &client, &auth_header, definition_id, component_id)
+        delete_modbus_component_async(&client, definition_id, component_id)
             .await
             .unwrap();
 
@@ -277,17 +221,9 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
-
-        let result = delete_modbus_component_async(
-            &config,
-            &client,
-            &auth_header,
-            definition_id,
-            component_id,
-        )
-        .await;
+        let client = HyperviewClient::for_test(config);
+
+        let result = delete_modbus_component_async(&client, definition_id, component_id).await;
 
         m.assert();
         assert!(result.is_err());
@@ -1,13 +1,10 @@
 use log::{debug, error};
-use reqwest::{Client, header::AUTHORIZATION};
 use uuid::Uuid;
 
-use crate::retry_on_unauthorized_async;
-
 use super::{
+    api_client::HyperviewClient,
     api_constants::MODBUS_DEFINITION_API_PREFIX,
-    auth::AuthToken,
-    cli_data::AppConfig,
+    bulk::run_rows_async,
     modbus_definition_api_data::{
         ModbusNonNumericSensorDefinitionDto, ModbusNonNumericSensorDefinitionImportCsv,
         ModbusNonNumericSensorDefinitionImportDto, ModbusNumericSensorDefinitionDto,
@@ -16,111 +13,64 @@ use super::{
 };
 
 pub async fn list_modbus_numeric_sensor_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
 ) -> color_eyre::Result<Vec<ModbusNumericSensorDefinitionDto>> {
-    let target_url = format!(
-        "{}{}/modbusTcpNumericSensors/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<ModbusNumericSensorDefinitionDto>>()
-        .await?;
-
-    Ok(resp)
+    let target_path =
+        format!("{MODBUS_DEFINITION_API_PREFIX}/modbusTcpNumericSensors/{definition_id}");
+
+    client.get(&target_path).await
 }
 
 pub async fn list_modbus_non_numeric_sensor_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
 ) -> color_eyre::Result<Vec<ModbusNonNumericSensorDefinitionDto>> {
-    let target_url = format!(
-        "{}{}/modbusTcpNonNumericSensors/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
-
-    let resp = req
-        .get(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .send()
-        .await?
-        .error_for_status()?
-        .json::<Vec<ModbusNonNumericSensorDefinitionDto>>()
-        .await?;
-
-    Ok(resp)
+    let target_path =
+        format!("{MODBUS_DEFINITION_API_PREFIX}/modbusTcpNonNumericSensors/{definition_id}");
+
+    client.get(&target_path).await
 }
 
 async fn create_modbus_numeric_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     sensor: &ModbusNumericSensorDefinitionImportDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/modbusTcpNumericSensors/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
+    let target_path =
+        format!("{MODBUS_DEFINITION_API_PREFIX}/modbusTcpNumericSensors/{definition_id}");
 
-    req.post(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.post(&target_path, sensor).await?;
 
     Ok(())
 }
 
 async fn update_modbus_numeric_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     sensor_id: Uuid,
     sensor: &ModbusNumericSensorDefinitionImportDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/modbusTcpNumericSensors/{}/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id, sensor_id
+    let target_path = format!(
+        "{MODBUS_DEFINITION_API_PREFIX}/modbusTcpNumericSensors/{definition_id}/{sensor_id}"
     );
-    debug!("Request URL: {target_url}");
 
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.put(&target_path, sensor).await?;
 
     Ok(())
 }
 
 pub async fn bulk_import_modbus_numeric_sensor_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: &String,
     definition_id: Uuid,
     create_as_new: bool,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
     let mut reader = csv::Reader::from_path(filename)?;
     let mut total: usize = 0;
     let mut failed: usize = 0;
+    let mut sensors = Vec::new();
 
     for result in reader.deserialize::<ModbusNumericSensorDefinitionImportDto>() {
         total += 1;
@@ -140,47 +90,36 @@ pub async fn bulk_import_modbus_numeric_sensor_definitions_async(
             sensor.id = None;
         }
 
-        auth_token.refresh_if_needed_async(config).await?;
+        sensors.push(sensor);
+    }
 
-        debug!("Processing sensor definition row: {sensor:?}");
+    failed += run_rows_async(
+        sensors,
+        concurrency,
+        |sensor| sensor.id,
+        |sensor| async move {
+            debug!("Processing sensor definition row: {sensor:?}");
 
-        let result = match sensor.id {
-            Some(id) => {
-                retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
+            let result = match sensor.id {
+                Some(id) => {
                     update_modbus_numeric_sensor_definition_async(
-                        config,
-                        req,
-                        &auth_token.header,
+                        client,
                         definition_id,
                         id,
                         &sensor,
                     )
                     .await
-                )
-            }
-            None => {
-                retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
-                    create_modbus_numeric_sensor_definition_async(
-                        config,
-                        req,
-                        &auth_token.header,
-                        definition_id,
-                        &sensor,
-                    )
-                    .await
-                )
-            }
-        };
-
-        if let Err(e) = result {
-            error!("Failed to import sensor definition '{}': {e}", sensor.name);
-            failed += 1;
-        }
-    }
+                }
+                None => {
+                    create_modbus_numeric_sensor_definition_async(client, definition_id, &sensor)
+                        .await
+                }
+            };
+
+            result.map_err(|e| format!("Failed to import sensor definition '{}': {e}", sensor.name))
+        },
+    )
+    .await;
 
     if failed > 0 {
         return Err(super::app_errors::AppError::BulkOperationFailures { failed, total }.into());
@@ -190,63 +129,44 @@ pub async fn bulk_import_modbus_numeric_sensor_definitions_async(
 }
 
 async fn create_modbus_non_numeric_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     sensor: &ModbusNonNumericSensorDefinitionImportDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/modbusTcpNonNumericSensors/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id
-    );
-    debug!("Request URL: {target_url}");
+    let target_path =
+        format!("{MODBUS_DEFINITION_API_PREFIX}/modbusTcpNonNumericSensors/{definition_id}");
 
-    req.post(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.post(&target_path, sensor).await?;
 
     Ok(())
 }
 
 async fn update_modbus_non_numeric_sensor_definition_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_header: &String,
+    client: &HyperviewClient,
     definition_id: Uuid,
     sensor_id: Uuid,
     sensor: &ModbusNonNumericSensorDefinitionImportDto,
 ) -> color_eyre::Result<()> {
-    let target_url = format!(
-        "{}{}/modbusTcpNonNumericSensors/{}/{}",
-        config.instance_url, MODBUS_DEFINITION_API_PREFIX, definition_id, sensor_id
+    let target_path = format!(
+        "{MODBUS_DEFINITION_API_PREFIX}/modbusTcpNonNumericSensors/{definition_id}/{sensor_id}"
     );
-    debug!("Request URL: {target_url}");
 
-    req.put(target_url)
-        .header(AUTHORIZATION, auth_header)
-        .json(sensor)
-        .send()
-        .await?
-        .error_for_status()?;
+    client.put(&target_path, sensor).await?;
 
     Ok(())
 }
 
 pub async fn bulk_import_modbus_non_numeric_sensor_definitions_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
+    client: &HyperviewClient,
     filename: &String,
     definition_id: Uuid,
     create_as_new: bool,
+    concurrency: usize,
 ) -> color_eyre::Result<()> {
     let mut reader = csv::Reader::from_path(filename)?;
     let mut total: usize = 0;
     let mut failed: usize = 0;
+    let mut sensors = Vec::new();
 
     for result in reader.deserialize::<ModbusNonNumericSensorDefinitionImportCsv>() {
         total += 1;
@@ -260,8 +180,6 @@ pub async fn bulk_import_modbus_non_numeric_sensor_definitions_async(
             }
         };
 
-        auth_token.refresh_if_needed_async(config).await?;
-
         let mut sensor = match ModbusNonNumericSensorDefinitionImportDto::try_from(&row) {
             Ok(sensor) => sensor,
             Err(e) => {
@@ -280,45 +198,40 @@ pub async fn bulk_import_modbus_non_numeric_sensor_definitions_async(
             sensor.id = None;
         }
 
-        debug!("Processing sensor definition row: {sensor:?}");
+        sensors.push(sensor);
+    }
+
+    failed += run_rows_async(
+        sensors,
+        concurrency,
+        |sensor| sensor.id,
+        |sensor| async move {
+            debug!("Processing sensor definition row: {sensor:?}");
 
-        let result = match sensor.id {
-            Some(id) => {
-                retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
+            let result = match sensor.id {
+                Some(id) => {
                     update_modbus_non_numeric_sensor_definition_async(
-                        config,
-                        req,
-                        &auth_token.header,
+                        client,
                         definition_id,
                         id,
                         &sensor,
                     )
                     .await
-                )
-            }
-            None => {
-                retry_on_unauthorized_async!(
-                    config,
-                    auth_token,
+                }
+                None => {
                     create_modbus_non_numeric_sensor_definition_async(
-                        config,
-                        req,
-                        &auth_token.header,
+                        client,
                         definition_id,
                         &sensor,
                     )
                     .await
-                )
-            }
-        };
+                }
+            };
 
-        if let Err(e) = result {
-            error!("Failed to import sensor definition '{}': {e}", sensor.name);
-            failed += 1;
-        }
-    }
+            result.map_err(|e| format!("Failed to import sensor definition '{}': {e}", sensor.name))
+        },
+    )
+    .await;
 
     if failed > 0 {
         return Err(super::app_errors::AppError::BulkOperationFailures { failed, total }.into());
@@ -330,14 +243,10 @@ pub async fn bulk_import_modbus_non_numeric_sensor_definitions_async(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::hyperview::config_data::AppConfig;
     use httpmock::prelude::*;
     use serde_json::json;
     use std::io::Write;
-    use std::time::Duration;
-
-    fn auth_token() -> AuthToken {
-        AuthToken::for_test("Bearer test_token", Duration::from_hours(1))
-    }
 
     #[tokio::test]
     // offset is asserted against an exact deserialized sentinel (0.0), so an exact compare is fine.
@@ -376,17 +285,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = list_modbus_numeric_sensor_definitions_async(
-            &config,
-            &client,
-            &auth_header,
-            definition_id,
-        )
-        .await
-        .unwrap();
+        let resp = list_modbus_numeric_sensor_definitions_async(&client, definition_id)
+            .await
+            .unwrap();
 
         m.assert();
         assert_eq!(resp.len(), 1);
@@ -431,17 +334,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let auth_header = "Bearer test_token".to_string();
+        let client = HyperviewClient::for_test(config);
 
-        let resp = list_modbus_non_numeric_sensor_definitions_async(
-            &config,
-            &client,
-            &auth_header,
-            definition_id,
-        )
-        .await
-        .unwrap();
+        let resp = list_modbus_non_numeric_sensor_definitions_async(&client, definition_id)
+            .await
+            .unwrap();
 
         m.assert();
         assert_eq!(resp.len(), 1);
@@ -483,17 +380,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_numeric_csv(&[("", "New Sensor")]);
 
         bulk_import_modbus_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await
         .unwrap();
@@ -525,17 +420,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_numeric_csv(&[(&sensor_id.to_string(), "Existing Sensor")]);
 
         bulk_import_modbus_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await
         .unwrap();
@@ -568,17 +461,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_numeric_csv(&[("", "Bad Sensor"), ("", "Good Sensor")]);
 
         let result = bulk_import_modbus_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await;
 
@@ -638,16 +529,14 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
         let result = bulk_import_modbus_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await;
 
@@ -701,17 +590,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_non_numeric_csv(&[("", "Status", "Inactive:0,Active:1")]);
 
         bulk_import_modbus_non_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await
         .unwrap();
@@ -736,17 +623,15 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
         let csv = write_non_numeric_csv(&[("", "Status", "Inactive:not-a-number")]);
 
         let result = bulk_import_modbus_non_numeric_sensor_definitions_async(
-            &config,
             &client,
-            &mut token,
             &csv.path().to_string_lossy().to_string(),
             definition_id,
             false,
+            1,
         )
         .await;
 
@@ -0,0 +1,254 @@
+use std::fs;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+use super::app_errors::AppError;
+
+/// What to do when an output file already exists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExistingFilePolicy {
+    Fail,
+    Overwrite,
+    Append,
+}
+
+/// An output file that only appears at its path once it is complete. Records are written to a
+/// temporary file next to it, which [`OutputFile::commit`] renames over the path in one step, so
+/// an interrupted run leaves either the old file or no file, never half a report. When appending,
+/// the temporary file starts as a copy of the existing one.
+pub struct OutputFile {
+    temp: BufWriter<NamedTempFile>,
+    path: PathBuf,
+    policy: ExistingFilePolicy,
+}
+
+impl OutputFile {
+    pub fn create(path: &Path, policy: ExistingFilePolicy) -> color_eyre::Result<Self> {
+        let existing = match fs::metadata(path) {
+            Ok(metadata) => Some(metadata),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+        if existing.is_some() && policy == ExistingFilePolicy::Fail {
+            return Err(AppError::FileExists.into());
+        }
+
+        let dir = match path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let prefix = format!(
+            ".{}.",
+            path.file_name().unwrap_or_default().to_string_lossy()
+        );
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(&prefix).suffix(".tmp");
+        // Temporary files are private by default; give a new report the usual permissions.
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(fs::Permissions::from_mode(0o666));
+        }
+        let mut temp = builder.tempfile_in(dir)?;
+
+        if let Some(metadata) = existing {
+            temp.as_file().set_permissions(metadata.permissions())?;
+            if policy == ExistingFilePolicy::Append {
+                let copied = fs::read(path)?;
+                temp.write_all(&copied)?;
+                // Appended records start on a line of their own.
+                if copied.last().is_some_and(|&b| b != b'\n') {
+                    temp.write_all(b"\n")?;
+                }
+            }
+        }
+
+        Ok(Self {
+            temp: BufWriter::new(temp),
+            path: path.to_path_buf(),
+            policy,
+        })
+    }
+
+    /// Moves the finished file into place. Under [`ExistingFilePolicy::Fail`], a file that
+    /// appeared at the path while this one was being written is left alone and
+    /// [`AppError::FileExists`] is returned.
+    pub fn commit(self) -> color_eyre::Result<()> {
+        let temp = self.temp.into_inner().map_err(|e| e.into_error())?;
+        temp.as_file().sync_all()?;
+        if self.policy == ExistingFilePolicy::Fail {
+            temp.persist_noclobber(&self.path).map_err(|e| {
+                if e.error.kind() == io::ErrorKind::AlreadyExists {
+                    color_eyre::Report::new(AppError::FileExists)
+                } else {
+                    e.into()
+                }
+            })?;
+        } else {
+            temp.persist(&self.path)?;
+        }
+
+        Ok(())
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.temp.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.temp.flush()
+    }
+}
+
+/// Where command output goes: standard output, or a file that is put in place by
+/// [`OutputTarget::close`].
+pub enum OutputTarget {
+    Stdout(io::Stdout),
+    File(OutputFile),
+}
+
+impl OutputTarget {
+    /// Flushes standard output or commits the file.
+    pub fn close(self) -> color_eyre::Result<()> {
+        match self {
+            Self::Stdout(mut stdout) => Ok(stdout.flush()?),
+            Self::File(file) => file.commit(),
+        }
+    }
+}
+
+impl Write for OutputTarget {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Self::Stdout(stdout) => stdout.write(buf),
+            Self::File(file) => file.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Self::Stdout(stdout) => stdout.flush(),
+            Self::File(file) => file.flush(),
+        }
+    }
+}
+
+/// The header row of the CSV file at `path`, or `None` if the file is empty.
+pub fn read_csv_header(path: &Path) -> color_eyre::Result<Option<Vec<String>>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .from_path(path)?;
+    let mut record = csv::StringRecord::new();
+    if !reader.read_record(&mut record)? {
+        return Ok(None);
+    }
+
+    Ok(Some(record.iter().map(str::to_string).collect()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_and_commit(path: &Path, policy: ExistingFilePolicy, contents: &str) {
+        let mut file = OutputFile::create(path, policy).unwrap();
+        file.write_all(contents.as_bytes()).unwrap();
+        file.commit().unwrap();
+    }
+
+    #[test]
+    fn test_policies() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.csv");
+
+        write_and_commit(&path, ExistingFilePolicy::Fail, "a\n");
+        let err = OutputFile::create(&path, ExistingFilePolicy::Fail)
+            .err()
+            .unwrap()
+            .downcast::<AppError>()
+            .unwrap();
+        assert!(matches!(err, AppError::FileExists));
+
+        write_and_commit(&path, ExistingFilePolicy::Overwrite, "b\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b\n");
+
+        write_and_commit(&path, ExistingFilePolicy::Append, "c\n");
+        assert_eq!(fs::read_to_string(&path).unwrap(), "b\nc\n");
+    }
+
+    #[test]
+    fn test_fail_policy_keeps_a_file_that_appears_while_writing() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.csv");
+
+        let mut file = OutputFile::create(&path, ExistingFilePolicy::Fail).unwrap();
+        file.write_all(b"new\n").unwrap();
+        fs::write(&path, "theirs\n").unwrap();
+
+        let err = file.commit().unwrap_err().downcast::<AppError>().unwrap();
+        assert!(matches!(err, AppError::FileExists));
+        assert_eq!(fs::read_to_string(&path).unwrap(), "theirs\n");
+    }
+
+    #[test]
+    fn test_append_starts_a_new_line() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.ndjson");
+        fs::write(&path, "{\"id\":1}").unwrap();
+
+        write_and_commit(&path, ExistingFilePolicy::Append, "{\"id\":2}\n");
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "{\"id\":1}\n{\"id\":2}\n"
+        );
+    }
+
+    #[test]
+    fn test_uncommitted_file_leaves_target_untouched() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.csv");
+        fs::write(&path, "old\n").unwrap();
+
+        let mut file = OutputFile::create(&path, ExistingFilePolicy::Overwrite).unwrap();
+        file.write_all(b"half a rep").unwrap();
+        drop(file);
+
+        assert_eq!(fs::read_to_string(&path).unwrap(), "old\n");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_overwrite_keeps_permissions() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.csv");
+        fs::write(&path, "old\n").unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o640)).unwrap();
+
+        write_and_commit(&path, ExistingFilePolicy::Overwrite, "new\n");
+
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o640);
+    }
+
+    #[test]
+    fn test_read_csv_header() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.csv");
+
+        fs::write(&path, "").unwrap();
+        assert_eq!(read_csv_header(&path).unwrap(), None);
+
+        fs::write(&path, "id,\"name, full\"\n1,x\n").unwrap();
+        assert_eq!(
+            read_csv_header(&path).unwrap(),
+            Some(vec!["id".to_string(), "name, full".to_string()])
+        );
+    }
+}
@@ -0,0 +1,320 @@
+use serde_json::{Map, Value};
+use std::str::FromStr;
+
+use super::app_errors::AppError;
+
+/// A line of text with record fields filled in, given with `--format`.
+///
+/// - `{name}` is replaced with the field's value. Nested fields are reached with dots, e.g.
+///   `{location.name}`.
+/// - `{name|text}` uses `text` when the field is missing, null or empty.
+/// - `{?name}...{/name}` keeps what is between the tags only when the field has a value, and
+///   `{!name}...{/name}` only when it does not. Sections can be nested.
+/// - `{{` and `}}` stand for literal braces.
+///
+/// Values are written as text, with numbers, booleans and nested values as JSON. A field counts
+/// as having a value unless it is missing, null, empty or `false`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutputTemplate {
+    nodes: Vec<Node>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Text(String),
+    Field {
+        path: Vec<String>,
+        default: Option<String>,
+    },
+    Section {
+        path: Vec<String>,
+        negated: bool,
+        body: Vec<Node>,
+    },
+}
+
+impl OutputTemplate {
+    pub fn parse(template: &str) -> Result<Self, AppError> {
+        let invalid = |reason: String| AppError::InvalidFormat {
+            template: template.to_string(),
+            reason,
+        };
+
+        // Sections still open, innermost last, each with the nodes collected so far.
+        let mut open: Vec<(String, bool, Vec<Node>)> = Vec::new();
+        let mut nodes: Vec<Node> = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '}' => {
+                    return Err(invalid(
+                        "'}' without a matching '{'; write '}}' for a literal brace".to_string(),
+                    ));
+                }
+                '{' => {
+                    let mut tag = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => {
+                                return Err(invalid(format!("'{{{tag}' is not closed with '}}'")));
+                            }
+                            Some(c) => tag.push(c),
+                        }
+                    }
+
+                    if !text.is_empty() {
+                        innermost(&mut open, &mut nodes)
+                            .push(Node::Text(std::mem::take(&mut text)));
+                    }
+
+                    if let Some(name) = tag.strip_prefix('?').or_else(|| tag.strip_prefix('!')) {
+                        let name = field_name(name).map_err(&invalid)?;
+                        open.push((name, tag.starts_with('!'), Vec::new()));
+                    } else if let Some(name) = tag.strip_prefix('/') {
+                        let name = field_name(name).map_err(&invalid)?;
+                        let Some((opened, negated, body)) = open.pop() else {
+                            return Err(invalid(format!(
+                                "'{{/{name}}}' closes a section that was never opened"
+                            )));
+                        };
+                        if opened != name {
+                            return Err(invalid(format!(
+                                "'{{/{name}}}' closes the section opened for '{opened}'"
+                            )));
+                        }
+                        innermost(&mut open, &mut nodes).push(Node::Section {
+                            path: split_path(&opened),
+                            negated,
+                            body,
+                        });
+                    } else {
+                        let (name, default) = match tag.split_once('|') {
+                            Some((name, default)) => (name, Some(default.to_string())),
+                            None => (tag.as_str(), None),
+                        };
+                        let name = field_name(name).map_err(&invalid)?;
+                        innermost(&mut open, &mut nodes).push(Node::Field {
+                            path: split_path(&name),
+                            default,
+                        });
+                    }
+                }
+                c => text.push(c),
+            }
+        }
+
+        if let Some((name, ..)) = open.last() {
+            return Err(invalid(format!(
+                "the section for '{name}' is never closed with '{{/{name}}}'"
+            )));
+        }
+        if !text.is_empty() {
+            nodes.push(Node::Text(text));
+        }
+
+        Ok(Self { nodes })
+    }
+
+    /// Fails if the template names a field `record` does not have, listing the ones it has. Only
+    /// the first part of a dotted name is checked.
+    pub fn check_fields(&self, record: &Map<String, Value>) -> Result<(), AppError> {
+        let mut unknown: Vec<&str> = Vec::new();
+        let mut pending: Vec<&Node> = self.nodes.iter().collect();
+        while let Some(node) = pending.pop() {
+            let path = match node {
+                Node::Text(_) => continue,
+                Node::Field { path, .. } => path,
+                Node::Section { path, body, .. } => {
+                    pending.extend(body);
+                    path
+                }
+            };
+            if !record.contains_key(&path[0]) && !unknown.contains(&path[0].as_str()) {
+                unknown.push(&path[0]);
+            }
+        }
+
+        if unknown.is_empty() {
+            return Ok(());
+        }
+        unknown.sort_unstable();
+
+        Err(AppError::UnknownFormatFields {
+            unknown: unknown.join(", "),
+            valid: record.keys().cloned().collect::<Vec<_>>().join(", "),
+        })
+    }
+
+    pub fn render(&self, record: &Value) -> String {
+        let mut out = String::new();
+        render_nodes(&self.nodes, record, &mut out);
+        out
+    }
+}
+
+impl FromStr for OutputTemplate {
+    type Err = AppError;
+
+    fn from_str(template: &str) -> Result<Self, Self::Err> {
+        Self::parse(template)
+    }
+}
+
+/// Where parsed nodes go: the innermost open section, or the top level.
+fn innermost<'a>(
+    open: &'a mut [(String, bool, Vec<Node>)],
+    nodes: &'a mut Vec<Node>,
+) -> &'a mut Vec<Node> {
+    match open.last_mut() {
+        Some((_, _, body)) => body,
+        None => nodes,
+    }
+}
+
+fn field_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() || name.split('.').any(str::is_empty) {
+        return Err(format!("'{name}' is not a field name"));
+    }
+
+    Ok(name.to_string())
+}
+
+fn split_path(name: &str) -> Vec<String> {
+    name.split('.').map(str::to_string).collect()
+}
+
+fn lookup<'a>(record: &'a Value, path: &[String]) -> Option<&'a Value> {
+    path.iter().try_fold(record, |value, key| value.get(key))
+}
+
+fn has_value(value: Option<&Value>) -> bool {
+    !matches!(value, None | Some(Value::Null) | Some(Value::Bool(false)))
+        && value.and_then(Value::as_str) != Some("")
+}
+
+fn render_nodes(nodes: &[Node], record: &Value, out: &mut String) {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Field { path, default } => {
+                let value = lookup(record, path);
+                match (value, default) {
+                    (Some(Value::String(s)), _) if !s.is_empty() => out.push_str(s),
+                    (Some(value), _) if !value.is_null() && !value.is_string() => {
+                        out.push_str(&value.to_string())
+                    }
+                    (_, Some(default)) => out.push_str(default),
+                    _ => {}
+                }
+            }
+            Node::Section {
+                path,
+                negated,
+                body,
+            } => {
+                if has_value(lookup(record, path)) != *negated {
+                    render_nodes(body, record, out);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn render(template: &str, record: Value) -> String {
+        OutputTemplate::parse(template).unwrap().render(&record)
+    }
+
+    #[test]
+    fn test_substitutes_fields_and_defaults() {
+        let rack = json!({
+            "id": "aaaa-1",
+            "name": "rack-1",
+            "path": "All/DC1",
+            "serialNumber": "",
+            "power": 5000,
+            "location": {"name": "Room 101"},
+        });
+
+        assert_eq!(
+            render("{name} ({id}) in {path}", rack.clone()),
+            "rack-1 (aaaa-1) in All/DC1"
+        );
+        assert_eq!(
+            render(
+                "{serialNumber|no serial}, {power|?} W, {location.name}",
+                rack.clone()
+            ),
+            "no serial, 5000 W, Room 101"
+        );
+        assert_eq!(render("{{literal}} {missing|-}", rack), "{literal} -");
+    }
+
+    #[test]
+    fn test_sections_render_on_presence() {
+        let template = "{name}{?parent} under {parent}{/parent}{!parent} (top level){/parent}";
+
+        assert_eq!(
+            render(template, json!({"name": "rack-1", "parent": "Room 101"})),
+            "rack-1 under Room 101"
+        );
+        assert_eq!(
+            render(template, json!({"name": "DC1", "parent": null})),
+            "DC1 (top level)"
+        );
+        assert_eq!(
+            render("{?a}a{?b} and b{/b}{/a}", json!({"a": true, "b": false})),
+            "a"
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_malformed_templates() {
+        for template in [
+            "{name",
+            "name}",
+            "{?name}unclosed",
+            "{?a}{/b}",
+            "{/a}",
+            "{}",
+            "{a..b}",
+        ] {
+            let err = OutputTemplate::parse(template).unwrap_err();
+            assert!(
+                matches!(err, AppError::InvalidFormat { .. }),
+                "{template}: {err}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_check_fields_lists_valid_ones() {
+        let template = OutputTemplate::parse("{name} {?nmae}{idd.x}{/nmae}").unwrap();
+        let record = json!({"id": 1, "name": "rack-1"});
+
+        let err = template
+            .check_fields(record.as_object().unwrap())
+            .unwrap_err();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown field(s) idd, nmae in --format. Valid fields are: id, name"
+        );
+    }
+}
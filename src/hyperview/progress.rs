@@ -0,0 +1,38 @@
+use std::io::{IsTerminal, Write};
+
+/// A running count of fetched records, redrawn in place on standard error. Nothing is drawn when
+/// standard error is not a terminal, so redirected logs stay clean.
+pub struct Progress {
+    label: &'static str,
+    enabled: bool,
+    drawn: bool,
+}
+
+impl Progress {
+    pub fn new(label: &'static str, enabled: bool) -> Self {
+        Self {
+            label,
+            enabled: enabled && std::io::stderr().is_terminal(),
+            drawn: false,
+        }
+    }
+
+    /// `fetched` records so far, of an estimated `total`.
+    pub fn update(&mut self, fetched: u64, total: u64) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut stderr = std::io::stderr().lock();
+        let _ = write!(stderr, "\r{}: {fetched} of ~{total}", self.label);
+        let _ = stderr.flush();
+        self.drawn = true;
+    }
+
+    /// Ends the line, so later output starts on a fresh one.
+    pub fn finish(self) {
+        if self.drawn {
+            eprintln!();
+        }
+    }
+}
@@ -0,0 +1,187 @@
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use super::config_data::AppConfig;
+
+/// Token bucket that spaces out requests to at most `rate` per second on average, while letting
+/// up to `burst` go out back to back after a quiet spell. Shared by every request a client
+/// sends, including retries, so the limit holds however many bulk rows are in flight.
+pub struct RateLimiter {
+    rate: f64,
+    burst: f64,
+    bucket: Mutex<Bucket>,
+    requests: AtomicU64,
+    waited_micros: AtomicU64,
+}
+
+struct Bucket {
+    /// Requests that may go out now. Negative when callers have reserved slots they are still
+    /// waiting for.
+    tokens: f64,
+    updated: Instant,
+}
+
+impl RateLimiter {
+    /// A limiter for the profile's `rate_limit_per_sec`, or `None` when requests are not limited.
+    /// `rate_limit_burst` defaults to one second's worth of requests.
+    pub fn from_config(config: &AppConfig) -> Option<Self> {
+        let rate = config
+            .rate_limit_per_sec
+            .filter(|rate| rate.is_finite() && *rate > 0.0)?;
+        let burst = config
+            .rate_limit_burst
+            .map_or(rate.ceil(), f64::from)
+            .max(1.0);
+
+        Some(Self::new(rate, burst))
+    }
+
+    fn new(rate: f64, burst: f64) -> Self {
+        Self {
+            rate,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst,
+                updated: Instant::now(),
+            }),
+            requests: AtomicU64::new(0),
+            waited_micros: AtomicU64::new(0),
+        }
+    }
+
+    /// Waits until one more request may be sent. Callers are served in the order they arrive.
+    pub async fn acquire_async(&self) {
+        let wait = self.reserve(Instant::now());
+
+        self.requests.fetch_add(1, Ordering::Relaxed);
+        if !wait.is_zero() {
+            let micros = u64::try_from(wait.as_micros()).unwrap_or(u64::MAX);
+            self.waited_micros.fetch_add(micros, Ordering::Relaxed);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes a token, going into debt if none is left, and returns how long the caller must wait
+    /// for the debt to be repaid.
+    fn reserve(&self, now: Instant) -> Duration {
+        let mut bucket = self.bucket.lock().expect("rate limiter lock poisoned");
+
+        let elapsed = now.saturating_duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.rate).min(self.burst);
+        bucket.updated = now;
+        bucket.tokens -= 1.0;
+
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            // A tiny rate can put the wait beyond what a `Duration` holds.
+            Duration::try_from_secs_f64(-bucket.tokens / self.rate).unwrap_or(Duration::MAX)
+        }
+    }
+
+    pub fn summary(&self) -> RateLimitSummary {
+        RateLimitSummary {
+            requests: self.requests.load(Ordering::Relaxed),
+            waited: Duration::from_micros(self.waited_micros.load(Ordering::Relaxed)),
+        }
+    }
+}
+
+/// How much a run was slowed down by the rate limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitSummary {
+    pub requests: u64,
+    /// Total time requests spent waiting, summed over all requests; with several rows in flight
+    /// this can exceed the run's wall-clock time.
+    pub waited: Duration,
+}
+
+impl fmt::Display for RateLimitSummary {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Rate limit: {} request(s), {:.1}s spent waiting",
+            self.requests,
+            self.waited.as_secs_f64()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reserve_allows_burst_then_spaces_requests() {
+        let limiter = RateLimiter::new(10.0, 3.0);
+        let start = Instant::now();
+
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::from_millis(100));
+        assert_eq!(limiter.reserve(start), Duration::from_millis(200));
+
+        // Ten seconds later the debt is repaid and the bucket has refilled, but never above burst.
+        let later = start + Duration::from_secs(10);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert_eq!(limiter.reserve(later), Duration::ZERO);
+        assert!(limiter.reserve(later) > Duration::ZERO);
+    }
+
+    #[test]
+    fn test_reserve_saturates_wait_for_tiny_rate() {
+        let config = AppConfig {
+            rate_limit_per_sec: Some(1e-20),
+            ..Default::default()
+        };
+        let limiter = RateLimiter::from_config(&config).unwrap();
+        let start = Instant::now();
+
+        assert_eq!(limiter.reserve(start), Duration::ZERO);
+        assert_eq!(limiter.reserve(start), Duration::MAX);
+    }
+
+    #[test]
+    fn test_from_config_is_off_by_default_and_burst_defaults_to_rate() {
+        assert!(RateLimiter::from_config(&AppConfig::default()).is_none());
+        assert!(
+            RateLimiter::from_config(&AppConfig {
+                rate_limit_per_sec: Some(0.0),
+                ..Default::default()
+            })
+            .is_none()
+        );
+
+        let limiter = RateLimiter::from_config(&AppConfig {
+            rate_limit_per_sec: Some(2.5),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!((limiter.burst - 3.0).abs() < f64::EPSILON);
+
+        let limiter = RateLimiter::from_config(&AppConfig {
+            rate_limit_per_sec: Some(0.5),
+            rate_limit_burst: Some(0),
+            ..Default::default()
+        })
+        .unwrap();
+        assert!((limiter.burst - 1.0).abs() < f64::EPSILON);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_records_requests_and_wait() {
+        let limiter = RateLimiter::new(20.0, 1.0);
+
+        limiter.acquire_async().await;
+        limiter.acquire_async().await;
+        limiter.acquire_async().await;
+
+        let summary = limiter.summary();
+        assert_eq!(summary.requests, 3);
+        assert!(summary.waited >= Duration::from_millis(90));
+    }
+}
@@ -0,0 +1,485 @@
+use clap::ValueEnum;
+use csv::{Writer, WriterBuilder};
+use serde::Serialize;
+use serde_json::{Map, Value};
+use std::fmt::Display;
+use std::io::Write;
+
+use super::{app_errors::AppError, output_template::OutputTemplate, table_output::render_table};
+
+/// Writes records in one of the output formats. CSV, JSON Lines and record output are written as
+/// each record arrives, so a consumer on the other end of a pipe sees them straight away. JSON
+/// and table output need every record before the first can be written and are written by
+/// [`RecordWriter::finish`].
+pub struct RecordWriter<W: Write> {
+    sink: Sink<W>,
+    columns: Option<Vec<String>>,
+    template: Option<OutputTemplate>,
+    existing_csv_header: Option<Vec<String>>,
+    written: usize,
+}
+
+enum Sink<W: Write> {
+    Csv(Box<Writer<W>>),
+    Ndjson(W),
+    Record {
+        out: W,
+    },
+    Json {
+        out: W,
+        records: Vec<Value>,
+    },
+    Table {
+        out: W,
+        records: Vec<Value>,
+        max_width: Option<usize>,
+    },
+}
+
+impl<W: Write> RecordWriter<W> {
+    /// A writer for `output_type` on `out`. `max_width` only applies to table output; see
+    /// [`render_table`].
+    pub fn new(output_type: OutputOptions, out: W, max_width: Option<usize>) -> Self {
+        let sink = match output_type {
+            // The header is written by `write_csv_header`, so it can be checked when appending.
+            OutputOptions::CsvFile => Sink::Csv(Box::new(
+                WriterBuilder::new().has_headers(false).from_writer(out),
+            )),
+            OutputOptions::Ndjson => Sink::Ndjson(out),
+            OutputOptions::Record => Sink::Record { out },
+            OutputOptions::Json => Sink::Json {
+                out,
+                records: Vec::new(),
+            },
+            OutputOptions::Table => Sink::Table {
+                out,
+                records: Vec::new(),
+                max_width,
+            },
+        };
+
+        Self {
+            sink,
+            columns: None,
+            template: None,
+            existing_csv_header: None,
+            written: 0,
+        }
+    }
+
+    /// Writes only these serialized fields of each record, in this order. Record output lists
+    /// them by name in place of the record's own layout.
+    pub fn with_columns(mut self, columns: Vec<String>) -> Self {
+        let mut unique: Vec<String> = Vec::with_capacity(columns.len());
+        for column in columns {
+            if !unique.contains(&column) {
+                unique.push(column);
+            }
+        }
+
+        self.columns = Some(unique);
+        self
+    }
+
+    /// Writes each record as one line rendered from `template` in place of the record's own
+    /// layout. Only record output can be templated.
+    pub fn with_template(mut self, template: OutputTemplate) -> Result<Self, AppError> {
+        if !matches!(self.sink, Sink::Record { .. }) {
+            return Err(AppError::FormatNeedsRecordOutput);
+        }
+
+        self.template = Some(template);
+        Ok(self)
+    }
+
+    /// CSV output is being appended to a file whose header is `header`. Records must have the
+    /// same columns, and the header is not written again.
+    pub fn with_existing_csv_header(mut self, header: Vec<String>) -> Self {
+        self.existing_csv_header = Some(header);
+        self
+    }
+
+    pub fn write<T: Display + Serialize>(&mut self, record: &T) -> color_eyre::Result<()> {
+        match (&self.template, &self.columns) {
+            (Some(_), _) => self.write_templated(record)?,
+            (None, Some(columns)) => {
+                let projected = project(serde_json::to_value(record)?, columns, self.written)?;
+                self.write_projected(projected)?;
+            }
+            (None, None) => self.write_whole(record)?,
+        }
+        self.written += 1;
+
+        Ok(())
+    }
+
+    fn write_whole<T: Display + Serialize>(&mut self, record: &T) -> color_eyre::Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => {
+                if self.written == 0 {
+                    let header = match serde_json::to_value(record)? {
+                        Value::Object(fields) => Some(fields.keys().cloned().collect()),
+                        _ => None,
+                    };
+                    write_csv_header(writer, header, self.existing_csv_header.as_deref())?;
+                }
+                writer.serialize(record)?;
+            }
+            Sink::Ndjson(out) => {
+                serde_json::to_writer(&mut *out, record)?;
+                out.write_all(b"\n")?;
+            }
+            Sink::Record { out } => {
+                write!(out, "---- [{}] ----\n{record}\n\n", self.written)?;
+            }
+            Sink::Json { records, .. } | Sink::Table { records, .. } => {
+                records.push(serde_json::to_value(record)?);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn write_templated<T: Serialize>(&mut self, record: &T) -> color_eyre::Result<()> {
+        let (Some(template), Sink::Record { out }) = (&self.template, &mut self.sink) else {
+            return Err(AppError::FormatNeedsRecordOutput.into());
+        };
+
+        let mut value = serde_json::to_value(record)?;
+        if let Some(columns) = &self.columns {
+            value = Value::Object(project(value, columns, self.written)?);
+        }
+        if self.written == 0
+            && let Value::Object(fields) = &value
+        {
+            template.check_fields(fields)?;
+        }
+
+        writeln!(out, "{}", template.render(&value))?;
+        Ok(())
+    }
+
+    fn write_projected(&mut self, record: Map<String, Value>) -> color_eyre::Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => {
+                if self.written == 0 {
+                    let header = Some(record.keys().cloned().collect());
+                    write_csv_header(writer, header, self.existing_csv_header.as_deref())?;
+                }
+                writer.write_record(record.values().map(csv_field))?;
+            }
+            Sink::Ndjson(out) => {
+                serde_json::to_writer(&mut *out, &record)?;
+                out.write_all(b"\n")?;
+            }
+            Sink::Record { out } => {
+                let width = record.keys().map(|c| c.chars().count()).max().unwrap_or(0);
+                write!(out, "---- [{}] ----\n\n", self.written)?;
+                for (name, value) in &record {
+                    writeln!(out, "{name:<width$} : {}", csv_field(value))?;
+                }
+                write!(out, "\n\n")?;
+            }
+            Sink::Json { records, .. } | Sink::Table { records, .. } => {
+                records.push(Value::Object(record));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes out anything still held back, flushes and hands back the output.
+    pub fn finish(self) -> color_eyre::Result<W> {
+        let mut out = match self.sink {
+            Sink::Csv(writer) => writer.into_inner().map_err(|e| e.into_error())?,
+            Sink::Ndjson(out) | Sink::Record { out } => out,
+            Sink::Json { mut out, records } => {
+                serde_json::to_writer_pretty(&mut out, &records)?;
+                out.write_all(b"\n")?;
+                out
+            }
+            Sink::Table {
+                mut out,
+                records,
+                max_width,
+            } => {
+                out.write_all(render_table(&records, max_width)?.as_bytes())?;
+                out
+            }
+        };
+        out.flush()?;
+
+        Ok(out)
+    }
+}
+
+/// The `columns` of `record`, in that order. The names are checked against the first record, so
+/// a typo fails before anything is written; later records that lack a field get a null.
+fn project(
+    record: Value,
+    columns: &[String],
+    index: usize,
+) -> Result<Map<String, Value>, AppError> {
+    let Value::Object(fields) = record else {
+        return Err(AppError::NoColumns);
+    };
+
+    if index == 0 {
+        let unknown: Vec<&str> = columns
+            .iter()
+            .filter(|column| !fields.contains_key(*column))
+            .map(String::as_str)
+            .collect();
+        if !unknown.is_empty() {
+            return Err(AppError::UnknownColumns {
+                unknown: unknown.join(", "),
+                valid: fields.keys().cloned().collect::<Vec<_>>().join(", "),
+            });
+        }
+    }
+
+    Ok(columns
+        .iter()
+        .map(|column| {
+            let value = fields.get(column).cloned().unwrap_or(Value::Null);
+            (column.clone(), value)
+        })
+        .collect())
+}
+
+/// Writes `header` unless the file being appended to already has it. `header` is `None` for
+/// records without named fields, which have no header row.
+fn write_csv_header<W: Write>(
+    writer: &mut Writer<W>,
+    header: Option<Vec<String>>,
+    existing: Option<&[String]>,
+) -> color_eyre::Result<()> {
+    match (header, existing) {
+        (Some(header), Some(existing)) if header != existing => {
+            Err(AppError::AppendColumnsDiffer {
+                existing: existing.join(","),
+                new: header.join(","),
+            }
+            .into())
+        }
+        (Some(header), None) => Ok(writer.write_record(&header)?),
+        _ => Ok(()),
+    }
+}
+
+/// A value as a CSV field: text as is, nulls empty and anything else as JSON.
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[derive(Debug, ValueEnum, Clone, Copy)]
+pub enum OutputOptions {
+    #[value(alias = "csv")]
+    CsvFile,
+    Json,
+    Ndjson,
+    Record,
+    Table,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fmt;
+
+    #[derive(Serialize)]
+    struct Asset {
+        id: u32,
+        name: String,
+    }
+
+    impl Display for Asset {
+        fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            write!(f, "id: {}\nname: {}", self.id, self.name)
+        }
+    }
+
+    fn render(output_type: OutputOptions) -> String {
+        let mut out = Vec::new();
+        let mut writer = RecordWriter::new(output_type, &mut out, None);
+        for (id, name) in [(1, "rack-1"), (2, "rack, \"two\"")] {
+            writer
+                .write(&Asset {
+                    id,
+                    name: name.to_string(),
+                })
+                .unwrap();
+        }
+        writer.finish().unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_formats() {
+        assert_eq!(
+            render(OutputOptions::CsvFile),
+            "id,name\n1,rack-1\n2,\"rack, \"\"two\"\"\"\n"
+        );
+        assert_eq!(
+            render(OutputOptions::Ndjson),
+            "{\"id\":1,\"name\":\"rack-1\"}\n{\"id\":2,\"name\":\"rack, \\\"two\\\"\"}\n"
+        );
+        assert_eq!(
+            render(OutputOptions::Record),
+            "---- [0] ----\nid: 1\nname: rack-1\n\n---- [1] ----\nid: 2\nname: rack, \"two\"\n\n"
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&render(OutputOptions::Json)).unwrap(),
+            json!([{"id": 1, "name": "rack-1"}, {"id": 2, "name": "rack, \"two\""}])
+        );
+        assert_eq!(
+            render(OutputOptions::Table),
+            "id  name\n 1  rack-1\n 2  rack, \"two\"\n"
+        );
+    }
+
+    fn render_columns(output_type: OutputOptions, columns: &[&str]) -> String {
+        let mut out = Vec::new();
+        let mut writer = RecordWriter::new(output_type, &mut out, None)
+            .with_columns(columns.iter().map(|c| c.to_string()).collect());
+        for record in [
+            json!({"id": 1, "name": "rack-1", "path": "All/DC1", "power": 5000}),
+            json!({"id": 2, "name": "rack-2", "path": "All/DC2"}),
+        ] {
+            writer.write(&record).unwrap();
+        }
+        writer.finish().unwrap();
+
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn test_columns_project_and_order_fields() {
+        let columns = ["path", "id", "power", "path"];
+
+        assert_eq!(
+            render_columns(OutputOptions::CsvFile, &columns),
+            "path,id,power\nAll/DC1,1,5000\nAll/DC2,2,\n"
+        );
+        assert_eq!(
+            render_columns(OutputOptions::Ndjson, &columns),
+            "{\"path\":\"All/DC1\",\"id\":1,\"power\":5000}\n\
+             {\"path\":\"All/DC2\",\"id\":2,\"power\":null}\n"
+        );
+        assert_eq!(
+            render_columns(OutputOptions::Table, &columns),
+            "path     id  power\nAll/DC1   1   5000\nAll/DC2   2\n"
+        );
+        assert_eq!(
+            render_columns(OutputOptions::Record, &["name", "id"]),
+            "---- [0] ----\n\nname : rack-1\nid   : 1\n\n\n---- [1] ----\n\nname : rack-2\nid   : 2\n\n\n"
+        );
+
+        let json: Value =
+            serde_json::from_str(&render_columns(OutputOptions::Json, &["name"])).unwrap();
+        assert_eq!(json, json!([{"name": "rack-1"}, {"name": "rack-2"}]));
+    }
+
+    #[test]
+    fn test_unknown_columns_list_valid_ones_before_writing() {
+        let mut out = Vec::new();
+        let mut writer = RecordWriter::new(OutputOptions::Ndjson, &mut out, None)
+            .with_columns(vec!["id".to_string(), "nmae".to_string()]);
+
+        let err = writer
+            .write(&json!({"id": 1, "name": "rack-1"}))
+            .unwrap_err()
+            .downcast::<AppError>()
+            .unwrap();
+
+        assert_eq!(
+            err.to_string(),
+            "Unknown column(s) nmae. Valid columns are: id, name"
+        );
+        drop(writer);
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn test_existing_csv_header_is_not_repeated_and_must_match() {
+        let header = vec!["id".to_string(), "name".to_string()];
+
+        let mut out = Vec::new();
+        let mut writer = RecordWriter::new(OutputOptions::CsvFile, &mut out, None)
+            .with_existing_csv_header(header.clone());
+        let rack = Asset {
+            id: 3,
+            name: "rack-3".to_string(),
+        };
+        writer.write(&rack).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(out, b"3,rack-3\n");
+
+        let mut out = Vec::new();
+        let mut writer = RecordWriter::new(OutputOptions::CsvFile, &mut out, None)
+            .with_existing_csv_header(header)
+            .with_columns(vec!["name".to_string(), "id".to_string()]);
+        let err = writer
+            .write(&rack)
+            .unwrap_err()
+            .downcast::<AppError>()
+            .unwrap();
+        assert!(matches!(err, AppError::AppendColumnsDiffer { .. }));
+        assert!(err.to_string().contains("id,name"));
+    }
+
+    #[test]
+    fn test_template_replaces_record_layout() {
+        let template = OutputTemplate::parse("{name} ({id})").unwrap();
+
+        let mut out = Vec::new();
+        let mut writer = RecordWriter::new(OutputOptions::Record, &mut out, None)
+            .with_template(template.clone())
+            .unwrap();
+        writer.write(&json!({"id": 1, "name": "rack-1"})).unwrap();
+        writer.write(&json!({"id": 2, "name": "rack-2"})).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(out, b"rack-1 (1)\nrack-2 (2)\n");
+
+        let mut out = Vec::new();
+        let mut writer = RecordWriter::new(OutputOptions::Record, &mut out, None)
+            .with_columns(vec!["id".to_string()])
+            .with_template(template.clone())
+            .unwrap();
+        let err = writer
+            .write(&json!({"id": 1, "name": "rack-1"}))
+            .unwrap_err()
+            .downcast::<AppError>()
+            .unwrap();
+        assert!(matches!(err, AppError::UnknownFormatFields { .. }));
+
+        let err = RecordWriter::new(OutputOptions::Json, Vec::new(), None)
+            .with_template(template)
+            .err()
+            .unwrap();
+        assert!(matches!(err, AppError::FormatNeedsRecordOutput));
+    }
+
+    #[test]
+    fn test_streamed_formats_write_before_finish() {
+        let mut out = Vec::new();
+        {
+            let mut writer = RecordWriter::new(OutputOptions::Ndjson, &mut out, None);
+            writer.write(&json!({"id": 1})).unwrap();
+        }
+        assert_eq!(out, b"{\"id\":1}\n");
+
+        let mut out = Vec::new();
+        {
+            let mut writer = RecordWriter::new(OutputOptions::Table, &mut out, None);
+            writer.write(&json!({"id": 1})).unwrap();
+        }
+        assert!(out.is_empty());
+    }
+}
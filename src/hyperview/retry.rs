@@ -0,0 +1,217 @@
+use chrono::{DateTime, Utc};
+use reqwest::{
+    Response, StatusCode,
+    header::{HeaderMap, RETRY_AFTER},
+};
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+use super::config_data::AppConfig;
+
+/// Attempts per request when the profile sets no `max_attempts`.
+const DEFAULT_MAX_ATTEMPTS: u32 = 4;
+/// Base of the exponential backoff when the profile sets no `retry_base_delay_ms`.
+const DEFAULT_BASE_DELAY: Duration = Duration::from_millis(500);
+/// Longest wait between attempts when the profile sets no `retry_max_delay_secs`.
+const DEFAULT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+/// How often, and after how long, a request that failed for a transient reason is sent again.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first; 1 turns retries off.
+    pub max_attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: DEFAULT_MAX_ATTEMPTS,
+            base_delay: DEFAULT_BASE_DELAY,
+            max_delay: DEFAULT_MAX_DELAY,
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn from_config(config: &AppConfig) -> Self {
+        let default = Self::default();
+
+        Self {
+            max_attempts: config.max_attempts.unwrap_or(default.max_attempts).max(1),
+            base_delay: config
+                .retry_base_delay_ms
+                .map_or(default.base_delay, Duration::from_millis),
+            max_delay: config
+                .retry_max_delay_secs
+                .map_or(default.max_delay, Duration::from_secs),
+        }
+    }
+
+    /// How long to wait before sending attempt `attempt + 1`, or `None` when `attempt` was the
+    /// last one. A `Retry-After` from the server is waited out as given; if it asks for longer
+    /// than `max_delay` the request is not retried. Otherwise the wait is a random ("full
+    /// jitter") share of `base_delay * 2^(attempt - 1)`, capped at `max_delay`, so that many
+    /// clients backing off at once do not retry in lockstep.
+    pub fn delay(&self, attempt: u32, retry_after: Option<Duration>) -> Option<Duration> {
+        if attempt >= self.max_attempts {
+            return None;
+        }
+
+        match retry_after {
+            Some(wait) if wait > self.max_delay => None,
+            Some(wait) => Some(wait),
+            None => {
+                let backoff = self
+                    .base_delay
+                    .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+                    .min(self.max_delay);
+
+                Some(jitter(backoff))
+            }
+        }
+    }
+}
+
+/// Statuses that say "try again later" rather than "this request is wrong".
+pub fn is_retryable_status(status: StatusCode) -> bool {
+    matches!(
+        status,
+        StatusCode::TOO_MANY_REQUESTS
+            | StatusCode::INTERNAL_SERVER_ERROR
+            | StatusCode::BAD_GATEWAY
+            | StatusCode::SERVICE_UNAVAILABLE
+            | StatusCode::GATEWAY_TIMEOUT
+    )
+}
+
+/// Connection failures, resets and timeouts, as opposed to errors building the request or
+/// reading a response that did arrive.
+pub fn is_transient_error(err: &reqwest::Error) -> bool {
+    err.is_connect() || err.is_timeout() || (err.is_request() && !err.is_builder())
+}
+
+/// The wait requested by a `Retry-After` header on a 429 or 503, given either as seconds or as
+/// an HTTP date.
+pub fn retry_after(resp: &Response) -> Option<Duration> {
+    match resp.status() {
+        StatusCode::TOO_MANY_REQUESTS | StatusCode::SERVICE_UNAVAILABLE => {
+            parse_retry_after(resp.headers(), Utc::now())
+        }
+        _ => None,
+    }
+}
+
+fn parse_retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    let at = DateTime::parse_from_rfc2822(value)
+        .ok()?
+        .with_timezone(&Utc);
+
+    Some((at - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// A random duration between zero and `max`.
+fn jitter(max: Duration) -> Duration {
+    let random = RandomState::new().build_hasher().finish();
+
+    max.mul_f64(random as f64 / u64::MAX as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use reqwest::header::HeaderValue;
+
+    fn policy() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: 4,
+            base_delay: Duration::from_millis(100),
+            max_delay: Duration::from_millis(250),
+        }
+    }
+
+    #[test]
+    fn test_delay_backs_off_within_cap_and_stops_at_max_attempts() {
+        let policy = policy();
+
+        for _ in 0..100 {
+            assert!(policy.delay(1, None).unwrap() <= Duration::from_millis(100));
+            assert!(policy.delay(2, None).unwrap() <= Duration::from_millis(200));
+            assert!(policy.delay(3, None).unwrap() <= Duration::from_millis(250));
+        }
+        assert_eq!(policy.delay(4, None), None);
+    }
+
+    #[test]
+    fn test_delay_honors_retry_after_up_to_max_delay() {
+        let policy = policy();
+
+        assert_eq!(
+            policy.delay(1, Some(Duration::from_millis(200))),
+            Some(Duration::from_millis(200))
+        );
+        assert_eq!(policy.delay(1, Some(Duration::from_secs(1))), None);
+    }
+
+    #[test]
+    fn test_from_config_defaults_and_overrides() {
+        assert_eq!(
+            RetryPolicy::from_config(&AppConfig::default()),
+            RetryPolicy::default()
+        );
+
+        let config = AppConfig {
+            max_attempts: Some(0),
+            retry_base_delay_ms: Some(10),
+            retry_max_delay_secs: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            RetryPolicy::from_config(&config),
+            RetryPolicy {
+                max_attempts: 1,
+                base_delay: Duration::from_millis(10),
+                max_delay: Duration::from_secs(2),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_retry_after_seconds_and_http_date() {
+        let now = Utc.with_ymd_and_hms(2024, 5, 1, 12, 0, 0).unwrap();
+        let mut headers = HeaderMap::new();
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("7"));
+        assert_eq!(
+            parse_retry_after(&headers, now),
+            Some(Duration::from_secs(7))
+        );
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 01 May 2024 12:00:30 GMT"),
+        );
+        assert_eq!(
+            parse_retry_after(&headers, now),
+            Some(Duration::from_secs(30))
+        );
+
+        headers.insert(
+            RETRY_AFTER,
+            HeaderValue::from_static("Wed, 01 May 2024 11:59:00 GMT"),
+        );
+        assert_eq!(parse_retry_after(&headers, now), Some(Duration::ZERO));
+
+        headers.insert(RETRY_AFTER, HeaderValue::from_static("soon"));
+        assert_eq!(parse_retry_after(&headers, now), None);
+    }
+}
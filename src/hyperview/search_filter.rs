@@ -0,0 +1,176 @@
+use std::fmt::{self, Display};
+
+use super::filter_expression::FilterExpression;
+
+/// A filter for the asset search API: conditions that must all hold, joined with `AND`.
+///
+/// Values are always written as quoted string literals, with quotes and backslashes escaped, so
+/// user input can never end a literal early or add conditions of its own. Field names are
+/// written as they are when they only contain letters, digits, `_`, `-` and `.`, and quoted the
+/// same way otherwise.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SearchFilter {
+    conditions: Vec<String>,
+}
+
+impl SearchFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `field = value`
+    pub fn equals(&mut self, field: &str, value: impl Display) -> &mut Self {
+        self.push(field, "=", &value.to_string())
+    }
+
+    /// `field CONTAINS value`
+    pub fn contains(&mut self, field: &str, value: impl Display) -> &mut Self {
+        self.push(field, "CONTAINS", &value.to_string())
+    }
+
+    /// `field STARTS WITH value`
+    pub fn starts_with(&mut self, field: &str, value: impl Display) -> &mut Self {
+        self.push(field, "STARTS WITH", &value.to_string())
+    }
+
+    /// `field EXISTS`
+    pub fn exists(&mut self, field: &str) -> &mut Self {
+        self.conditions
+            .push(format!("{} EXISTS", quote_field(field)));
+        self
+    }
+
+    /// `field IN [value, ...]`
+    pub fn any_of<V: AsRef<str>>(&mut self, field: &str, values: &[V]) -> &mut Self {
+        let values: Vec<String> = values.iter().map(|v| quote_value(v.as_ref())).collect();
+        self.conditions
+            .push(format!("{} IN [{}]", quote_field(field), values.join(", ")));
+        self
+    }
+
+    /// A `--filter` expression, in parentheses when it has more than one condition.
+    pub fn expression(&mut self, expression: &FilterExpression) -> &mut Self {
+        let syntax = expression.search_syntax();
+        self.conditions.push(if expression.is_compound() {
+            format!("({syntax})")
+        } else {
+            syntax
+        });
+        self
+    }
+
+    /// An expression in the search API's own syntax, passed on unchecked. It is put in
+    /// parentheses so an `OR` inside it cannot escape the conditions around it.
+    pub fn raw(&mut self, expression: &str) -> &mut Self {
+        self.conditions.push(format!("({expression})"));
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.conditions.is_empty()
+    }
+
+    fn push(&mut self, field: &str, operator: &str, value: &str) -> &mut Self {
+        self.conditions.push(format!(
+            "{} {operator} {}",
+            quote_field(field),
+            quote_value(value)
+        ));
+        self
+    }
+}
+
+impl Display for SearchFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.conditions.join(" AND "))
+    }
+}
+
+/// `value` as a single-quoted string literal.
+pub fn quote_value(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('\'');
+    for c in value.chars() {
+        if matches!(c, '\'' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('\'');
+    quoted
+}
+
+/// `name` as it can stand on the left of a condition: bare when it is a plain name, otherwise
+/// quoted like a value.
+pub fn quote_field(name: &str) -> String {
+    let plain = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.'));
+    if plain {
+        name.to_string()
+    } else {
+        quote_value(name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builds_conditions_joined_with_and() {
+        let mut filter = SearchFilter::new();
+        assert!(filter.is_empty());
+
+        filter
+            .exists("assetProperty_assetTag")
+            .any_of("assetProperty_assetTag", &["A1", "A2"])
+            .equals("assetType", "rack")
+            .starts_with("delimitedPath", "All~DC1")
+            .contains("productName", "PowerEdge");
+
+        assert_eq!(
+            filter.to_string(),
+            "assetProperty_assetTag EXISTS AND assetProperty_assetTag IN ['A1', 'A2'] \
+             AND assetType = 'rack' AND delimitedPath STARTS WITH 'All~DC1' \
+             AND productName CONTAINS 'PowerEdge'"
+        );
+    }
+
+    #[test]
+    fn test_escapes_hostile_values() {
+        let cases = [
+            ("O'Brien", r"'O\'Brien'"),
+            (r#"say "hi""#, r#"'say "hi"'"#),
+            (r"C:\racks\", r"'C:\\racks\\'"),
+            (r"\'", r"'\\\''"),
+            ("x' OR assetType = 'rack", r"'x\' OR assetType = \'rack'"),
+            ("", "''"),
+            ("Ünïcødé 机架", "'Ünïcødé 机架'"),
+        ];
+
+        for (value, expected) in cases {
+            assert_eq!(quote_value(value), expected, "{value}");
+        }
+
+        let mut filter = SearchFilter::new();
+        filter.any_of("serialNumber", &["a'b", r"c\", "d] OR id EXISTS"]);
+        assert_eq!(
+            filter.to_string(),
+            r"serialNumber IN ['a\'b', 'c\\', 'd] OR id EXISTS']"
+        );
+    }
+
+    #[test]
+    fn test_quotes_field_names_only_when_needed() {
+        assert_eq!(quote_field("customProperty_Owner"), "customProperty_Owner");
+        assert_eq!(quote_field("location.name-2"), "location.name-2");
+        assert_eq!(
+            quote_field("customProperty_Cost Center"),
+            "'customProperty_Cost Center'"
+        );
+        assert_eq!(quote_field("x = 'y' OR z"), r"'x = \'y\' OR z'");
+        assert_eq!(quote_field(""), "''");
+    }
+}
@@ -1,6 +1,43 @@
+use chrono::NaiveDate;
 use serde::Serialize;
 use std::fmt;
 
+use super::asset_api_data::AssetSearch;
+
+/// What a sensor report covers: the daily summaries of the sensor named `sensor` on every asset
+/// `assets` finds, for the days from `start` up to but not including `end`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SensorReportQuery {
+    pub assets: AssetSearch,
+    pub sensor: String,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    /// A custom asset property to add to each row, such as a customer or cost center.
+    pub custom_property: Option<String>,
+}
+
+impl SensorReportQuery {
+    pub fn new(
+        assets: AssetSearch,
+        sensor: impl Into<String>,
+        start: NaiveDate,
+        end: NaiveDate,
+    ) -> Self {
+        Self {
+            assets,
+            sensor: sensor.into(),
+            start,
+            end,
+            custom_property: None,
+        }
+    }
+
+    pub fn custom_property(mut self, name: impl Into<String>) -> Self {
+        self.custom_property = Some(name.into());
+        self
+    }
+}
+
 #[derive(Debug, Serialize, Clone)]
 pub struct SensorReportRow {
     pub asset_name: String,
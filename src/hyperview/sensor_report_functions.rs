@@ -1,23 +1,19 @@
-use chrono::NaiveDate;
+use futures::TryStreamExt;
 use log::{debug, error};
-use reqwest::Client;
 use std::collections::HashMap;
+use std::pin::pin;
 use uuid::Uuid;
 
-use crate::retry_on_unauthorized_async;
-
 use super::{
+    api_client::HyperviewClient,
     api_constants::BULK_ACTION_BATCH_SIZE,
-    app_errors::AppError,
-    asset_api_functions::search_assets_async,
+    asset_api_functions::search_asset_pages,
     asset_sensor_api_functions::{
         get_asset_sensor_list_async, get_numeric_sensor_daily_summaries_async,
     },
-    auth::AuthToken,
-    cli_data::{AppConfig, GenerateSensorReportArgs, OutputOptions, SearchAssetsArgs},
     common_types::MultiTypeValue,
     custom_asset_properties_api_functions::get_custom_asset_property_list_async,
-    sensor_report_data::SensorReportRow,
+    sensor_report_data::{SensorReportQuery, SensorReportRow},
 };
 
 fn multi_type_value_to_plain_string(v: &MultiTypeValue) -> String {
@@ -40,113 +36,81 @@ struct AssetContext {
 
 #[allow(clippy::too_many_lines)]
 pub async fn generate_sensor_report_async(
-    config: &AppConfig,
-    req: &Client,
-    auth_token: &mut AuthToken,
-    options: GenerateSensorReportArgs,
+    client: &HyperviewClient,
+    query: &SensorReportQuery,
 ) -> color_eyre::Result<Vec<SensorReportRow>> {
-    let (start, end) = resolve_date_range(&options)?;
+    let (start, end) = (query.start, query.end);
     debug!("Report date range: {start} .. {end}");
 
-    let search_args = SearchAssetsArgs {
-        search_pattern: None,
-        asset_type: Some(options.asset_type.clone()),
-        location_path: options.location_path.clone(),
-        properties: None,
-        custom_properties: None,
-        id: None,
-        manufacturer: options.manufacturer.clone(),
-        product: options.product.clone(),
-        show_property: None,
-        skip: options.skip,
-        limit: options.limit,
-        output_type: OutputOptions::Record,
-        filename: None,
-    };
-
-    auth_token.refresh_if_needed_async(config).await?;
-    let assets = retry_on_unauthorized_async!(
-        config,
-        auth_token,
-        search_assets_async(config, req, &auth_token.header, search_args.clone()).await
-    )?;
-
     let mut contexts: Vec<AssetContext> = Vec::new();
 
-    for asset in assets {
-        auth_token.refresh_if_needed_async(config).await?;
+    // Look up each page's sensors as it arrives rather than after the whole search.
+    let mut pages = pin!(search_asset_pages(client, &query.assets));
+    while let Some(page) = pages.try_next().await? {
+        for asset in page.assets {
+            let sensors = match get_asset_sensor_list_async(client, asset.id).await {
+                Ok(s) => s,
+                Err(e) => {
+                    error!("Failed to fetch sensors for asset {}: {e}", asset.id);
+                    continue;
+                }
+            };
 
-        let sensors = match retry_on_unauthorized_async!(
-            config,
-            auth_token,
-            get_asset_sensor_list_async(config, req, &auth_token.header, asset.id).await
-        ) {
-            Ok(s) => s,
-            Err(e) => {
-                error!("Failed to fetch sensors for asset {}: {e}", asset.id);
+            let Some(sensor) = sensors.into_iter().find(|s| s.name == query.sensor) else {
+                error!(
+                    "Sensor {:?} not found on asset {}. Skipping.",
+                    query.sensor, asset.id
+                );
                 continue;
-            }
-        };
+            };
 
-        let Some(sensor) = sensors.into_iter().find(|s| s.name == options.sensor) else {
-            error!(
-                "Sensor {:?} not found on asset {}. Skipping.",
-                options.sensor, asset.id
-            );
-            continue;
-        };
-
-        if !sensor.is_numeric {
-            error!(
-                "Sensor {:?} on asset {} is not numeric. Skipping.",
-                options.sensor, asset.id
-            );
-            continue;
-        }
-
-        let sensor_id = match Uuid::parse_str(&sensor.id) {
-            Ok(u) => u,
-            Err(e) => {
+            if !sensor.is_numeric {
                 error!(
-                    "Failed to parse sensor id {:?} on asset {}: {e}. Skipping.",
-                    sensor.id, asset.id
+                    "Sensor {:?} on asset {} is not numeric. Skipping.",
+                    query.sensor, asset.id
                 );
                 continue;
             }
-        };
 
-        let custom_property = if let Some(name) = options.custom_property.as_ref() {
-            match retry_on_unauthorized_async!(
-                config,
-                auth_token,
-                get_custom_asset_property_list_async(config, req, &auth_token.header, asset.id)
-                    .await
-            ) {
-                Ok(props) => props
-                    .into_iter()
-                    .find(|p| p.name.eq_ignore_ascii_case(name))
-                    .map(|p| multi_type_value_to_plain_string(&p.value))
-                    .unwrap_or_default(),
+            let sensor_id = match Uuid::parse_str(&sensor.id) {
+                Ok(u) => u,
                 Err(e) => {
                     error!(
-                        "Failed to fetch custom properties for asset {}: {e}. Continuing without enrichment.",
-                        asset.id
+                        "Failed to parse sensor id {:?} on asset {}: {e}. Skipping.",
+                        sensor.id, asset.id
                     );
-                    String::new()
+                    continue;
                 }
-            }
-        } else {
-            String::new()
-        };
-
-        contexts.push(AssetContext {
-            asset_name: asset.name.clone(),
-            asset_id: asset.id,
-            custom_property,
-            sensor_id,
-            sensor_name: sensor.name,
-            sensor_unit: sensor.unit_string,
-        });
+            };
+
+            let custom_property = if let Some(name) = query.custom_property.as_ref() {
+                match get_custom_asset_property_list_async(client, asset.id).await {
+                    Ok(props) => props
+                        .into_iter()
+                        .find(|p| p.name.eq_ignore_ascii_case(name))
+                        .map(|p| multi_type_value_to_plain_string(&p.value))
+                        .unwrap_or_default(),
+                    Err(e) => {
+                        error!(
+                            "Failed to fetch custom properties for asset {}: {e}. Continuing without enrichment.",
+                            asset.id
+                        );
+                        String::new()
+                    }
+                }
+            } else {
+                String::new()
+            };
+
+            contexts.push(AssetContext {
+                asset_name: asset.name.clone(),
+                asset_id: asset.id,
+                custom_property,
+                sensor_id,
+                sensor_name: sensor.name,
+                sensor_unit: sensor.unit_string,
+            });
+        }
     }
 
     // Key by `Uuid` (not String) so uppercase/braced UUIDs from the server still match the
@@ -162,21 +126,7 @@ pub async fn generate_sensor_report_async(
         .collect::<Vec<Uuid>>()
         .chunks(BULK_ACTION_BATCH_SIZE)
     {
-        auth_token.refresh_if_needed_async(config).await?;
-
-        match retry_on_unauthorized_async!(
-            config,
-            auth_token,
-            get_numeric_sensor_daily_summaries_async(
-                config,
-                req,
-                &auth_token.header,
-                chunk,
-                start,
-                end,
-            )
-            .await
-        ) {
+        match get_numeric_sensor_daily_summaries_async(client, chunk, start, end).await {
             Ok(summaries) => {
                 for summary in summaries {
                     match Uuid::parse_str(&summary.sensor_id) {
@@ -230,44 +180,6 @@ pub async fn generate_sensor_report_async(
     Ok(rows)
 }
 
-fn resolve_date_range(
-    options: &GenerateSensorReportArgs,
-) -> color_eyre::Result<(NaiveDate, NaiveDate)> {
-    let has_ym = options.year.is_some() || options.month.is_some();
-    let has_range = options.start.is_some() || options.end.is_some();
-
-    if has_ym && has_range {
-        return Err(AppError::InvalidDateRangeArgs.into());
-    }
-
-    if has_range {
-        let start_str = options
-            .start
-            .as_ref()
-            .ok_or(AppError::InvalidDateRangeArgs)?;
-        let end_str = options.end.as_ref().ok_or(AppError::InvalidDateRangeArgs)?;
-        let start = NaiveDate::parse_from_str(start_str, "%Y-%m-%d")
-            .map_err(|_| AppError::InvalidDateFormat(start_str.clone()))?;
-        let end = NaiveDate::parse_from_str(end_str, "%Y-%m-%d")
-            .map_err(|_| AppError::InvalidDateFormat(end_str.clone()))?;
-        return Ok((start, end));
-    }
-
-    let year = options.year.ok_or(AppError::InvalidDateRangeArgs)?;
-    let month = options.month.ok_or(AppError::InvalidDateRangeArgs)?;
-
-    let start = NaiveDate::from_ymd_opt(year, month, 1)
-        .ok_or_else(|| AppError::InvalidDateFormat(format!("{year}-{month:02}-01")))?;
-    let (ny, nm) = if month == 12 {
-        (year + 1, 1)
-    } else {
-        (year, month + 1)
-    };
-    let end = NaiveDate::from_ymd_opt(ny, nm, 1)
-        .ok_or_else(|| AppError::InvalidDateFormat(format!("{ny}-{nm:02}-01")))?;
-    Ok((start, end))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -275,35 +187,24 @@ mod tests {
         ASSET_ASSETS_API_PREFIX, ASSET_SEARCH_API_PREFIX, CUSTOM_ASSET_PROPERTIES_API_PREFIX,
         SENSOR_API_PREFIX, SENSOR_DAILY_SUMMARIES_NUMERIC_API_PREFIX,
     };
-    use crate::hyperview::cli_data::AssetTypes;
+    use crate::hyperview::asset_api_data::AssetSearch;
+    use crate::hyperview::http_replay::HttpReplay;
+    use crate::hyperview::{asset_api_data::AssetTypes, config_data::AppConfig};
+    use chrono::NaiveDate;
     use httpmock::prelude::*;
     use httpmock::{Mock, MockServer};
     use serde_json::{Value, json};
-    use std::time::Duration;
 
     const ALL_LOCATION_ID: &str = "11223344-5566-7788-99aa-bbccddeeff00";
 
-    fn base_args(asset_type: AssetTypes, sensor: &str) -> GenerateSensorReportArgs {
-        GenerateSensorReportArgs {
-            asset_type,
-            sensor: sensor.to_string(),
-            year: Some(2026),
-            month: Some(2),
-            start: None,
-            end: None,
-            custom_property: None,
-            location_path: None,
-            manufacturer: None,
-            product: None,
-            skip: 0,
-            limit: 100,
-            output_type: OutputOptions::Record,
-            filename: None,
-        }
-    }
-
-    fn auth_token() -> AuthToken {
-        AuthToken::for_test("Bearer test_token", Duration::from_hours(1))
+    /// The February 2026 report for `sensor` on every asset of `asset_type`.
+    fn base_query(asset_type: AssetTypes, sensor: &str) -> SensorReportQuery {
+        SensorReportQuery::new(
+            AssetSearch::new().asset_type(asset_type),
+            sensor,
+            NaiveDate::from_ymd_opt(2026, 2, 1).unwrap(),
+            NaiveDate::from_ymd_opt(2026, 3, 1).unwrap(),
+        )
     }
 
     fn asset_hit(asset_id: Uuid, name: &str) -> Value {
@@ -374,74 +275,6 @@ mod tests {
         })
     }
 
-    #[test]
-    fn test_resolve_date_range_year_month_happy_path() {
-        let mut args = base_args(AssetTypes::Rack, "s");
-        args.year = Some(2026);
-        args.month = Some(2);
-        let (start, end) = resolve_date_range(&args).unwrap();
-        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 2, 1).unwrap());
-        assert_eq!(end, NaiveDate::from_ymd_opt(2026, 3, 1).unwrap());
-    }
-
-    #[test]
-    fn test_resolve_date_range_december_rollover() {
-        let mut args = base_args(AssetTypes::Rack, "s");
-        args.year = Some(2026);
-        args.month = Some(12);
-        let (start, end) = resolve_date_range(&args).unwrap();
-        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 12, 1).unwrap());
-        assert_eq!(end, NaiveDate::from_ymd_opt(2027, 1, 1).unwrap());
-    }
-
-    #[test]
-    fn test_resolve_date_range_start_end_overrides() {
-        let mut args = base_args(AssetTypes::Rack, "s");
-        args.year = None;
-        args.month = None;
-        args.start = Some("2026-02-15".to_string());
-        args.end = Some("2026-02-20".to_string());
-        let (start, end) = resolve_date_range(&args).unwrap();
-        assert_eq!(start, NaiveDate::from_ymd_opt(2026, 2, 15).unwrap());
-        assert_eq!(end, NaiveDate::from_ymd_opt(2026, 2, 20).unwrap());
-    }
-
-    #[test]
-    fn test_resolve_date_range_rejects_mixing_ym_and_range() {
-        let mut args = base_args(AssetTypes::Rack, "s");
-        args.start = Some("2026-02-15".to_string());
-        args.end = Some("2026-02-20".to_string());
-        // year/month still set from base_args
-        assert!(resolve_date_range(&args).is_err());
-    }
-
-    #[test]
-    fn test_resolve_date_range_rejects_when_nothing_supplied() {
-        let mut args = base_args(AssetTypes::Rack, "s");
-        args.year = None;
-        args.month = None;
-        assert!(resolve_date_range(&args).is_err());
-    }
-
-    #[test]
-    fn test_resolve_date_range_rejects_start_without_end() {
-        let mut args = base_args(AssetTypes::Rack, "s");
-        args.year = None;
-        args.month = None;
-        args.start = Some("2026-02-15".to_string());
-        assert!(resolve_date_range(&args).is_err());
-    }
-
-    #[test]
-    fn test_resolve_date_range_rejects_bad_date_format() {
-        let mut args = base_args(AssetTypes::Rack, "s");
-        args.year = None;
-        args.month = None;
-        args.start = Some("not-a-date".to_string());
-        args.end = Some("2026-02-20".to_string());
-        assert!(resolve_date_range(&args).is_err());
-    }
-
     #[tokio::test]
     async fn test_generate_sensor_report_happy_path_single_asset() {
         let asset_id = Uuid::new_v4();
@@ -486,14 +319,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
         let rows = generate_sensor_report_async(
-            &config,
             &client,
-            &mut token,
-            base_args(AssetTypes::Rack, "averageKwhByHour"),
+            &base_query(AssetTypes::Rack, "averageKwhByHour"),
         )
         .await
         .unwrap();
@@ -512,6 +342,82 @@ mod tests {
         assert!((rows[1].avg - 3.0).abs() < f64::EPSILON);
     }
 
+    #[tokio::test]
+    async fn test_generate_sensor_report_replays_captured_run_offline() {
+        let asset_id = Uuid::new_v4();
+        let sensor_id = Uuid::new_v4();
+        let dir = tempfile::tempdir().unwrap();
+        let capture_path = dir.path().join("capture.jsonl");
+
+        let captured_rows = {
+            let server = MockServer::start();
+            mock_search(&server, &[asset_hit(asset_id, "Rack-42")]);
+            server.mock(|when, then| {
+                when.method(GET)
+                    .path(format!("{SENSOR_API_PREFIX}/{asset_id}"));
+                then.status(200)
+                    .header("Content-Type", "application/json")
+                    .json_body(json!([sensor_body(
+                        &sensor_id.to_string(),
+                        asset_id,
+                        "averageKwhByHour",
+                        true
+                    )]));
+            });
+            server.mock(|when, then| {
+                when.method(GET).path(SENSOR_DAILY_SUMMARIES_NUMERIC_API_PREFIX);
+                then.status(200)
+                    .header("Content-Type", "application/json")
+                    .json_body(json!([{
+                        "sensorId": sensor_id.to_string(),
+                        "sensorTypeDescription": "Power",
+                        "sensorTypeId": "type-1",
+                        "name": "averageKwhByHour",
+                        "sensorDataPoints": [
+                            { "r": "2026-02-01T00:00:00.000", "avg": 1.0, "max": 2.0, "min": 0.5, "lst": 1.5 }
+                        ]
+                    }]));
+            });
+
+            let client = HyperviewClient::for_test(AppConfig {
+                instance_url: format!("http://{}", server.address()),
+                ..Default::default()
+            })
+            .with_capture(&capture_path);
+
+            generate_sensor_report_async(&client, &base_query(AssetTypes::Rack, "averageKwhByHour"))
+                .await
+                .unwrap()
+        };
+
+        // The mock server is gone; every response now comes from the capture.
+        let client = HyperviewClient::for_replay(HttpReplay::load(&capture_path).unwrap());
+        let replayed_rows = generate_sensor_report_async(
+            &client,
+            &base_query(AssetTypes::Rack, "averageKwhByHour"),
+        )
+        .await
+        .unwrap();
+
+        assert_eq!(replayed_rows.len(), 1);
+        assert_eq!(
+            serde_json::to_value(&replayed_rows).unwrap(),
+            serde_json::to_value(&captured_rows).unwrap()
+        );
+
+        // March was never captured: its summaries request fails instead of going online, and the
+        // report skips that batch as it would after a network error.
+        let mut query = base_query(AssetTypes::Rack, "averageKwhByHour");
+        query.start = NaiveDate::from_ymd_opt(2026, 3, 1).unwrap();
+        query.end = NaiveDate::from_ymd_opt(2026, 4, 1).unwrap();
+        assert!(
+            generate_sensor_report_async(&client, &query)
+                .await
+                .unwrap()
+                .is_empty()
+        );
+    }
+
     #[tokio::test]
     async fn test_generate_sensor_report_skips_non_numeric_sensor() {
         let asset_id = Uuid::new_v4();
@@ -544,14 +450,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
         let rows = generate_sensor_report_async(
-            &config,
             &client,
-            &mut token,
-            base_args(AssetTypes::Rack, "averageKwhByHour"),
+            &base_query(AssetTypes::Rack, "averageKwhByHour"),
         )
         .await
         .unwrap();
@@ -592,14 +495,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
         let rows = generate_sensor_report_async(
-            &config,
             &client,
-            &mut token,
-            base_args(AssetTypes::Rack, "averageKwhByHour"),
+            &base_query(AssetTypes::Rack, "averageKwhByHour"),
         )
         .await
         .unwrap();
@@ -659,14 +559,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
         let rows = generate_sensor_report_async(
-            &config,
             &client,
-            &mut token,
-            base_args(AssetTypes::Rack, "averageKwhByHour"),
+            &base_query(AssetTypes::Rack, "averageKwhByHour"),
         )
         .await
         .expect("bulk report must not abort on a per-asset error");
@@ -723,15 +620,13 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
-        let mut args = base_args(AssetTypes::Rack, "averageKwhByHour");
-        args.custom_property = Some("business unit".to_string()); // lowercase to prove case-insensitive
+        // Lowercase to prove the lookup is case-insensitive.
+        let query =
+            base_query(AssetTypes::Rack, "averageKwhByHour").custom_property("business unit");
 
-        let rows = generate_sensor_report_async(&config, &client, &mut token, args)
-            .await
-            .unwrap();
+        let rows = generate_sensor_report_async(&client, &query).await.unwrap();
 
         assert_eq!(rows.len(), 1);
         assert_eq!(rows[0].custom_property, "Engineering");
@@ -781,13 +676,12 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
-        let mut args = base_args(AssetTypes::Rack, "averageKwhByHour");
-        args.custom_property = Some("Business Unit".to_string());
+        let query =
+            base_query(AssetTypes::Rack, "averageKwhByHour").custom_property("Business Unit");
 
-        let rows = generate_sensor_report_async(&config, &client, &mut token, args)
+        let rows = generate_sensor_report_async(&client, &query)
             .await
             .expect("custom property fetch failure must not abort the run");
 
@@ -841,14 +735,11 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
         let rows = generate_sensor_report_async(
-            &config,
             &client,
-            &mut token,
-            base_args(AssetTypes::Rack, "averageKwhByHour"),
+            &base_query(AssetTypes::Rack, "averageKwhByHour"),
         )
         .await
         .unwrap();
@@ -919,19 +810,18 @@ mod tests {
             instance_url: format!("http://{}", server.address()),
             ..Default::default()
         };
-        let client = Client::new();
-        let mut token = auth_token();
+        let client = HyperviewClient::for_test(config);
 
         let rows = generate_sensor_report_async(
-            &config,
             &client,
-            &mut token,
-            base_args(AssetTypes::Rack, "averageKwhByHour"),
+            &base_query(AssetTypes::Rack, "averageKwhByHour"),
         )
         .await
         .expect("orchestrator must not abort on a per-batch error");
 
-        batch1_mock.assert();
+        // The 500 is retried up to the default max_attempts before the batch is given up.
+
+        batch1_mock.assert_calls(4);
         batch2_mock.assert();
         assert_eq!(rows.len(), 1);
         assert!((rows[0].avg - 42.0).abs() < f64::EPSILON);
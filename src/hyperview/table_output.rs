@@ -0,0 +1,266 @@
+use serde::Serialize;
+use serde_json::Value;
+
+/// Space between columns.
+const COLUMN_GAP: &str = "  ";
+
+/// Columns are not shrunk below this many characters to fit the terminal.
+const MIN_COLUMN_WIDTH: usize = 6;
+
+const ELLIPSIS: char = '…';
+
+/// Renders `rows` as aligned columns, one per serialized field, in field order. Fields missing
+/// from a row are left blank, nested values are shown as compact JSON and numeric columns are
+/// right-aligned.
+///
+/// With a `max_width`, the widest columns are shrunk until the table fits and the values that no
+/// longer fit are truncated. Paths keep their last segments, as that is where assets differ.
+/// Without one, every value is shown in full.
+pub fn render_table<T: Serialize>(
+    rows: &[T],
+    max_width: Option<usize>,
+) -> serde_json::Result<String> {
+    let values = rows
+        .iter()
+        .map(serde_json::to_value)
+        .collect::<serde_json::Result<Vec<_>>>()?;
+
+    let mut headers: Vec<String> = Vec::new();
+    for value in &values {
+        match value {
+            Value::Object(map) => {
+                for key in map.keys() {
+                    if !headers.contains(key) {
+                        headers.push(key.clone());
+                    }
+                }
+            }
+            _ if headers.is_empty() => headers.push("value".to_string()),
+            _ => {}
+        }
+    }
+    if headers.is_empty() {
+        return Ok(String::new());
+    }
+
+    let cells: Vec<Vec<Option<&Value>>> = values
+        .iter()
+        .map(|value| {
+            headers
+                .iter()
+                .map(|header| match value {
+                    Value::Object(map) => map.get(header),
+                    other => Some(other),
+                })
+                .collect()
+        })
+        .collect();
+    let numeric: Vec<bool> = (0..headers.len())
+        .map(|column| {
+            let mut present = cells.iter().filter_map(|row| row[column]).peekable();
+            present.peek().is_some() && present.all(|v| v.is_number() || v.is_null())
+        })
+        .collect();
+    let text: Vec<Vec<String>> = cells
+        .iter()
+        .map(|row| row.iter().map(|cell| cell_text(*cell)).collect())
+        .collect();
+
+    let natural: Vec<usize> = headers
+        .iter()
+        .enumerate()
+        .map(|(column, header)| {
+            text.iter()
+                .map(|row| row[column].chars().count())
+                .chain([header.chars().count()])
+                .max()
+                .unwrap_or_default()
+        })
+        .collect();
+    let widths = match max_width {
+        Some(max_width) => fit_widths(&natural, max_width),
+        None => natural,
+    };
+
+    let mut out = String::new();
+    push_line(&mut out, &headers, &widths, &vec![false; headers.len()]);
+    for row in &text {
+        push_line(&mut out, row, &widths, &numeric);
+    }
+
+    Ok(out)
+}
+
+/// The width to fit tables to: the terminal's when standard output is one, else `$COLUMNS` if
+/// set. `None` when output goes to a pipe or file, so nothing is truncated there.
+pub fn terminal_width() -> Option<usize> {
+    if let Some((terminal_size::Width(width), _)) = terminal_size::terminal_size() {
+        return Some(width.into());
+    }
+
+    std::env::var("COLUMNS").ok()?.trim().parse().ok()
+}
+
+fn cell_text(value: Option<&Value>) -> String {
+    let text = match value {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(s)) => s.clone(),
+        Some(other) => other.to_string(),
+    };
+
+    text.chars()
+        .map(|c| if c.is_control() { ' ' } else { c })
+        .collect()
+}
+
+/// Shrinks the widest columns one character at a time until the table fits in `max_width`, or
+/// every column is down to [`MIN_COLUMN_WIDTH`].
+fn fit_widths(natural: &[usize], max_width: usize) -> Vec<usize> {
+    let mut widths = natural.to_vec();
+    let gaps = COLUMN_GAP.len() * widths.len().saturating_sub(1);
+    let budget = max_width.saturating_sub(gaps);
+
+    while widths.iter().sum::<usize>() > budget {
+        let Some(widest) = widths
+            .iter_mut()
+            .filter(|w| **w > MIN_COLUMN_WIDTH)
+            .max_by_key(|w| **w)
+        else {
+            break;
+        };
+        *widest -= 1;
+    }
+
+    widths
+}
+
+fn push_line(out: &mut String, cells: &[String], widths: &[usize], right_align: &[bool]) {
+    let line = cells
+        .iter()
+        .zip(widths)
+        .zip(right_align)
+        .map(|((cell, &width), &right)| {
+            let cell = truncate(cell, width);
+            if right {
+                format!("{cell:>width$}")
+            } else {
+                format!("{cell:<width$}")
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(COLUMN_GAP);
+
+    out.push_str(line.trim_end());
+    out.push('\n');
+}
+
+/// `text` cut to `width` characters with an ellipsis marking the cut. Paths are cut at the
+/// front, on a segment boundary where one fits, and everything else at the end.
+fn truncate(text: &str, width: usize) -> String {
+    let length = text.chars().count();
+    if length <= width {
+        return text.to_string();
+    }
+    if width == 0 {
+        return String::new();
+    }
+
+    if text.contains('/') {
+        let tail: String = text.chars().skip(length - (width - 1)).collect();
+        let tail = match tail.find('/') {
+            Some(slash) => &tail[slash..],
+            None => &tail,
+        };
+        format!("{ELLIPSIS}{tail}")
+    } else {
+        let head: String = text.chars().take(width - 1).collect();
+        format!("{head}{ELLIPSIS}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[derive(Serialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Row {
+        name: String,
+        asset_type: String,
+        path: String,
+        power: Option<u32>,
+    }
+
+    fn rows() -> Vec<Row> {
+        vec![
+            Row {
+                name: "rack-1".to_string(),
+                asset_type: "rack".to_string(),
+                path: "All/Building A/Floor 1/Room 101".to_string(),
+                power: Some(5000),
+            },
+            Row {
+                name: "pdu-10".to_string(),
+                asset_type: "rackPdu".to_string(),
+                path: "All/Building B".to_string(),
+                power: None,
+            },
+        ]
+    }
+
+    #[test]
+    fn test_render_table_aligns_fields_in_order() {
+        let table = render_table(&rows(), None).unwrap();
+
+        assert_eq!(
+            table,
+            "\
+name    assetType  path                             power
+rack-1  rack       All/Building A/Floor 1/Room 101   5000
+pdu-10  rackPdu    All/Building B
+"
+        );
+    }
+
+    #[test]
+    fn test_render_table_fits_width_by_truncating_widest_column() {
+        let table = render_table(&rows(), Some(40)).unwrap();
+
+        assert_eq!(
+            table,
+            "\
+name    assetType  path            power
+rack-1  rack       …/Room 101       5000
+pdu-10  rackPdu    All/Building B
+"
+        );
+        assert!(table.lines().all(|line| line.chars().count() <= 40));
+    }
+
+    #[test]
+    fn test_render_table_handles_mixed_and_scalar_rows() {
+        let mixed = vec![
+            json!({"id": 1, "tags": ["a", "b"]}),
+            json!({"id": 2, "note": "line one\nline two"}),
+        ];
+        assert_eq!(
+            render_table(&mixed, None).unwrap(),
+            "\
+id  tags       note
+ 1  [\"a\",\"b\"]
+ 2             line one line two
+"
+        );
+
+        assert_eq!(render_table(&["x", "y"], None).unwrap(), "value\nx\ny\n");
+        assert_eq!(render_table::<Value>(&[], None).unwrap(), "");
+    }
+
+    #[test]
+    fn test_truncate_keeps_path_tail_and_text_head() {
+        assert_eq!(truncate("All/DC1/Room 4", 8), "…/Room 4");
+        assert_eq!(truncate("PowerEdge R740", 8), "PowerEd…");
+        assert_eq!(truncate("short", 8), "short");
+    }
+}
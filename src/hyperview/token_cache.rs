@@ -0,0 +1,372 @@
+use chrono::{DateTime, Utc};
+use log::{debug, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tempfile::NamedTempFile;
+
+use super::{
+    auth::IssuedToken,
+    config_data::{AppConfig, TOKEN_CACHE_FILENAME},
+};
+
+/// An access token as stored on disk, with the absolute time at which the server said it expires.
+/// Entries written by `hvcli login` also hold the refresh token of that login session.
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CachedToken {
+    pub access_token: String,
+    pub expires_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+impl CachedToken {
+    /// Time left before expiry; zero once expired.
+    pub fn remaining(&self) -> Duration {
+        (self.expires_at - Utc::now()).to_std().unwrap_or_default()
+    }
+}
+
+/// One entry of the shared token cache file, which maps `<profile>|<token_url>` to a token so
+/// invocations against different profiles or identity servers never share a token.
+///
+/// The cache is best effort: unreadable or unwritable files are logged and treated as a cache miss
+/// so a run never fails because of it.
+#[derive(Debug, Clone)]
+pub struct TokenCache {
+    path: PathBuf,
+    key: String,
+    /// Whether client credentials tokens are kept (`token_cache = true`). Login sessions always
+    /// are; storing them is the point of `hvcli login`.
+    pub reuse_access_tokens: bool,
+}
+
+impl TokenCache {
+    pub fn new(dir: &Path, profile: &str, token_url: &str) -> Self {
+        Self {
+            path: dir.join(TOKEN_CACHE_FILENAME),
+            key: format!("{profile}|{token_url}"),
+            reuse_access_tokens: true,
+        }
+    }
+
+    /// The cache for a run using `config`, or `None` if there is no config directory to keep it
+    /// in. Client credentials tokens are only reused if the profile opted in with
+    /// `token_cache = true`.
+    pub fn for_config(config_path: Option<&Path>, config: &AppConfig) -> Option<Self> {
+        match config_path.and_then(Path::parent) {
+            Some(dir) => Some(Self {
+                reuse_access_tokens: config.token_cache,
+                ..Self::new(dir, &config.profile, &config.token_url)
+            }),
+            None => {
+                if config.token_cache {
+                    warn!("token_cache is enabled but there is no config directory; not caching");
+                }
+                None
+            }
+        }
+    }
+
+    pub fn load(&self) -> Option<CachedToken> {
+        read_entries(&self.path)
+            .remove(&self.key)
+            .filter(|token| self.reuse_access_tokens || token.refresh_token.is_some())
+    }
+
+    pub fn store(&self, token: &IssuedToken) {
+        let Some(expires_at) = chrono::Duration::from_std(token.lifetime)
+            .ok()
+            .and_then(|lifetime| Utc::now().checked_add_signed(lifetime))
+        else {
+            warn!(
+                "Not caching a token with an out-of-range lifetime of {:?}",
+                token.lifetime
+            );
+            return;
+        };
+        let token = CachedToken {
+            access_token: token.access_token.clone(),
+            expires_at,
+            refresh_token: token.refresh_token.clone(),
+        };
+
+        self.update(|entries| {
+            entries.insert(self.key.clone(), token);
+            true
+        });
+    }
+
+    /// Marks this entry's access token as unusable, e.g. after the server rejected it. The
+    /// refresh token of a login session is kept so the next fetch can use it.
+    pub fn invalidate(&self) {
+        self.update(|entries| match entries.get_mut(&self.key) {
+            Some(token) if token.refresh_token.is_some() => {
+                token.expires_at = DateTime::UNIX_EPOCH;
+                true
+            }
+            _ => entries.remove(&self.key).is_some(),
+        });
+    }
+
+    /// Drops this entry entirely, login session included.
+    pub fn remove(&self) {
+        self.update(|entries| entries.remove(&self.key).is_some());
+    }
+
+    /// Applies `change` to the stored entries, writing them back only if it reports a change.
+    fn update(&self, change: impl FnOnce(&mut BTreeMap<String, CachedToken>) -> bool) {
+        let mut entries = read_entries(&self.path);
+        if !change(&mut entries) {
+            return;
+        }
+
+        if let Err(e) = write_entries(&self.path, &entries) {
+            warn!("Could not update token cache {}: {e}", self.path.display());
+        }
+    }
+}
+
+/// Removes cached tokens for `profile` (any token URL), or every cached token if `profile` is
+/// `None`. Returns how many entries were removed.
+pub fn clear_token_cache(dir: &Path, profile: Option<&str>) -> color_eyre::Result<usize> {
+    let path = dir.join(TOKEN_CACHE_FILENAME);
+    if !path.exists() {
+        return Ok(0);
+    }
+
+    let mut entries = read_entries(&path);
+    let before = entries.len();
+
+    match profile {
+        Some(name) => {
+            let prefix = format!("{name}|");
+            entries.retain(|key, _| !key.starts_with(&prefix));
+        }
+        None => entries.clear(),
+    }
+
+    if entries.is_empty() {
+        fs::remove_file(&path)?;
+    } else {
+        write_entries(&path, &entries)?;
+    }
+
+    Ok(before - entries.len())
+}
+
+fn read_entries(path: &Path) -> BTreeMap<String, CachedToken> {
+    let contents = match fs::read_to_string(path) {
+        Ok(c) => c,
+        Err(e) => {
+            debug!("No usable token cache at {}: {e}", path.display());
+            return BTreeMap::new();
+        }
+    };
+
+    serde_json::from_str(&contents).unwrap_or_else(|e| {
+        warn!("Ignoring corrupt token cache {}: {e}", path.display());
+        BTreeMap::new()
+    })
+}
+
+/// Writes to a private temporary file and renames it into place, so a concurrent invocation
+/// never reads a half-written cache. Each write gets its own temporary file, so concurrent
+/// refreshes can't interleave their contents.
+fn write_entries(path: &Path, entries: &BTreeMap<String, CachedToken>) -> color_eyre::Result<()> {
+    let dir = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    fs::create_dir_all(dir)?;
+
+    // Temporary files are created owner-only (0600), which is what the cache needs.
+    let mut temp = NamedTempFile::new_in(dir)?;
+    temp.write_all(serde_json::to_string_pretty(entries)?.as_bytes())?;
+    temp.as_file().sync_all()?;
+    temp.persist(path)?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn issued(access_token: &str, lifetime_secs: u64) -> IssuedToken {
+        IssuedToken {
+            access_token: access_token.to_string(),
+            lifetime: Duration::from_secs(lifetime_secs),
+            refresh_token: None,
+        }
+    }
+
+    #[test]
+    fn test_store_and_load_are_keyed_by_profile_and_token_url() {
+        let dir = TempDir::new().unwrap();
+        let prod = TokenCache::new(dir.path(), "prod", "https://prod.test/connect/token");
+        let staging = TokenCache::new(dir.path(), "staging", "https://prod.test/connect/token");
+        let other_idp = TokenCache::new(dir.path(), "prod", "https://other.test/connect/token");
+
+        prod.store(&issued("prod_token", 3600));
+
+        let cached = prod.load().unwrap();
+        assert_eq!(cached.access_token, "prod_token");
+        assert!(cached.remaining() > Duration::from_secs(3500));
+        assert!(staging.load().is_none());
+        assert!(other_idp.load().is_none());
+    }
+
+    #[test]
+    fn test_invalidate_removes_only_own_entry() {
+        let dir = TempDir::new().unwrap();
+        let prod = TokenCache::new(dir.path(), "prod", "https://example.test/token");
+        let staging = TokenCache::new(dir.path(), "staging", "https://example.test/token");
+
+        prod.store(&issued("prod_token", 3600));
+        staging.store(&issued("staging_token", 3600));
+        prod.invalidate();
+
+        assert!(prod.load().is_none());
+        assert_eq!(staging.load().unwrap().access_token, "staging_token");
+    }
+
+    #[test]
+    fn test_expired_token_has_no_remaining_lifetime() {
+        let token = CachedToken {
+            access_token: "old".to_string(),
+            expires_at: Utc::now() - chrono::Duration::seconds(10),
+            refresh_token: None,
+        };
+
+        assert_eq!(token.remaining(), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_corrupt_cache_is_a_miss() {
+        let dir = TempDir::new().unwrap();
+        fs::write(dir.path().join(TOKEN_CACHE_FILENAME), "not json").unwrap();
+        let cache = TokenCache::new(dir.path(), "default", "https://example.test/token");
+
+        assert!(cache.load().is_none());
+
+        cache.store(&issued("fresh", 60));
+        assert_eq!(cache.load().unwrap().access_token, "fresh");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_cache_file_is_owner_only() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new().unwrap();
+        TokenCache::new(dir.path(), "default", "https://example.test/token")
+            .store(&issued("token", 60));
+
+        let mode = fs::metadata(dir.path().join(TOKEN_CACHE_FILENAME))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    #[test]
+    fn test_clear_token_cache_by_profile_and_all() {
+        let dir = TempDir::new().unwrap();
+        TokenCache::new(dir.path(), "prod", "https://a.test/token").store(&issued("a", 3600));
+        TokenCache::new(dir.path(), "prod", "https://b.test/token").store(&issued("b", 3600));
+        TokenCache::new(dir.path(), "production", "https://a.test/token").store(&issued("c", 3600));
+
+        assert_eq!(clear_token_cache(dir.path(), Some("prod")).unwrap(), 2);
+        assert!(
+            TokenCache::new(dir.path(), "production", "https://a.test/token")
+                .load()
+                .is_some()
+        );
+
+        assert_eq!(clear_token_cache(dir.path(), None).unwrap(), 1);
+        assert!(!dir.path().join(TOKEN_CACHE_FILENAME).exists());
+        assert_eq!(clear_token_cache(dir.path(), None).unwrap(), 0);
+    }
+
+    #[test]
+    fn test_for_config_reuses_client_credentials_tokens_only_on_opt_in() {
+        let dir = TempDir::new().unwrap();
+        let config_path = dir.path().join("hyperview.toml");
+        let mut config = AppConfig {
+            token_url: "https://example.test/token".to_string(),
+            profile: "default".to_string(),
+            ..Default::default()
+        };
+        TokenCache::new(dir.path(), "default", &config.token_url).store(&issued("cc", 3600));
+
+        let cache = TokenCache::for_config(Some(&config_path), &config).unwrap();
+        assert_eq!(cache.path, dir.path().join(TOKEN_CACHE_FILENAME));
+        assert_eq!(cache.key, "default|https://example.test/token");
+        assert!(cache.load().is_none());
+
+        config.token_cache = true;
+        let cache = TokenCache::for_config(Some(&config_path), &config).unwrap();
+        assert_eq!(cache.load().unwrap().access_token, "cc");
+
+        assert!(TokenCache::for_config(None, &config).is_none());
+    }
+
+    #[test]
+    fn test_login_session_is_loaded_without_opt_in_and_survives_invalidate() {
+        let dir = TempDir::new().unwrap();
+        let cache = TokenCache {
+            reuse_access_tokens: false,
+            ..TokenCache::new(dir.path(), "me", "https://example.test/token")
+        };
+        cache.store(&IssuedToken {
+            refresh_token: Some("refresh".to_string()),
+            ..issued("session_access", 3600)
+        });
+
+        assert_eq!(cache.load().unwrap().access_token, "session_access");
+
+        cache.invalidate();
+        let token = cache.load().unwrap();
+        assert_eq!(token.remaining(), Duration::ZERO);
+        assert_eq!(token.refresh_token.as_deref(), Some("refresh"));
+
+        cache.remove();
+        assert!(cache.load().is_none());
+    }
+
+    #[test]
+    fn test_concurrent_stores_leave_a_readable_cache() {
+        let dir = TempDir::new().unwrap();
+
+        std::thread::scope(|scope| {
+            for i in 0..8 {
+                let dir = dir.path();
+                scope.spawn(move || {
+                    let cache =
+                        TokenCache::new(dir, &format!("p{i}"), "https://example.test/token");
+                    for _ in 0..10 {
+                        cache.store(&issued(&format!("token{i}"), 3600));
+                    }
+                });
+            }
+        });
+
+        let contents = fs::read_to_string(dir.path().join(TOKEN_CACHE_FILENAME)).unwrap();
+        assert!(serde_json::from_str::<BTreeMap<String, CachedToken>>(&contents).is_ok());
+        let leftovers = fs::read_dir(dir.path()).unwrap().count();
+        assert_eq!(leftovers, 1);
+    }
+
+    #[test]
+    fn test_invalidate_without_entry_does_not_create_file() {
+        let dir = TempDir::new().unwrap();
+        TokenCache::new(dir.path(), "default", "https://example.test/token").invalidate();
+
+        assert!(!dir.path().join(TOKEN_CACHE_FILENAME).exists());
+    }
+}
@@ -0,0 +1,43 @@
+//! The Hyperview API client behind the `hvcli` command line tool, for Rust programs that work
+//! with Hyperview asset data directly.
+//!
+//! Load a profile, fetch a token and build a [`HyperviewClient`]; the functions in the
+//! `*_functions` modules then take the client and plain query types such as
+//! [`AssetSearch`]:
+//!
+//! ```no_run
+//! use hvcli::{AppConfig, AssetSearch, AuthToken, HyperviewClient};
+//! use hvcli::hyperview::{asset_api_data::AssetTypes, asset_api_functions::search_assets_async};
+//!
+//! # async fn run() -> color_eyre::Result<()> {
+//! let config = AppConfig {
+//!     client_id: "my-client".to_string(),
+//!     client_secret: "my-secret".to_string(),
+//!     scope: "HyperviewManagerApi".to_string(),
+//!     auth_url: "https://example.hyperviewhq.com/connect/authorize".to_string(),
+//!     token_url: "https://example.hyperviewhq.com/connect/token".to_string(),
+//!     instance_url: "https://example.hyperviewhq.com".to_string(),
+//!     ..Default::default()
+//! };
+//! let token = AuthToken::fetch_cached_async(&config, None).await?;
+//! let client = HyperviewClient::new(config, token)?;
+//!
+//! let racks = search_assets_async(&client, &AssetSearch::new().asset_type(AssetTypes::Rack)).await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The command line parsing and routing are internal to the `hvcli` binary, which calls
+//! [`run_async`].
+
+pub mod hyperview;
+
+pub use hyperview::{
+    api_client::HyperviewClient,
+    app_errors::{ApiError, AppError},
+    asset_api_data::AssetSearch,
+    auth::AuthToken,
+    cli_functions::run_async,
+    config_data::AppConfig,
+    sensor_report_data::SensorReportQuery,
+};